@@ -0,0 +1,51 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rocks_tables::{Db, IngestBuffer};
+use std::{env::temp_dir, fs::remove_dir_all};
+
+const COUNT: u64 = 10_000;
+
+fn ingest(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ingest");
+    group.throughput(Throughput::Elements(COUNT));
+
+    let path = temp_dir().join("rocks-tables-bench-ingest");
+    let _ = remove_dir_all(&path);
+
+    {
+        let db = Db::<u64>::open(&path).unwrap();
+
+        // one write batch per key.
+        group.bench_function("put", |b| {
+            b.iter(|| {
+                for k in 0..COUNT {
+                    db.put(&k, &k).unwrap();
+                }
+            })
+        });
+
+        // the keys are committed in groups.
+        for &max_bytes in &[64usize << 10, 4 << 20] {
+            group.bench_with_input(
+                BenchmarkId::new("ingest_buffer", max_bytes),
+                &max_bytes,
+                |b, &max_bytes| {
+                    b.iter(|| {
+                        let mut buffer = IngestBuffer::new(db.clone()).max_bytes(max_bytes);
+
+                        for k in 0..COUNT {
+                            buffer.put(&k, &k).unwrap();
+                        }
+
+                        buffer.flush().unwrap();
+                    })
+                },
+            );
+        }
+    }
+
+    let _ = remove_dir_all(&path);
+    group.finish();
+}
+
+criterion_group!(benches, ingest);
+criterion_main!(benches);
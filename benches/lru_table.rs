@@ -0,0 +1,38 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rocks_tables::{Db, LruTable};
+use std::{env::temp_dir, fs::remove_dir_all};
+
+fn eviction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lru_table_eviction");
+
+    for &capacity in &[1_000usize, 10_000, 100_000] {
+        let path = temp_dir().join(format!("rocks-tables-bench-lru-{}", capacity));
+        let _ = remove_dir_all(&path);
+
+        {
+            let db = Db::<u64>::open(&path).unwrap();
+            let mut table = LruTable::<u64, u64>::with_capacity(db, capacity);
+
+            for k in 0..(capacity as u64 * 2) {
+                table.put(&k, k).unwrap();
+            }
+
+            let mut k = 0;
+
+            // every get is a miss at capacity, forcing an eviction.
+            group.bench_with_input(BenchmarkId::from_parameter(capacity), &capacity, |b, _| {
+                b.iter(|| {
+                    k = (k + 1) % (capacity as u64 * 2);
+                    table.get(&k).unwrap();
+                })
+            });
+        }
+
+        let _ = remove_dir_all(&path);
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, eviction);
+criterion_main!(benches);
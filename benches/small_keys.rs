@@ -0,0 +1,46 @@
+//! Gets and puts of small keys / values, dominated by their serialization.
+//!
+//! To compare with the allocation of a buffer per operation, save a baseline on the commit
+//! preceding the thread-local buffers (`cargo bench --bench small_keys -- --save-baseline alloc`),
+//! then run `cargo bench --bench small_keys -- --baseline alloc` on the current tree.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use rocks_tables::Db;
+use std::{env::temp_dir, fs::remove_dir_all};
+
+const COUNT: u64 = 1_000;
+
+fn small_keys(c: &mut Criterion) {
+    let mut group = c.benchmark_group("small_keys");
+    group.throughput(Throughput::Elements(COUNT));
+
+    let path = temp_dir().join("rocks-tables-bench-small-keys");
+    let _ = remove_dir_all(&path);
+
+    {
+        let db = Db::<(u32, u64)>::open(&path).unwrap();
+
+        group.bench_function("put", |b| {
+            b.iter(|| {
+                for k in 0..COUNT {
+                    db.put(&(7, k), &k).unwrap();
+                }
+            })
+        });
+
+        // every key was written by the puts above.
+        group.bench_function("get", |b| {
+            b.iter(|| {
+                for k in 0..COUNT {
+                    db.get(&(7, k)).unwrap();
+                }
+            })
+        });
+    }
+
+    let _ = remove_dir_all(&path);
+    group.finish();
+}
+
+criterion_group!(benches, small_keys);
+criterion_main!(benches);
@@ -0,0 +1,56 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Error, Fields, GenericParam};
+
+/// Derives `rocks_tables::MinValue` for a struct, using the minimum value of each field.
+#[proc_macro_derive(MinValue)]
+pub fn derive_min_value(input: TokenStream) -> TokenStream {
+    let mut input = parse_macro_input!(input as DeriveInput);
+
+    let body = match &input.data {
+        Data::Struct(data) => min_fields(&data.fields),
+        _ => {
+            return Error::new_spanned(&input.ident, "MinValue can only be derived for structs")
+                .to_compile_error()
+                .into()
+        }
+    };
+
+    for param in &mut input.generics.params {
+        if let GenericParam::Type(t) = param {
+            t.bounds.push(parse_quote!(::rocks_tables::MinValue));
+        }
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let expanded = quote! {
+        impl #impl_generics ::rocks_tables::MinValue for #name #ty_generics #where_clause {
+            #[inline]
+            fn min_value() -> Self {
+                Self #body
+            }
+        }
+    };
+
+    expanded.into()
+}
+
+fn min_fields(fields: &Fields) -> TokenStream2 {
+    match fields {
+        Fields::Named(fields) => {
+            let names = fields.named.iter().map(|f| &f.ident);
+            quote!({ #(#names: ::rocks_tables::MinValue::min_value()),* })
+        }
+        Fields::Unnamed(fields) => {
+            let values = fields
+                .unnamed
+                .iter()
+                .map(|_| quote!(::rocks_tables::MinValue::min_value()));
+            quote!((#(#values),*))
+        }
+        Fields::Unit => TokenStream2::new(),
+    }
+}
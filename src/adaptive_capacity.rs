@@ -0,0 +1,76 @@
+/// The number of lookups over which the hit rate is measured.
+const WINDOW: u32 = 1000;
+
+/// Below this hit rate, a table that evicted during the window grows.
+const GROW_BELOW: f64 = 0.9;
+
+/// From this hit rate, a table shrinks.
+const SHRINK_FROM: f64 = 0.99;
+
+/// Tunes the capacity of a table from its hit rate over a sliding window of lookups.
+///
+/// The capacity grows by a quarter when the hit rate is low while entries are evicted,
+/// and shrinks by a quarter when almost every lookup is a hit, within `min..=max`.
+pub(crate) struct AdaptiveCapacity {
+    evictions: u32,
+    hits: u32,
+    lookups: u32,
+    max: usize,
+    min: usize,
+}
+
+impl AdaptiveCapacity {
+    pub fn new(min: usize, max: usize) -> Self {
+        assert!(min > 0 && min <= max);
+
+        Self {
+            evictions: 0,
+            hits: 0,
+            lookups: 0,
+            max,
+            min,
+        }
+    }
+
+    pub fn eviction(&mut self) {
+        self.evictions = self.evictions.saturating_add(1);
+    }
+
+    pub fn lookup(&mut self, hit: bool) {
+        self.lookups += 1;
+
+        if hit {
+            self.hits += 1;
+        }
+    }
+
+    /// Returns the capacity to switch to at the end of a window, if it changes.
+    pub fn next_capacity(&mut self, capacity: usize) -> Option<usize> {
+        if self.lookups < WINDOW {
+            return None;
+        }
+
+        let rate = f64::from(self.hits) / f64::from(self.lookups);
+        let evicted = self.evictions > 0;
+
+        self.evictions = 0;
+        self.hits = 0;
+        self.lookups = 0;
+
+        let new = if rate < GROW_BELOW && evicted {
+            capacity.saturating_add(capacity / 4).max(capacity + 1)
+        } else if rate >= SHRINK_FROM {
+            capacity - capacity / 4
+        } else {
+            capacity
+        };
+
+        let new = new.clamp(self.min, self.max);
+
+        if new == capacity {
+            None
+        } else {
+            Some(new)
+        }
+    }
+}
@@ -1,4 +1,75 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering::Relaxed},
+        Arc,
+    },
+};
+
 pub(crate) struct Aged<V> {
     pub age: u64,
     pub value: V,
 }
+
+/// Keeps the keys ordered by age, so the least recently used key is found in O(log n).
+///
+/// An age of 0 means that the entry is not yet indexed.
+pub(crate) struct AgeIndex<K> {
+    age: u64,
+    clock: Option<Arc<AtomicU64>>,
+    keys: BTreeMap<u64, K>,
+}
+
+impl<K> AgeIndex<K> {
+    pub fn new() -> Self {
+        Self {
+            age: 0,
+            clock: None,
+            keys: BTreeMap::new(),
+        }
+    }
+
+    /// Creates an index taking its ages from a clock shared with other indexes,
+    /// so the ages of their entries can be compared.
+    pub fn with_clock(clock: Arc<AtomicU64>) -> Self {
+        Self {
+            clock: Some(clock),
+            ..Self::new()
+        }
+    }
+
+    /// Returns the least recently used key.
+    pub fn oldest(&self) -> Option<&K> {
+        self.keys.values().next()
+    }
+
+    /// Returns the age of the least recently used key.
+    pub fn oldest_age(&self) -> Option<u64> {
+        self.keys.keys().next().copied()
+    }
+
+    /// Removes the entry of the specified age from the index.
+    pub fn remove(&mut self, age: u64) {
+        if age != 0 {
+            self.keys.remove(&age);
+        }
+    }
+
+    /// Marks the entry as the most recently used, updating its age.
+    pub fn touch<Q: ?Sized>(&mut self, key: &Q, age: &mut u64)
+    where
+        Q: ToOwned<Owned = K>,
+    {
+        self.remove(*age);
+
+        *age = match &self.clock {
+            Some(clock) => clock.fetch_add(1, Relaxed) + 1,
+            None => {
+                self.age += 1;
+                self.age
+            }
+        };
+
+        self.keys.insert(*age, key.to_owned());
+    }
+}
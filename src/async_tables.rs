@@ -0,0 +1,201 @@
+use crate::{lru_table::lock, Db, Error, IteratorMode, LruTable, MemTable, Result, UpdateFrom};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fmt::Debug,
+    hash::{BuildHasher, Hash},
+    sync::{Arc, Mutex},
+};
+
+/// Runs a blocking RocksDB operation on the tokio blocking thread pool.
+async fn blocking<F, T>(f: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(f).await.map_err(Error::Join)?
+}
+
+/// An async facade over a [`Db`], offloading each operation to `spawn_blocking`.
+pub struct AsyncDb<K> {
+    db: Arc<Db<K>>,
+}
+
+impl<K> AsyncDb<K>
+where
+    K: Debug + DeserializeOwned + Serialize + Send + Sync + 'static,
+{
+    pub fn new(db: Db<K>) -> Self {
+        Self { db: Arc::new(db) }
+    }
+
+    pub async fn contains_key(&self, key: K) -> Result<bool> {
+        let db = Arc::clone(&self.db);
+        blocking(move || db.contains_key(&key)).await
+    }
+
+    pub async fn delete(&self, key: K) -> Result<()> {
+        let db = Arc::clone(&self.db);
+        blocking(move || db.delete(&key)).await
+    }
+
+    pub async fn get<V>(&self, key: K) -> Result<Option<V>>
+    where
+        V: DeserializeOwned + Send + 'static,
+    {
+        let db = Arc::clone(&self.db);
+
+        blocking(move || match db.get(&key)? {
+            Some(v) => Ok(Some(v.to_inner()?)),
+            None => Ok(None),
+        })
+        .await
+    }
+
+    /// Reads all the key / values of the iterator mode.
+    ///
+    /// The records are collected on the blocking thread since the iterator cannot be sent across threads.
+    pub async fn iter<V>(&self, mode: IteratorMode<K>) -> Result<Vec<(K, V)>>
+    where
+        V: DeserializeOwned + Send + 'static,
+    {
+        let db = Arc::clone(&self.db);
+
+        blocking(move || {
+            let mut iter = db.iter(mode)?;
+            let mut vec = Vec::new();
+
+            while let Some(kv) = iter.next()? {
+                vec.push((kv.key()?, kv.value()?));
+            }
+
+            Ok(vec)
+        })
+        .await
+    }
+
+    pub async fn put<V>(&self, key: K, value: V) -> Result<()>
+    where
+        V: Serialize + Send + 'static,
+    {
+        let db = Arc::clone(&self.db);
+        blocking(move || db.put(&key, &value)).await
+    }
+}
+
+impl<K> Clone for AsyncDb<K> {
+    fn clone(&self) -> Self {
+        Self {
+            db: Arc::clone(&self.db),
+        }
+    }
+}
+
+/// An async facade over a [`LruTable`] shared behind a mutex.
+///
+/// Values are cloned out of the table since the lock is released before returning.
+pub struct AsyncLruTable<K, V, S> {
+    table: Arc<Mutex<LruTable<K, V, S>>>,
+}
+
+impl<K, V, S> AsyncLruTable<K, V, S>
+where
+    K: Clone + Debug + DeserializeOwned + Eq + Hash + Serialize + Send + 'static,
+    V: Clone + DeserializeOwned + Serialize + Send + 'static,
+    S: BuildHasher + Send + 'static,
+{
+    pub fn new(table: LruTable<K, V, S>) -> Self {
+        Self {
+            table: Arc::new(Mutex::new(table)),
+        }
+    }
+
+    pub async fn contains_key(&self, key: K) -> Result<bool> {
+        let table = Arc::clone(&self.table);
+        blocking(move || lock(&table).contains_key(&key)).await
+    }
+
+    pub async fn delete(&self, key: K) -> Result<()> {
+        let table = Arc::clone(&self.table);
+        blocking(move || lock(&table).delete(&key)).await
+    }
+
+    pub async fn get(&self, key: K) -> Result<Option<V>> {
+        let table = Arc::clone(&self.table);
+        blocking(move || Ok(lock(&table).get(&key)?.cloned())).await
+    }
+
+    pub async fn put(&self, key: K, value: V) -> Result<()> {
+        let table = Arc::clone(&self.table);
+        blocking(move || lock(&table).put(&key, value)).await
+    }
+
+    pub async fn update<U>(&self, key: K, update: U) -> Result<()>
+    where
+        U: UpdateFrom<V> + Send + 'static,
+    {
+        let table = Arc::clone(&self.table);
+        blocking(move || lock(&table).update(&key, update)).await
+    }
+}
+
+impl<K, V, S> Clone for AsyncLruTable<K, V, S> {
+    fn clone(&self) -> Self {
+        Self {
+            table: Arc::clone(&self.table),
+        }
+    }
+}
+
+/// An async facade over a [`MemTable`] shared behind a mutex.
+///
+/// Reads are served from memory without blocking; writes are offloaded to `spawn_blocking`.
+pub struct AsyncMemTable<K, V, S> {
+    table: Arc<Mutex<MemTable<K, V, S>>>,
+}
+
+impl<K, V, S> AsyncMemTable<K, V, S>
+where
+    K: Clone + Debug + DeserializeOwned + Eq + Hash + Serialize + Send + 'static,
+    V: Clone + DeserializeOwned + Serialize + Send + 'static,
+    S: BuildHasher + Send + 'static,
+{
+    pub fn new(table: MemTable<K, V, S>) -> Self {
+        Self {
+            table: Arc::new(Mutex::new(table)),
+        }
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        lock(&self.table).contains_key(key)
+    }
+
+    pub async fn delete(&self, key: K) -> Result<Option<V>> {
+        let table = Arc::clone(&self.table);
+        blocking(move || lock(&table).delete(&key)).await
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        lock(&self.table).get(key).cloned()
+    }
+
+    pub async fn put(&self, key: K, value: V) -> Result<Option<V>> {
+        let table = Arc::clone(&self.table);
+        blocking(move || lock(&table).put(&key, value)).await
+    }
+
+    pub async fn update<U>(&self, key: K, update: U) -> Result<()>
+    where
+        U: UpdateFrom<V> + Send + 'static,
+    {
+        let table = Arc::clone(&self.table);
+        blocking(move || lock(&table).update(key, update)).await
+    }
+}
+
+impl<K, V, S> Clone for AsyncMemTable<K, V, S> {
+    fn clone(&self) -> Self {
+        Self {
+            table: Arc::clone(&self.table),
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use crate::{
+    db::log_err, ChangeEvent, ChangeKind, Db, DecodedIter, Direction, IteratorMode, Result,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU64, Ordering::Relaxed},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+type Hasher = Arc<dyn Fn(&[u8]) -> Vec<u8> + Send + Sync>;
+
+/// The key of an [`AuditEntry`], ordering the entries by time of write.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct AuditKey {
+    /// The time of the write, in microseconds since the unix epoch.
+    pub timestamp: u64,
+    /// Orders the entries written in the same microsecond.
+    pub seq: u64,
+}
+
+/// A write mirrored by an [`AuditLog`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AuditEntry {
+    /// The name of the audited database.
+    pub db: String,
+    pub kind: ChangeKind,
+    /// The serialized key, empty for a [`ChangeKind::Clear`].
+    pub key: Vec<u8>,
+    /// The serialized end, excluded, of a [`ChangeKind::DeleteRange`].
+    pub range_end: Option<Vec<u8>>,
+    /// The hash of the value of a put or of the operand of a merge, when the log hashes the values.
+    ///
+    /// The hash of the value before a put is the one of the previous put of the key.
+    pub value_hash: Option<Vec<u8>>,
+}
+
+/// An append-only log of the writes on one or more databases, kept for compliance.
+///
+/// The entries are written in their own database, usually a column family dedicated to the
+/// audit, on the thread writing the audited database. The values themselves are never
+/// copied: only their hash is recorded, with the hasher given to [`with_hasher`](Self::with_hasher).
+/// The entries older than the [retention](Self::with_retention) are removed by [`purge`](Self::purge).
+#[derive(Clone)]
+pub struct AuditLog {
+    db: Db<AuditKey>,
+    hasher: Option<Hasher>,
+    retention: Option<Duration>,
+    seq: Arc<AtomicU64>,
+}
+
+impl AuditLog {
+    /// Creates a log appending its entries to `db`, which must not be audited itself.
+    pub fn new(db: Db<AuditKey>) -> Self {
+        Self {
+            db,
+            hasher: None,
+            retention: None,
+            seq: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Records the hash of the written values computed by `f`, such as a SHA-256 digest.
+    pub fn with_hasher<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&[u8]) -> Vec<u8> + Send + Sync + 'static,
+    {
+        self.hasher = Some(Arc::new(f));
+        self
+    }
+
+    /// Keeps the entries for `retention` before they are removed by [`purge`](Self::purge).
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = Some(retention);
+        self
+    }
+
+    /// Mirrors the writes made on `source` from now on.
+    pub fn audit<K>(&self, source: &Db<K>)
+    where
+        K: 'static,
+    {
+        let log = self.clone();
+        let name = source.name().to_owned();
+
+        source.subscribe(move |event| {
+            if let Err(e) = log.append(&name, event) {
+                log_err(e, log.db.name());
+            }
+        });
+    }
+
+    /// Returns the database of the entries.
+    pub fn db(&self) -> &Db<AuditKey> {
+        &self.db
+    }
+
+    /// Iterates over the entries written since `time`, oldest first.
+    pub fn iter_since(&self, time: SystemTime) -> Result<DecodedIter<AuditKey, AuditEntry>> {
+        let from = AuditKey {
+            timestamp: micros(time),
+            seq: 0,
+        };
+
+        self.db
+            .iter_decoded(IteratorMode::From(from, Direction::Forward))
+    }
+
+    /// Removes the entries older than the retention, if any.
+    pub fn purge(&self) -> Result<()> {
+        match self.retention {
+            Some(retention) => self.purge_before(SystemTime::now() - retention),
+            None => Ok(()),
+        }
+    }
+
+    /// Removes the entries written before `time`.
+    pub fn purge_before(&self, time: SystemTime) -> Result<()> {
+        let from = AuditKey {
+            timestamp: 0,
+            seq: 0,
+        };
+
+        let to = AuditKey {
+            timestamp: micros(time),
+            seq: 0,
+        };
+
+        self.db.delete_range(&from, &to)
+    }
+
+    fn append<K>(&self, db: &str, event: &ChangeEvent<K>) -> Result<()> {
+        let kind = event.kind();
+        let value = event.value_bytes();
+
+        let (range_end, value_hash) = match kind {
+            ChangeKind::DeleteRange => (value.map(|v| v.to_vec()), None),
+            ChangeKind::Merge | ChangeKind::Put => (None, self.hash(value)),
+            ChangeKind::Clear | ChangeKind::Delete => (None, None),
+        };
+
+        let key = AuditKey {
+            timestamp: micros(SystemTime::now()),
+            seq: self.seq.fetch_add(1, Relaxed),
+        };
+
+        let entry = AuditEntry {
+            db: db.to_owned(),
+            kind,
+            key: event.key_bytes().to_vec(),
+            range_end,
+            value_hash,
+        };
+
+        self.db.put(&key, &entry)
+    }
+
+    fn hash(&self, value: Option<&[u8]>) -> Option<Vec<u8>> {
+        self.hasher.as_ref().map(|f| f(value.unwrap_or_default()))
+    }
+}
+
+impl Debug for AuditLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("AuditLog")
+            .field("db", &self.db.name())
+            .field("retention", &self.retention)
+            .finish()
+    }
+}
+
+fn micros(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_micros() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AuditEntry, AuditLog};
+    use crate::{key_ser::key_to_bytes, serialize_to_bytes, ChangeKind, Db};
+    use std::{
+        thread::sleep,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
+
+    fn entries(log: &AuditLog) -> Vec<AuditEntry> {
+        log.iter_since(UNIX_EPOCH)
+            .unwrap()
+            .map(|r| r.unwrap().1)
+            .collect()
+    }
+
+    #[test]
+    fn mirrors_writes() {
+        let source = Db::<u32>::open_temporary().unwrap();
+        let log = AuditLog::new(Db::open_temporary().unwrap()).with_hasher(|v| v.to_vec());
+
+        log.audit(&source);
+        source.put(&1, &"a".to_string()).unwrap();
+        source.delete(&1).unwrap();
+        source.delete_range(&2, &5).unwrap();
+
+        let entries = entries(&log);
+        let kinds = entries.iter().map(|e| e.kind).collect::<Vec<_>>();
+        let key = |k: u32| key_to_bytes(&k).unwrap();
+
+        assert_eq!(
+            kinds,
+            vec![ChangeKind::Put, ChangeKind::Delete, ChangeKind::DeleteRange]
+        );
+
+        assert!(entries.iter().all(|e| e.db == source.name()));
+        assert_eq!(entries[0].key, key(1));
+        assert_eq!(
+            entries[0].value_hash,
+            Some(serialize_to_bytes(&"a".to_string()).unwrap())
+        );
+        assert_eq!(entries[1].key, key(1));
+        assert_eq!(entries[1].value_hash, None);
+        assert_eq!(entries[2].key, key(2));
+        assert_eq!(entries[2].range_end, Some(key(5)));
+    }
+
+    #[test]
+    fn purge_respects_retention() {
+        let source = Db::<u32>::open_temporary().unwrap();
+        let log = AuditLog::new(Db::open_temporary().unwrap());
+
+        log.audit(&source);
+        source.put(&1, &()).unwrap();
+        sleep(Duration::from_millis(2));
+
+        let time = SystemTime::now();
+        sleep(Duration::from_millis(2));
+        source.put(&2, &()).unwrap();
+
+        log.purge_before(time).unwrap();
+
+        let keys = entries(&log).into_iter().map(|e| e.key).collect::<Vec<_>>();
+        assert_eq!(keys, vec![key_to_bytes(&2u32).unwrap()]);
+
+        // the entries within the retention are kept.
+        let log = log.with_retention(Duration::from_secs(3600));
+        log.purge().unwrap();
+        assert_eq!(entries(&log).len(), 1);
+
+        sleep(Duration::from_millis(2));
+        let expired = log.clone().with_retention(Duration::from_millis(1));
+        expired.purge().unwrap();
+        assert!(entries(&log).is_empty());
+    }
+}
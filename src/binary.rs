@@ -1,4 +1,4 @@
-use crate::{deserialize_from_bytes, serialize_to_bytes, Encrypt, Result};
+use crate::{deserialize_from_bytes, serialize_to_bytes, Compress, Encrypt, Result};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 enum Data<'a> {
@@ -153,3 +153,55 @@ impl<'a> Serialize for Crypted<'a> {
         self.0.serialize(serializer)
     }
 }
+
+/// A value compressed by a [`Compress`] implementation, independently of the block compression.
+pub struct Compressed<'a>(Data<'a>);
+
+impl<'a> Compressed<'a> {
+    pub fn with_ref<T, C>(value: &T, compressor: &C) -> Result<Compressed<'static>>
+    where
+        C: Compress,
+        T: Serialize,
+    {
+        let bytes = serialize_to_bytes(value)?;
+        let bytes = compressor.compress(&bytes)?;
+        Ok(Compressed(Data::Owned(bytes)))
+    }
+
+    pub fn as_ref(&self) -> Compressed {
+        Compressed(self.0.as_ref())
+    }
+
+    pub fn to_inner<'de, T, C>(&self, compressor: &C, temp: &'de mut Vec<u8>) -> Result<T>
+    where
+        C: Compress,
+        T: Deserialize<'de>,
+    {
+        *temp = compressor.decompress(self.0.as_bytes())?;
+        deserialize_from_bytes(temp)
+    }
+
+    pub fn to_owned(&self) -> Compressed<'static> {
+        Compressed(self.0.to_owned())
+    }
+}
+
+impl<'a, 'de: 'a> Deserialize<'de> for Compressed<'a> {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Ok(Self(Data::deserialize(deserializer)?))
+    }
+}
+
+impl<'a> Serialize for Compressed<'a> {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
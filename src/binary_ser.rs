@@ -3,10 +3,11 @@ use bincode::{
     config::{BigEndian, WithOtherEndian},
     Options,
 };
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeSeed, Deserialize, Serialize};
 
 fn bin_opts() -> WithOtherEndian<bincode::DefaultOptions, BigEndian> {
-    // serializing keys in big endian to preserve sorting order when iterating the db.
+    // the values and the metadata are serialized in big endian, the keys having their own
+    // order-preserving encoding in `key_ser`.
     bincode::options().with_big_endian()
 }
 
@@ -15,7 +16,24 @@ pub(super) fn deserialize_from_bytes<'a, T: Deserialize<'a>>(bytes: &'a [u8]) ->
     bin_opts().deserialize(bytes).map_err(Error::Serde)
 }
 
+/// Deserializes the bytes with a seed, such as a field of a struct being deserialized.
 #[inline]
-pub(super) fn serialize_to_bytes<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+pub(super) fn deserialize_seed<'a, S: DeserializeSeed<'a>>(
+    seed: S,
+    bytes: &'a [u8],
+) -> Result<S::Value> {
+    bin_opts()
+        .deserialize_seed(seed, bytes)
+        .map_err(Error::Serde)
+}
+
+#[inline]
+pub(super) fn serialize_to_bytes<T: ?Sized + Serialize>(value: &T) -> Result<Vec<u8>> {
     bin_opts().serialize(value).map_err(Error::Serde)
 }
+
+/// Appends the serialized value to `out`, reusing its allocation.
+#[inline]
+pub(super) fn serialize_into<T: ?Sized + Serialize>(value: &T, out: &mut Vec<u8>) -> Result<()> {
+    bin_opts().serialize_into(out, value).map_err(Error::Serde)
+}
@@ -0,0 +1,214 @@
+use crate::{Db, IterOptions, IteratorMode, RawBatch, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    mem::replace,
+    sync::{Mutex, MutexGuard},
+};
+
+const BATCH_LEN: usize = 1000;
+
+/// The part of a blob stored under a key, after its hash.
+const DATA: u8 = 0;
+const REFS: u8 = 1;
+
+/// The BLAKE3 hash of the content of a blob, identifying it in a [`BlobStore`].
+#[derive(Clone, Copy, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct BlobHash([u8; 32]);
+
+impl BlobHash {
+    /// Computes the hash of `bytes`.
+    pub fn of(bytes: &[u8]) -> Self {
+        Self(*blake3::hash(bytes).as_bytes())
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl From<[u8; 32]> for BlobHash {
+    #[inline]
+    fn from(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl fmt::Debug for BlobHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for BlobHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.iter().try_for_each(|b| write!(f, "{:02x}", b))
+    }
+}
+
+/// A store of blobs addressed by the hash of their content, so a content inserted many times,
+/// such as an attachment, is stored once.
+///
+/// Each insertion adds a reference to the blob, removed by [`BlobStore::release`]. The
+/// blobs without references are kept until [`BlobStore::gc`] removes them, so a blob
+/// released then inserted again is not rewritten.
+pub struct BlobStore {
+    db: Db<(BlobHash, u8)>,
+    /// Serializes the updates of the reference counts.
+    lock: Mutex<()>,
+}
+
+impl BlobStore {
+    pub fn new(db: Db<(BlobHash, u8)>) -> Self {
+        Self {
+            db,
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn contains(&self, hash: &BlobHash) -> Result<bool> {
+        self.db.contains_key(&(*hash, DATA))
+    }
+
+    /// Returns the content of a blob, even if it is no longer referenced.
+    pub fn get(&self, hash: &BlobHash) -> Result<Option<Vec<u8>>> {
+        match self.db.get(&(*hash, DATA))? {
+            Some(value) => Ok(Some(value.to_inner()?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Removes the blobs without references, returning the number of blobs removed.
+    pub fn gc(&self) -> Result<u64> {
+        let _guard = self.lock();
+        let mut batch = RawBatch::new();
+        let mut count = 0;
+        let mut iter = self
+            .db
+            .iter_opt(IteratorMode::Start, &IterOptions::bulk_scan())?;
+
+        while let Some(item) = iter.next()? {
+            let (hash, part) = item.key()?;
+
+            if part == REFS && item.value::<u64>()? == 0 {
+                self.db.batch_delete(&mut batch, &(hash, DATA))?;
+                self.db.batch_delete(&mut batch, &(hash, REFS))?;
+                count += 1;
+
+                if batch.len() >= BATCH_LEN {
+                    self.db.write_raw(replace(&mut batch, RawBatch::new()))?;
+                }
+            }
+        }
+
+        if !batch.is_empty() {
+            self.db.write_raw(batch)?;
+        }
+
+        Ok(count)
+    }
+
+    /// Stores `bytes` if its content is not already stored and adds a reference to it.
+    pub fn insert(&self, bytes: &[u8]) -> Result<BlobHash> {
+        let hash = BlobHash::of(bytes);
+        let _guard = self.lock();
+        let refs = self.ref_count(&hash)?;
+        let mut batch = RawBatch::new();
+
+        if !self.contains(&hash)? {
+            self.db.batch_put(&mut batch, &(hash, DATA), &bytes)?;
+        }
+
+        self.db.batch_put(&mut batch, &(hash, REFS), &(refs + 1))?;
+        self.db.write_raw(batch)?;
+        Ok(hash)
+    }
+
+    fn lock(&self) -> MutexGuard<()> {
+        self.lock.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns the number of references to a blob, 0 if it is missing.
+    pub fn ref_count(&self, hash: &BlobHash) -> Result<u64> {
+        match self.db.get(&(*hash, REFS))? {
+            Some(value) => value.to_inner(),
+            None => Ok(0),
+        }
+    }
+
+    /// Removes a reference to a blob, returning the number of references left.
+    pub fn release(&self, hash: &BlobHash) -> Result<u64> {
+        let _guard = self.lock();
+        let refs = self.ref_count(hash)?;
+
+        if refs == 0 {
+            return Ok(0);
+        }
+
+        self.db.put(&(*hash, REFS), &(refs - 1))?;
+        Ok(refs - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BlobHash, BlobStore, DATA};
+    use crate::Db;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering::Relaxed},
+        Arc,
+    };
+
+    #[test]
+    fn insert_dedups() {
+        let db = Db::<(BlobHash, u8)>::open_temporary().unwrap();
+        let writes = Arc::new(AtomicUsize::new(0));
+        let counted = Arc::clone(&writes);
+
+        db.subscribe(move |event| {
+            if event.key().unwrap().1 == DATA {
+                counted.fetch_add(1, Relaxed);
+            }
+        });
+
+        let store = BlobStore::new(db);
+        let hash = store.insert(b"attachment").unwrap();
+
+        assert_eq!(store.insert(b"attachment").unwrap(), hash);
+        assert_eq!(hash, BlobHash::of(b"attachment"));
+        assert_eq!(writes.load(Relaxed), 1);
+        assert_eq!(store.get(&hash).unwrap().unwrap(), b"attachment");
+    }
+
+    #[test]
+    fn ref_counts() {
+        let store = BlobStore::new(Db::open_temporary().unwrap());
+        let hash = store.insert(b"a").unwrap();
+
+        store.insert(b"a").unwrap();
+        assert_eq!(store.ref_count(&hash).unwrap(), 2);
+        assert_eq!(store.release(&hash).unwrap(), 1);
+        assert_eq!(store.release(&hash).unwrap(), 0);
+        assert_eq!(store.release(&hash).unwrap(), 0);
+
+        // a released blob is kept until collected.
+        assert!(store.contains(&hash).unwrap());
+        assert_eq!(store.ref_count(&BlobHash::of(b"missing")).unwrap(), 0);
+    }
+
+    #[test]
+    fn gc_removes_unreferenced() {
+        let store = BlobStore::new(Db::open_temporary().unwrap());
+        let kept = store.insert(b"kept").unwrap();
+        let released = store.insert(b"released").unwrap();
+
+        store.release(&released).unwrap();
+        assert_eq!(store.gc().unwrap(), 1);
+
+        assert!(store.contains(&kept).unwrap());
+        assert!(!store.contains(&released).unwrap());
+        assert_eq!(store.ref_count(&kept).unwrap(), 1);
+        assert_eq!(store.gc().unwrap(), 0);
+    }
+}
@@ -0,0 +1,300 @@
+use crate::{aged::AgeIndex, stats, Aged, Db, RawBatch, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    fmt::Debug,
+    hash::Hash,
+    sync::Arc,
+};
+
+/// A [`SectionLruTable`](crate::SectionLruTable) keeping at most a fixed number of records
+/// of each section in memory, for sections too large to be loaded entirely.
+///
+/// A section is loaded with its first records only; the least recently used records of a section
+/// are removed from memory when its capacity is reached, and the records missing from memory
+/// are read from the database one by one.
+///
+/// The number of records of each section is stored under the key of the section in its own
+/// column family, written in the same batch as the records.
+pub struct BoundedSectionLruTable<S, K, V> {
+    ages: AgeIndex<S>,
+    db: Db<(S, K)>,
+    lens: Db<S>,
+    map: HashMap<S, Aged<Section<K, V>>, RandomState>,
+    section_capacity: usize,
+}
+
+impl<S, K, V> BoundedSectionLruTable<S, K, V>
+where
+    S: for<'de> Deserialize<'de> + Clone + Debug + Eq + Hash + Serialize,
+    K: for<'de> Deserialize<'de> + Clone + Debug + Eq + Hash + Serialize,
+    V: for<'de> Deserialize<'de> + Serialize,
+{
+    /// Creates a table keeping `capacity` sections of at most `section_capacity` records in memory.
+    ///
+    /// `lens` holds the number of records of each section and must be another column family
+    /// of the database of `db`, so both are written atomically.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `lens` is not a view on the same database as `db`.
+    pub fn with_capacity(
+        db: Db<(S, K)>,
+        lens: Db<S>,
+        capacity: usize,
+        section_capacity: usize,
+    ) -> Self {
+        assert!(capacity > 0);
+        assert!(section_capacity > 0);
+        assert!(
+            Arc::ptr_eq(db.raw_db(), lens.raw_db()),
+            "the section lengths must be stored in the same database as the records"
+        );
+
+        Self {
+            ages: AgeIndex::new(),
+            db,
+            lens,
+            map: HashMap::with_capacity(capacity),
+            section_capacity,
+        }
+    }
+
+    pub fn contains_key(&self, section: S, key: &K) -> Result<bool> {
+        match self.map.get(&section) {
+            Some(aged) if aged.value.map.contains_key(key) => Ok(true),
+            _ => self.db.contains_key(&(section, key.clone())),
+        }
+    }
+
+    pub fn delete(&mut self, section: S, key: &K) -> Result<()> {
+        let db_key = (section, key.clone());
+
+        if !self.db.contains_key(&db_key)? {
+            return Ok(());
+        }
+
+        let len = self.section_len(&db_key.0)?;
+        let mut batch = RawBatch::new();
+
+        self.db.batch_delete(&mut batch, &db_key)?;
+        self.lens
+            .batch_put(&mut batch, &db_key.0, &len.saturating_sub(1))?;
+        self.db.write_raw(batch)?;
+
+        if let Some(aged) = self.map.get_mut(&db_key.0) {
+            aged.value.remove(key);
+        }
+
+        Ok(())
+    }
+
+    /// Removes a whole section from the database and from memory.
+    pub fn delete_section(&mut self, section: S) -> Result<()> {
+        let mut batch = RawBatch::new();
+
+        self.db.batch_delete_prefix(&mut batch, &section)?;
+        self.lens.batch_delete(&mut batch, &section)?;
+        self.db.write_raw(batch)?;
+
+        if let Some(aged) = self.map.remove(&section) {
+            self.ages.remove(aged.age);
+        }
+
+        Ok(())
+    }
+
+    fn ensure_capacity(&mut self) {
+        if self.map.capacity() == self.map.len() {
+            if let Some(section) = self.ages.oldest().cloned() {
+                if let Some(aged) = self.map.remove(&section) {
+                    self.ages.remove(aged.age);
+                    stats::eviction(self.db.name());
+                }
+            }
+        }
+    }
+
+    fn ensure_section_loaded(&mut self, section: &S) -> Result<&mut Section<K, V>> {
+        let hit = self.map.contains_key(section);
+        stats::cache_lookup(self.db.name(), hit);
+
+        if !hit {
+            self.ensure_capacity();
+
+            let value = Section::load(section, &self.db, self.section_capacity)?;
+            self.map.insert(section.clone(), Aged { age: 0, value });
+        }
+
+        let aged = self.map.get_mut(section).unwrap();
+        self.ages.touch(section, &mut aged.age);
+        Ok(&mut aged.value)
+    }
+
+    /// Returns the value of the key, read from the database if it is not in memory.
+    pub fn get(&mut self, section: S, key: &K) -> Result<Option<&V>> {
+        if !self.ensure_section_loaded(&section)?.map.contains_key(key) {
+            let value = match self.db.get(&(section.clone(), key.clone()))? {
+                Some(value) => value.to_inner()?,
+                None => return Ok(None),
+            };
+
+            let aged = self.map.get_mut(&section).unwrap();
+            aged.value.insert(key.clone(), value);
+        }
+
+        Ok(self.map.get_mut(&section).unwrap().value.get(key))
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the number of sections kept in memory.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn put(&mut self, section: S, key: &K, value: V) -> Result<()> {
+        let db_key = (section, key.clone());
+
+        let exists = match self.map.get(&db_key.0) {
+            Some(aged) if aged.value.map.contains_key(key) => true,
+            _ => self.db.contains_key(&db_key)?,
+        };
+
+        let mut batch = RawBatch::new();
+        self.db.batch_put(&mut batch, &db_key, &value)?;
+
+        if !exists {
+            let len = self.section_len(&db_key.0)?;
+            self.lens.batch_put(&mut batch, &db_key.0, &(len + 1))?;
+        }
+
+        self.db.write_raw(batch)?;
+
+        let (section, key) = db_key;
+        self.ensure_section_loaded(&section)?.insert(key, value);
+        Ok(())
+    }
+
+    /// Returns the number of records of a section, counted from the writes made through this table.
+    pub fn section_len(&self, section: &S) -> Result<u64> {
+        match self.lens.get(section)? {
+            Some(len) => len.to_inner(),
+            None => Ok(0),
+        }
+    }
+}
+
+/// The records of a section kept in memory, evicting the least recently used above capacity.
+struct Section<K, V> {
+    ages: AgeIndex<K>,
+    capacity: usize,
+    map: HashMap<K, Aged<V>>,
+}
+
+impl<K, V> Section<K, V>
+where
+    K: for<'de> Deserialize<'de> + Clone + Debug + Eq + Hash + Serialize,
+    V: for<'de> Deserialize<'de> + Serialize,
+{
+    /// Loads the first records of a section, up to the capacity.
+    fn load<S>(section: &S, db: &Db<(S, K)>, capacity: usize) -> Result<Self>
+    where
+        S: for<'de> Deserialize<'de> + Debug + Serialize,
+    {
+        let mut iter = db.iter_prefix_same_as_start(section)?;
+
+        let mut loaded = Self {
+            ages: AgeIndex::new(),
+            capacity,
+            map: HashMap::with_capacity(capacity),
+        };
+
+        while loaded.map.len() < capacity {
+            match iter.next()? {
+                Some(item) => {
+                    let (_, key): (S, K) = item.key()?;
+                    loaded.insert(key, item.value()?);
+                }
+                None => break,
+            }
+        }
+
+        Ok(loaded)
+    }
+
+    fn get(&mut self, key: &K) -> Option<&V> {
+        let aged = self.map.get_mut(key)?;
+        self.ages.touch(key, &mut aged.age);
+        Some(&aged.value)
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        if let Some(aged) = self.map.get_mut(&key) {
+            aged.value = value;
+            self.ages.touch(&key, &mut aged.age);
+            return;
+        }
+
+        if self.map.len() == self.capacity {
+            if let Some(oldest) = self.ages.oldest().cloned() {
+                self.remove(&oldest);
+            }
+        }
+
+        let mut aged = Aged { age: 0, value };
+        self.ages.touch(&key, &mut aged.age);
+        self.map.insert(key, aged);
+    }
+
+    fn remove(&mut self, key: &K) {
+        if let Some(aged) = self.map.remove(key) {
+            self.ages.remove(aged.age);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BoundedSectionLruTable;
+    use crate::DbHandle;
+
+    #[test]
+    fn section_lens_in_column_family() {
+        let path =
+            std::env::temp_dir().join(format!("rocks-tables-bounded-{}", std::process::id()));
+        let handle = DbHandle::open_cf(&path, &["records", "lens"]).unwrap();
+
+        let mut table = BoundedSectionLruTable::<u32, u32, String>::with_capacity(
+            handle.cf("records").unwrap(),
+            handle.cf("lens").unwrap(),
+            2,
+            2,
+        );
+
+        for key in 0..3 {
+            table.put(1, &key, key.to_string()).unwrap();
+        }
+
+        table.put(1, &0, "0".to_string()).unwrap();
+        table.put(2, &0, "0".to_string()).unwrap();
+        assert_eq!(table.section_len(&1).unwrap(), 3);
+        assert_eq!(table.section_len(&2).unwrap(), 1);
+
+        table.delete(1, &2).unwrap();
+        assert_eq!(table.section_len(&1).unwrap(), 2);
+        assert_eq!(table.get(1, &2).unwrap(), None);
+
+        table.delete_section(1).unwrap();
+        assert_eq!(table.section_len(&1).unwrap(), 0);
+        assert_eq!(table.section_len(&2).unwrap(), 1);
+
+        drop(table);
+        drop(handle);
+        std::fs::remove_dir_all(&path).ok();
+    }
+}
@@ -0,0 +1,39 @@
+//! Thread-local buffers reused to serialize the keys / values, sparing an allocation per operation.
+
+use std::cell::RefCell;
+
+/// The buffers larger than this are dropped instead of being kept for reuse.
+const MAX_CAPACITY: usize = 64 << 10;
+
+/// The number of buffers kept per thread, enough for a key and a value and a nested call.
+const MAX_BUFFERS: usize = 4;
+
+thread_local! {
+    static BUFFERS: RefCell<Vec<Vec<u8>>> = RefCell::new(Vec::new());
+}
+
+/// Calls `f` with an empty buffer, returned to the thread pool afterwards.
+///
+/// The calls can be nested, each one getting its own buffer.
+pub(crate) fn with_buf<F, R>(f: F) -> R
+where
+    F: FnOnce(&mut Vec<u8>) -> R,
+{
+    let mut buf = BUFFERS.with(|b| b.borrow_mut().pop()).unwrap_or_default();
+
+    let out = f(&mut buf);
+
+    if buf.capacity() <= MAX_CAPACITY {
+        buf.clear();
+
+        BUFFERS.with(|b| {
+            let mut b = b.borrow_mut();
+
+            if b.len() < MAX_BUFFERS {
+                b.push(buf);
+            }
+        });
+    }
+
+    out
+}
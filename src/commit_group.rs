@@ -0,0 +1,299 @@
+use crate::{
+    db::{log_err, map_log_err},
+    Db, DbHandle, Error, IteratorMode, Result, Transaction, WriteMode,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::trace_span;
+
+/// A write recorded in the journal of a [`CommitGroup`], replayed on recovery.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) enum JournalOp {
+    Delete {
+        cf: Option<String>,
+        key: Vec<u8>,
+    },
+    DeleteRange {
+        cf: Option<String>,
+        from: Vec<u8>,
+        to: Vec<u8>,
+    },
+    Put {
+        cf: Option<String>,
+        key: Vec<u8>,
+        value: Vec<u8>,
+    },
+}
+
+/// The writes of a commit group, by database name.
+type Record = Vec<(String, Vec<JournalOp>)>;
+
+/// A database on which the journal of a [`CommitGroup`] can be replayed.
+pub trait JournalTarget {
+    fn db(&self) -> &Arc<rocksdb::DB>;
+    fn db_name(&self) -> &str;
+}
+
+impl<K> JournalTarget for Db<K> {
+    fn db(&self) -> &Arc<rocksdb::DB> {
+        self.raw_db()
+    }
+
+    fn db_name(&self) -> &str {
+        self.name()
+    }
+}
+
+impl JournalTarget for DbHandle {
+    fn db(&self) -> &Arc<rocksdb::DB> {
+        self.raw_db()
+    }
+
+    fn db_name(&self) -> &str {
+        self.name()
+    }
+}
+
+/// Transactions on distinct databases committed as a whole, even across a crash.
+///
+/// The writes are first recorded in a journal database, then written to each database,
+/// and finally removed from the journal. On reopen, [`CommitGroup::recover`] replays the
+/// groups that were interrupted, before any other write is made on the databases.
+pub struct CommitGroup<'a> {
+    journal: &'a Db<u64>,
+    txs: Vec<(String, Transaction<'a>)>,
+}
+
+impl<'a> CommitGroup<'a> {
+    pub fn new(journal: &'a Db<u64>) -> Self {
+        Self {
+            journal,
+            txs: Vec::new(),
+        }
+    }
+
+    /// Journals the writes, then commits each transaction synchronously.
+    pub fn commit(self) -> Result<()> {
+        let span = trace_span!(
+            "commit_group",
+            db.name = self.journal.name(),
+            db.statement = format!("len = {}", self.txs.len()).as_str(),
+            db.system = "rocksdb",
+        );
+        let _enter = span.enter();
+
+        let record = self
+            .txs
+            .iter()
+            .map(|(name, tx)| (name.clone(), tx.journal_ops().to_vec()))
+            .collect::<Record>();
+
+        let id = self.journal.append_sync(&record)?;
+
+        for (_, tx) in self.txs {
+            tx.commit_with(WriteMode::Sync)?;
+        }
+
+        self.journal.delete_sync(&id)
+    }
+
+    /// Replays the groups left in the journal by a crash, returning the number of groups replayed.
+    ///
+    /// Each database of the groups must be in `targets`, under the name it was given in
+    /// [`CommitGroup::transaction`].
+    pub fn recover(journal: &Db<u64>, targets: &[(&str, &dyn JournalTarget)]) -> Result<usize> {
+        let span = trace_span!("recover", db.name = journal.name(), db.system = "rocksdb");
+        let _enter = span.enter();
+
+        let mut pending = Vec::new();
+
+        {
+            let mut iter = journal.iter(IteratorMode::Start)?;
+
+            while let Some(kv) = iter.next()? {
+                pending.push((kv.key()?, kv.value::<Record>()?));
+            }
+        }
+
+        for (id, record) in &pending {
+            for (name, ops) in record {
+                let target = targets
+                    .iter()
+                    .find(|(n, _)| *n == name.as_str())
+                    .map(|(_, t)| *t)
+                    .ok_or_else(|| {
+                        log_err(Error::JournalTargetNotFound(name.clone()), journal.name())
+                    })?;
+
+                replay(target, ops)?;
+            }
+
+            journal.delete_sync(id)?;
+        }
+
+        Ok(pending.len())
+    }
+
+    /// Returns the transaction of the database identified by `name`, created on first access.
+    ///
+    /// All the writes made through this transaction are journaled.
+    pub fn transaction(&mut self, name: &str) -> &mut Transaction<'a> {
+        match self.txs.iter().position(|(n, _)| n == name) {
+            Some(index) => &mut self.txs[index].1,
+            None => {
+                self.txs.push((name.to_string(), Transaction::journaled()));
+                &mut self.txs.last_mut().unwrap().1
+            }
+        }
+    }
+}
+
+fn replay(target: &dyn JournalTarget, ops: &[JournalOp]) -> Result<()> {
+    let db = target.db();
+    let db_name = target.db_name();
+    let mut batch = rocksdb::WriteBatch::default();
+
+    let cf_handle = |cf: &Option<String>| match cf {
+        Some(name) => db
+            .cf_handle(name)
+            .map(Some)
+            .ok_or_else(|| log_err(Error::ColumnFamilyNotFound(name.clone()), db_name)),
+        None => Ok(None),
+    };
+
+    for op in ops {
+        match op {
+            JournalOp::Delete { cf, key } => match cf_handle(cf)? {
+                Some(cf) => batch.delete_cf(cf, key),
+                None => batch.delete(key),
+            },
+            JournalOp::DeleteRange { cf, from, to } => match cf_handle(cf)? {
+                Some(cf) => batch.delete_range_cf(cf, from, to),
+                None => batch.delete_range(from, to),
+            },
+            JournalOp::Put { cf, key, value } => match cf_handle(cf)? {
+                Some(cf) => batch.put_cf(cf, key, value),
+                None => batch.put(key, value),
+            },
+        }
+        .map_err(|e| map_log_err(e, db_name))?;
+    }
+
+    let mut opts = rocksdb::WriteOptions::default();
+    opts.set_sync(true);
+
+    db.write_opt(batch, &opts)
+        .map_err(|e| map_log_err(e, db_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CommitGroup, Record};
+    use crate::{Db, Error, IteratorMode, Result};
+
+    fn journal_len(journal: &Db<u64>) -> usize {
+        let mut iter = journal.iter(IteratorMode::Start).unwrap();
+        let mut len = 0;
+
+        while iter.next().unwrap().is_some() {
+            len += 1;
+        }
+
+        len
+    }
+
+    /// Journals the writes of a group without committing them, as interrupted by a crash.
+    fn interrupt(group: &CommitGroup) -> Result<u64> {
+        let record = group
+            .txs
+            .iter()
+            .map(|(name, tx)| (name.clone(), tx.journal_ops().to_vec()))
+            .collect::<Record>();
+
+        group.journal.append_sync(&record)
+    }
+
+    #[test]
+    fn commit_clears_journal() {
+        let journal = Db::<u64>::open_temporary().unwrap();
+        let a = Db::<u32>::open_temporary().unwrap();
+        let b = Db::<String>::open_temporary().unwrap();
+
+        let mut group = CommitGroup::new(&journal);
+        group.transaction("a").put(&a, &1, &"a").unwrap();
+        group
+            .transaction("b")
+            .put(&b, &"b".to_string(), &2)
+            .unwrap();
+        group.commit().unwrap();
+
+        assert!(a.get(&1).unwrap().is_some());
+        assert!(b.get(&"b".to_string()).unwrap().is_some());
+        assert_eq!(journal_len(&journal), 0);
+    }
+
+    #[test]
+    fn recover_replays_interrupted_group() {
+        let journal = Db::<u64>::open_temporary().unwrap();
+        let a = Db::<u32>::open_temporary().unwrap();
+        let b = Db::<String>::open_temporary().unwrap();
+
+        a.put(&5, &"old").unwrap();
+
+        let mut group = CommitGroup::new(&journal);
+        group.transaction("a").put(&a, &1, &"a").unwrap();
+        group.transaction("a").delete(&a, &5).unwrap();
+        group
+            .transaction("b")
+            .put(&b, &"b".to_string(), &2)
+            .unwrap();
+        interrupt(&group).unwrap();
+        drop(group);
+
+        assert!(a.get(&1).unwrap().is_none());
+        assert_eq!(journal_len(&journal), 1);
+
+        let replayed = CommitGroup::recover(&journal, &[("a", &a), ("b", &b)]).unwrap();
+        assert_eq!(replayed, 1);
+
+        let value = a.get(&1).unwrap().unwrap();
+        assert_eq!(value.to_inner::<String>().unwrap(), "a");
+        assert!(a.get(&5).unwrap().is_none());
+
+        let value = b.get(&"b".to_string()).unwrap().unwrap();
+        assert_eq!(value.to_inner::<i32>().unwrap(), 2);
+
+        assert_eq!(journal_len(&journal), 0);
+        assert_eq!(
+            CommitGroup::recover(&journal, &[("a", &a), ("b", &b)]).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn recover_missing_target() {
+        let journal = Db::<u64>::open_temporary().unwrap();
+        let a = Db::<u32>::open_temporary().unwrap();
+        let b = Db::<u32>::open_temporary().unwrap();
+
+        let mut group = CommitGroup::new(&journal);
+        group.transaction("a").put(&a, &1, &"a").unwrap();
+        group.transaction("b").put(&b, &1, &"b").unwrap();
+        interrupt(&group).unwrap();
+        drop(group);
+
+        let e = CommitGroup::recover(&journal, &[("a", &a)]).unwrap_err();
+        assert!(matches!(e.root(), Error::JournalTargetNotFound(name) if name == "b"));
+
+        // the group stays in the journal, to be replayed once all the targets are given.
+        assert_eq!(journal_len(&journal), 1);
+        assert!(b.get(&1).unwrap().is_none());
+
+        assert_eq!(
+            CommitGroup::recover(&journal, &[("a", &a), ("b", &b)]).unwrap(),
+            1
+        );
+        assert!(b.get(&1).unwrap().is_some());
+        assert_eq!(journal_len(&journal), 0);
+    }
+}
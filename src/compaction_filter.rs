@@ -0,0 +1,56 @@
+use crate::{
+    db::{log_err, META_KEY},
+    key_ser::key_from_bytes,
+};
+use rocksdb::compaction_filter::Decision;
+use serde::Deserialize;
+
+/// The fate of a record during a compaction, returned by the filter of [`Db::open_with_filter`](crate::Db::open_with_filter).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FilterDecision {
+    /// The record is kept as is.
+    Keep,
+    /// The record is dropped from the database.
+    Remove,
+    /// The value of the record is replaced by these serialized bytes.
+    Change(Vec<u8>),
+}
+
+/// Adapts a typed filter to the compaction filter of RocksDB.
+///
+/// The reserved meta key and the keys that cannot be decoded are always kept.
+pub(crate) fn compaction_filter<K, F>(
+    db_name: String,
+    filter: F,
+) -> impl FnMut(u32, &[u8], &[u8]) -> Decision + Send + 'static
+where
+    K: for<'de> Deserialize<'de>,
+    F: Fn(K, &[u8]) -> FilterDecision + Send + 'static,
+{
+    let mut changed = Vec::new();
+
+    move |_level, key, value| {
+        if key == META_KEY {
+            return Decision::Keep;
+        }
+
+        let key = match key_from_bytes(key) {
+            Ok(key) => key,
+            Err(e) => {
+                log_err(e, &db_name);
+                return Decision::Keep;
+            }
+        };
+
+        match filter(key, value) {
+            FilterDecision::Keep => Decision::Keep,
+            FilterDecision::Remove => Decision::Remove,
+            FilterDecision::Change(bytes) => {
+                changed = bytes;
+
+                // RocksDB copies the new value before calling the filter again.
+                Decision::Change(unsafe { &*(changed.as_slice() as *const [u8]) })
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use crate::key_ser::key_from_bytes;
+use serde::Deserialize;
+use std::cmp::Ordering;
+
+/// A comparison of serialized keys, as registered in RocksDB.
+pub(crate) type CompareFn = fn(&[u8], &[u8]) -> Ordering;
+
+/// Defines the order of the keys of a database in Rust, for the key types whose serialized
+/// form does not sort in their logical order.
+///
+/// The database must always be opened with the same comparator, see
+/// [`Db::open_with_comparator`](crate::Db::open_with_comparator). The prefix iterations
+/// and the section iterations rely on the bytewise order and fail with
+/// [`Error::KeyOrderUnsupported`](crate::Error::KeyOrderUnsupported), while the range
+/// deletes remove the keys one by one.
+pub trait KeyComparator<K> {
+    /// The name of the comparator, persisted by RocksDB and checked when the database is opened.
+    const NAME: &'static str;
+
+    fn compare(a: &K, b: &K) -> Ordering;
+}
+
+/// Orders the string keys ignoring the case, the keys only differing by case being
+/// ordered bytewise so they remain distinct.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CaseInsensitive;
+
+impl KeyComparator<String> for CaseInsensitive {
+    const NAME: &'static str = "rocks_tables_case_insensitive";
+
+    fn compare(a: &String, b: &String) -> Ordering {
+        let lower = |s: &String| s.chars().flat_map(char::to_lowercase).collect::<Vec<_>>();
+        lower(a).cmp(&lower(b)).then_with(|| a.cmp(b))
+    }
+}
+
+pub(crate) fn bytewise(a: &[u8], b: &[u8]) -> Ordering {
+    a.cmp(b)
+}
+
+pub(crate) fn compare_keys<K, C>(a: &[u8], b: &[u8]) -> Ordering
+where
+    K: for<'de> Deserialize<'de>,
+    C: KeyComparator<K>,
+{
+    // the meta key, empty, and the bytes that are not a key sort bytewise before all the keys,
+    // so the order stays total whatever their bytes.
+    match (key_from_bytes::<K>(a), key_from_bytes::<K>(b)) {
+        (Ok(a), Ok(b)) => C::compare(&a, &b),
+        (Ok(_), Err(_)) => Ordering::Greater,
+        (Err(_), Ok(_)) => Ordering::Less,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compare_keys, CaseInsensitive};
+    use crate::{key_ser::key_to_bytes, Db, Direction, IteratorMode};
+    use std::cmp::Ordering;
+
+    fn key(s: &str) -> Vec<u8> {
+        key_to_bytes(&s.to_string()).unwrap()
+    }
+
+    #[test]
+    fn case_insensitive_order() {
+        let compare = compare_keys::<String, CaseInsensitive>;
+
+        assert_eq!(compare(&key("a"), &key("B")), Ordering::Less);
+        assert_eq!(compare(&key("B"), &key("a")), Ordering::Greater);
+        assert_eq!(compare(&key("A"), &key("a")), Ordering::Less);
+        assert_eq!(compare(&key("abc"), &key("abc")), Ordering::Equal);
+    }
+
+    #[test]
+    fn bytes_not_keys_sort_first() {
+        let compare = compare_keys::<String, CaseInsensitive>;
+        let invalid = [0xff, 0xff, 0xff];
+
+        assert_eq!(compare(&[], &key("a")), Ordering::Less);
+        assert_eq!(compare(&invalid, &key("a")), Ordering::Less);
+        assert_eq!(compare(&key("a"), &invalid), Ordering::Greater);
+        assert_eq!(compare(&[], &invalid), Ordering::Less);
+    }
+
+    #[test]
+    fn database_case_insensitive() {
+        let db = Db::<String>::open_temporary_with(|path| {
+            Db::open_with_comparator::<CaseInsensitive, _>(path)
+        })
+        .unwrap();
+
+        for k in ["b", "C", "a", "B"] {
+            db.put(&k.to_string(), &()).unwrap();
+        }
+
+        let keys = db
+            .iter_decoded::<()>(IteratorMode::Start)
+            .unwrap()
+            .map(|r| r.unwrap().0)
+            .collect::<Vec<_>>();
+
+        assert_eq!(keys, ["a", "B", "b", "C"]);
+
+        let from = IteratorMode::From("b".to_string(), Direction::Forward);
+        let first = db
+            .iter_decoded::<()>(from)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.0, "b");
+
+        db.delete_range(&"B".to_string(), &"C".to_string()).unwrap();
+        assert!(db.get(&"a".to_string()).unwrap().is_some());
+        assert!(db.get(&"b".to_string()).unwrap().is_none());
+        assert!(db.get(&"C".to_string()).unwrap().is_some());
+
+        assert!(db.iter_prefix(&"a".to_string()).is_err());
+
+        db.clear().unwrap();
+        assert!(db.get(&"a".to_string()).unwrap().is_none());
+        assert!(db.get(&"C".to_string()).unwrap().is_none());
+    }
+}
@@ -0,0 +1,40 @@
+use crate::Result;
+
+/// Compresses the values independently of the block compression of RocksDB.
+pub trait Compress {
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>>;
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// The LZ4 block compression, fast but with a lower ratio than Zstd.
+#[cfg(feature = "lz4_flex")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Lz4;
+
+#[cfg(feature = "lz4_flex")]
+impl Compress for Lz4 {
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(lz4_flex::compress_prepend_size(data))
+    }
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        lz4_flex::decompress_size_prepended(data)
+            .map_err(|e| crate::Error::Compression(e.to_string()))
+    }
+}
+
+/// The Zstd compression at the specified level, `0` being the default level of Zstd.
+#[cfg(feature = "zstd")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Zstd(pub i32);
+
+#[cfg(feature = "zstd")]
+impl Compress for Zstd {
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(zstd::encode_all(data, self.0)?)
+    }
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(zstd::decode_all(data)?)
+    }
+}
@@ -0,0 +1,53 @@
+use crate::{Compress, Compressed, Db, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{fmt::Debug, marker::PhantomData};
+
+/// A table where each value is compressed before it is stored in the database.
+///
+/// Useful for large values, such as blobs or documents, compressed independently
+/// of the blocks of RocksDB.
+pub struct CompressedTable<K, V, C> {
+    _v: PhantomData<V>,
+    compressor: C,
+    db: Db<K>,
+}
+
+impl<K, V, C> CompressedTable<K, V, C>
+where
+    C: Compress,
+    K: Debug + for<'de> Deserialize<'de> + Serialize,
+    V: DeserializeOwned + Serialize,
+{
+    pub fn new(db: Db<K>, compressor: C) -> Self {
+        Self {
+            _v: PhantomData,
+            compressor,
+            db,
+        }
+    }
+
+    pub fn contains_key(&self, key: &K) -> Result<bool> {
+        self.db.contains_key(key)
+    }
+
+    pub fn delete(&self, key: &K) -> Result<()> {
+        self.db.delete(key)
+    }
+
+    /// Gets and decompresses a value from the database.
+    pub fn get(&self, key: &K) -> Result<Option<V>> {
+        match self.db.get(key)? {
+            Some(value) => {
+                let mut temp = Vec::new();
+                let compressed = value.to_inner::<Compressed>()?;
+                Ok(Some(compressed.to_inner(&self.compressor, &mut temp)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn put(&self, key: &K, value: &V) -> Result<()> {
+        self.db
+            .put(key, &Compressed::with_ref(value, &self.compressor)?)
+    }
+}
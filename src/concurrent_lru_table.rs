@@ -0,0 +1,289 @@
+use crate::{
+    in_flight::InFlight,
+    lru_table::{load_hot_keys, save_hot_keys},
+    Db, LruTable, Result, UpdateFrom,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::hash_map::RandomState,
+    fmt::Debug,
+    hash::{BuildHasher, Hash, Hasher},
+    sync::{Arc, Mutex, MutexGuard},
+    thread::{self, JoinHandle},
+};
+
+/// A [`LruTable`] that can be shared between threads.
+///
+/// The keys are partitioned into shards, each one being a [`LruTable`] behind its own lock,
+/// so threads accessing different shards do not contend. Each shard evicts its own least
+/// recently used entries when its part of the capacity is reached.
+pub struct ConcurrentLruTable<K, V, S = RandomState> {
+    db: Db<K>,
+    hash_builder: S,
+    loading: InFlight<K>,
+    shards: Vec<Mutex<LruTable<K, V>>>,
+}
+
+impl<K, V> ConcurrentLruTable<K, V, RandomState>
+where
+    K: Clone + Debug + for<'de> Deserialize<'de> + Eq + Hash + Serialize,
+    V: for<'de> Deserialize<'de> + Serialize,
+{
+    /// Creates a table with a total capacity split evenly between `shards`.
+    pub fn with_capacity(db: Db<K>, capacity: usize, shards: usize) -> Self {
+        Self::with_capacity_and_hasher(db, capacity, shards, Default::default())
+    }
+}
+
+impl<K, V, S> ConcurrentLruTable<K, V, S>
+where
+    K: Clone + Debug + for<'de> Deserialize<'de> + Eq + Hash + Serialize,
+    V: for<'de> Deserialize<'de> + Serialize,
+    S: BuildHasher,
+{
+    /// Creates a table with a total capacity split evenly between `shards`,
+    /// using `hash_builder` to select the shard of a key.
+    pub fn with_capacity_and_hasher(
+        db: Db<K>,
+        capacity: usize,
+        shards: usize,
+        hash_builder: S,
+    ) -> Self {
+        assert!(shards > 0);
+        assert!(capacity >= shards);
+
+        let shard_capacity = capacity / shards;
+
+        Self {
+            shards: (0..shards)
+                .map(|_| Mutex::new(LruTable::with_capacity(db.clone(), shard_capacity)))
+                .collect(),
+            db,
+            hash_builder,
+            loading: InFlight::new(),
+        }
+    }
+
+    /// Returns true if the table contains a value for the specified key.
+    pub fn contains_key(&self, key: &K) -> Result<bool> {
+        self.shard(key).contains_key(key)
+    }
+
+    /// Removes a key from the table.
+    pub fn delete(&self, key: &K) -> Result<()> {
+        self.shard(key).delete(key)
+    }
+
+    /// Returns a clone of the value corresponding to the key.
+    pub fn get(&self, key: &K) -> Result<Option<V>>
+    where
+        V: Clone,
+    {
+        Ok(self.shard(key).get(key)?.cloned())
+    }
+
+    /// Returns a clone of the value of the key, computing it with `loader` and storing it
+    /// if it does not exist.
+    ///
+    /// The threads missing the same key do not call their loaders concurrently: a single one
+    /// runs, outside the lock of the shard, while the others wait and then read its value.
+    /// If it fails, the next waiting thread runs its own loader.
+    pub fn get_or_load<F>(&self, key: &K, loader: F) -> Result<V>
+    where
+        F: FnOnce() -> Result<V>,
+        V: Clone,
+    {
+        if let Some(value) = self.get(key)? {
+            return Ok(value);
+        }
+
+        let _loading = self.loading.enter(key);
+
+        // another thread may have loaded the key while this one was waiting.
+        if let Some(value) = self.get(key)? {
+            return Ok(value);
+        }
+
+        let value = loader()?;
+        self.put(key, value.clone())?;
+        Ok(value)
+    }
+
+    pub fn put(&self, key: &K, value: V) -> Result<()> {
+        self.shard(key).put(key, value)
+    }
+
+    /// Persists the hottest tracked keys of all the shards in the database, to be reloaded
+    /// by [`ConcurrentLruTable::warm_up_hot_keys`] after a restart.
+    pub fn save_hot_keys(&self) -> Result<()> {
+        let mut hot_keys = (0..self.shards.len())
+            .flat_map(|index| self.shard_at(index).hot_keys())
+            .collect::<Vec<_>>();
+
+        hot_keys.sort_by(|a, b| b.1.cmp(&a.1));
+
+        let keys = hot_keys.into_iter().map(|(k, _)| k).collect::<Vec<_>>();
+        save_hot_keys(&self.db, &keys)
+    }
+
+    fn shard(&self, key: &K) -> MutexGuard<LruTable<K, V>> {
+        self.shard_at(self.shard_index(key))
+    }
+
+    fn shard_at(&self, index: usize) -> MutexGuard<LruTable<K, V>> {
+        // a poisoned shard is still consistent since the db is written before the cache.
+        self.shards[index].lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn shard_index(&self, key: &K) -> usize {
+        let mut hasher = self.hash_builder.build_hasher();
+        key.hash(&mut hasher);
+        hasher.finish() as usize % self.shards.len()
+    }
+
+    /// Starts counting the accesses of the keys, keeping track of about the `count` hottest ones.
+    pub fn track_hot_keys(&self, count: usize) {
+        let shard_count = (count / self.shards.len()).max(1);
+
+        for index in 0..self.shards.len() {
+            self.shard_at(index).track_hot_keys(shard_count);
+        }
+    }
+
+    pub fn update<U>(&self, key: &K, update: U) -> Result<()>
+    where
+        U: UpdateFrom<V>,
+    {
+        self.shard(key).update(key, update)
+    }
+
+    /// Loads the keys missing from the cache, without evicting any entry, and returns
+    /// the number of entries loaded.
+    ///
+    /// The keys are expected hottest first; each shard stops loading once it is full.
+    pub fn warm_up<I>(&self, keys: I) -> Result<usize>
+    where
+        I: IntoIterator<Item = K>,
+    {
+        let mut parts = (0..self.shards.len())
+            .map(|_| Vec::new())
+            .collect::<Vec<_>>();
+
+        for key in keys {
+            parts[self.shard_index(&key)].push(key);
+        }
+
+        let mut count = 0;
+
+        for (index, keys) in parts.into_iter().enumerate() {
+            count += self.shard_at(index).warm_up(keys)?;
+        }
+
+        Ok(count)
+    }
+
+    /// Loads the hottest keys persisted by [`ConcurrentLruTable::save_hot_keys`].
+    pub fn warm_up_hot_keys(&self) -> Result<usize> {
+        let keys = load_hot_keys(&self.db)?;
+        self.warm_up(keys)
+    }
+
+    /// Loads the persisted hottest keys on a background thread, while the table is in use.
+    pub fn warm_up_in_background(self: &Arc<Self>) -> JoinHandle<Result<usize>>
+    where
+        K: Send + 'static,
+        V: Send + 'static,
+        S: Send + Sync + 'static,
+    {
+        let table = Arc::clone(self);
+        thread::spawn(move || table.warm_up_hot_keys())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConcurrentLruTable;
+    use crate::Db;
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Barrier,
+        },
+        thread,
+        time::Duration,
+    };
+
+    #[test]
+    fn get_or_load_runs_loader_once() {
+        let db = Db::<u32>::open_temporary().unwrap();
+        let table = Arc::new(ConcurrentLruTable::<u32, u32>::with_capacity(db, 16, 4));
+        let barrier = Arc::new(Barrier::new(8));
+        let loads = Arc::new(AtomicUsize::new(0));
+
+        let threads = (0..8)
+            .map(|_| {
+                let barrier = Arc::clone(&barrier);
+                let loads = Arc::clone(&loads);
+                let table = Arc::clone(&table);
+
+                thread::spawn(move || {
+                    barrier.wait();
+
+                    table
+                        .get_or_load(&1, || {
+                            loads.fetch_add(1, Ordering::SeqCst);
+                            // keeps the key loading while the other threads miss it.
+                            thread::sleep(Duration::from_millis(50));
+                            Ok(42)
+                        })
+                        .unwrap()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), 42);
+        }
+
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+        assert_eq!(table.get(&1).unwrap(), Some(42));
+    }
+
+    #[test]
+    fn shards_between_threads() {
+        let db = Db::<u32>::open_temporary().unwrap();
+        let table = Arc::new(ConcurrentLruTable::<u32, u32>::with_capacity(db, 400, 4));
+
+        let threads = (0..8)
+            .map(|t| {
+                let table = Arc::clone(&table);
+
+                thread::spawn(move || {
+                    for key in t * 100..(t + 1) * 100 {
+                        table.put(&key, key * 2).unwrap();
+                    }
+
+                    for key in t * 100..(t + 1) * 100 {
+                        assert_eq!(table.get(&key).unwrap(), Some(key * 2));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        threads.into_iter().for_each(|t| t.join().unwrap());
+
+        // the keys are spread over all the shards, each within its part of the capacity.
+        let mut counts = [0; 4];
+        (0..800).for_each(|key| counts[table.shard_index(&key)] += 1);
+        assert!(counts.iter().all(|&count| count > 0));
+
+        for index in 0..4 {
+            let shard = table.shard_at(index);
+            assert!(!shard.is_empty() && shard.len() <= shard.capacity());
+        }
+
+        for key in 0..800 {
+            assert_eq!(table.get(&key).unwrap(), Some(key * 2));
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use crate::{Db, MergeSerde, Result, UpdateFrom};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::fmt::Debug;
+
+/// The merge operator of a [`CounterTable`], adding the increments to the stored `i64`.
+///
+/// The [`Db`] must be opened with `Db::open_with_merge::<Counter, _>`.
+pub struct Counter;
+
+impl MergeSerde for Counter {
+    const NAME: &'static str = "rocks_tables_counter";
+
+    type Update = Incr;
+    type Value = i64;
+}
+
+/// An increment of a counter, stored as a merge operand.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Incr(pub i64);
+
+impl UpdateFrom<i64> for Incr {
+    #[inline]
+    fn update_from(self, old: Option<i64>) -> i64 {
+        old.unwrap_or(0).wrapping_add(self.0)
+    }
+}
+
+/// A table of `i64` counters incremented by RocksDB merges, without a read-modify-write.
+///
+/// Concurrent increments of the same key are never lost, which makes it suitable for hot
+/// counters such as rate limits or statistics.
+pub struct CounterTable<K> {
+    db: Db<K>,
+}
+
+impl<K> CounterTable<K>
+where
+    K: Debug + DeserializeOwned + Serialize,
+{
+    pub fn new(db: Db<K>) -> Self {
+        Self { db }
+    }
+
+    /// Removes a counter, resetting it to 0.
+    pub fn delete(&self, key: &K) -> Result<()> {
+        self.db.delete(key)
+    }
+
+    /// Returns the value of a counter, 0 if it was never incremented.
+    pub fn get(&self, key: &K) -> Result<i64> {
+        match self.db.get(key)? {
+            Some(v) => v.to_inner(),
+            None => Ok(0),
+        }
+    }
+
+    /// Adds `delta` to a counter and returns its new value.
+    ///
+    /// The value is read after the increment, so it may include the increments
+    /// made concurrently by other threads.
+    pub fn incr(&self, key: &K, delta: i64) -> Result<i64> {
+        self.db.merge(key, &Incr(delta))?;
+        self.get(key)
+    }
+
+    pub fn put(&self, key: &K, value: i64) -> Result<()> {
+        self.db.put(key, &value)
+    }
+}
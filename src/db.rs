@@ -1,19 +1,94 @@
-use crate::{Error, Result};
+use crate::{
+    buf::with_buf,
+    commit_group::JournalOp,
+    compaction_filter::compaction_filter,
+    comparator::{bytewise, compare_keys, CompareFn},
+    export::{read_entry, read_header, write_end, write_entry, write_header},
+    hooks::{Change, ChangeEvent, ChangeKind, Hooks},
+    merge::{full_merge, full_merge_checksummed, MergeSerde},
+    stats, ChecksumReport, CorruptedRange, Error, ErrorContext, FilterDecision, IterOptions,
+    KeyComparator, RecoveryMode, Result, RetryPolicy, SizeHistogram, Tail, TraceConfig, UpdateFrom,
+};
 use fmt::Display;
-use rocksdb::{DBCompressionType, DBPinnableSlice, DBRawIterator, Options};
-use serde::{Deserialize, Serialize};
+use rocksdb::{
+    ColumnFamily, DBCompressionType, DBPinnableSlice, DBRawIterator, Options, ReadOptions,
+    WriteOptions,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
+    cmp::Ordering as CmpOrdering,
+    collections::{BTreeMap, HashMap},
     fmt::{self, Debug},
+    io::{Read, Write},
     marker::PhantomData,
-    path::Path,
-    sync::Arc,
+    mem::replace,
+    ops::{Bound, Deref, RangeBounds},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{channel, Receiver},
+        Arc, Mutex,
+    },
+    thread::sleep,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use tracing::{error, trace_span};
 
+/// Creates the span of an operation of a [`Db`](crate::Db), honoring its [`TraceConfig`].
+macro_rules! db_span {
+    (@level $db:expr, $name:literal, $statement:expr) => {{
+        let level = $db.trace.level;
+
+        if level == tracing::Level::ERROR {
+            db_span!(@span tracing::Level::ERROR, $db, $name, $statement)
+        } else if level == tracing::Level::WARN {
+            db_span!(@span tracing::Level::WARN, $db, $name, $statement)
+        } else if level == tracing::Level::INFO {
+            db_span!(@span tracing::Level::INFO, $db, $name, $statement)
+        } else if level == tracing::Level::DEBUG {
+            db_span!(@span tracing::Level::DEBUG, $db, $name, $statement)
+        } else {
+            db_span!(@span tracing::Level::TRACE, $db, $name, $statement)
+        }
+    }};
+    (@span $level:expr, $db:expr, $name:literal, $statement:expr) => {
+        tracing::span!(
+            $level,
+            $name,
+            db.name = $db.db_name.as_str(),
+            db.statement = $statement,
+            db.system = "rocksdb",
+        )
+    };
+    ($db:expr, $name:literal) => {
+        db_span!(@level $db, $name, tracing::field::Empty)
+    };
+    ($db:expr, $name:literal, $statement:expr) => {
+        db_span!(@level $db, $name, $db.trace.statement(&$statement).as_deref())
+    };
+}
+
+/// The key reserved for the metadata of the database, such as the schema version.
+///
+/// No key type serialize to an empty slice except zero-sized types, which cannot be stored.
+pub(crate) const META_KEY: &[u8] = b"";
+
 pub struct Db<K> {
     _k: PhantomData<K>,
-    db: rocksdb::DB,
+    cas_lock: Arc<Mutex<()>>,
+    cf: Option<String>,
+    /// True when the values are written with a trailing CRC32, verified when they are read.
+    checksums: bool,
+    /// The order of the serialized keys when opened with a comparator, bytewise otherwise.
+    comparator: Option<CompareFn>,
+    db: Arc<rocksdb::DB>,
     db_name: String,
+    hooks: Arc<Hooks>,
+    /// The checksum setting of the merge operator, if any, which the values must follow.
+    merge_checksums: Option<bool>,
+    /// The directory of a temporary database, removed once the database is closed.
+    temp_dir: Option<Arc<TempDir>>,
+    trace: TraceConfig,
 }
 
 impl<K> Db<K>
@@ -21,157 +96,2131 @@ where
     K: Debug + for<'de> Deserialize<'de> + Serialize,
 {
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let db_name = path
-            .as_ref()
-            .file_name()
-            .and_then(|s| s.to_str())
-            .unwrap_or("")
-            .to_string();
-
-        let _ = trace_span!("open", db.name = db_name.as_str(), db.system = "rocksdb").enter();
-        let mut opts = Options::default();
-        opts.create_if_missing(true);
-        opts.set_compression_type(DBCompressionType::Zstd);
+        Self::open_with_options(path, &default_options())
+    }
+
+    /// Opens the database with the merge operator `M`, required by [`MergeTable`](crate::MergeTable).
+    pub fn open_with_merge<M, P>(path: P) -> Result<Self>
+    where
+        M: MergeSerde,
+        P: AsRef<Path>,
+    {
+        let mut opts = default_options();
+        opts.set_merge_operator(M::NAME, full_merge::<M>, None);
+
+        let mut db = Self::open_with_options(path, &opts)?;
+        db.merge_checksums = Some(false);
+        Ok(db)
+    }
+
+    /// Opens the database with the merge operator `M` and the [value checksums](Self::with_value_checksums),
+    /// the merge operator verifying the checksum of the stored value and appending one to the result.
+    pub fn open_with_merge_and_checksums<M, P>(path: P) -> Result<Self>
+    where
+        M: MergeSerde,
+        P: AsRef<Path>,
+    {
+        let mut opts = default_options();
+        opts.set_merge_operator(M::NAME, full_merge_checksummed::<M>, None);
+
+        let mut db = Self::open_with_options(path, &opts)?;
+        db.checksums = true;
+        db.merge_checksums = Some(true);
+        Ok(db)
+    }
+
+    /// Opens the database with the comparator `C`, ordering the keys in their logical order
+    /// when their serialized form does not, such as case-insensitive string keys.
+    ///
+    /// The comparator decodes the keys for each comparison, slowing down the writes and the seeks.
+    /// The prefix iterations fail with [`Error::KeyOrderUnsupported`], the keys of a prefix not
+    /// being contiguous.
+    pub fn open_with_comparator<C, P>(path: P) -> Result<Self>
+    where
+        C: KeyComparator<K>,
+        P: AsRef<Path>,
+    {
+        let mut opts = default_options();
+        opts.set_comparator(C::NAME, compare_keys::<K, C>);
+
+        let mut db = Self::open_with_options(path, &opts)?;
+        db.comparator = Some(compare_keys::<K, C>);
+        Ok(db)
+    }
+
+    /// Opens the database, replaying its write-ahead log with the specified tolerance to corruption.
+    pub fn open_with_recovery<P: AsRef<Path>>(path: P, mode: RecoveryMode) -> Result<Self> {
+        let mut opts = default_options();
+        opts.set_wal_recovery_mode(mode.into());
+        Self::open_with_options(path, &opts)
+    }
+
+    /// Opens the database with a compaction filter, deciding the fate of each record
+    /// rewritten by a compaction from its key and its serialized value.
+    ///
+    /// Records are only filtered when compacted, so a removed record may still be read
+    /// until its file is compacted.
+    pub fn open_with_filter<F, P>(path: P, filter: F) -> Result<Self>
+    where
+        F: Fn(K, &[u8]) -> FilterDecision + Send + 'static,
+        K: 'static,
+        P: AsRef<Path>,
+    {
+        let mut opts = default_options();
+        let db_name = db_name_from_path(path.as_ref());
+
+        opts.set_compaction_filter("rocks_tables_filter", compaction_filter(db_name, filter));
+        Self::open_with_options(path, &opts)
+    }
+
+    pub(crate) fn open_with_options<P: AsRef<Path>>(path: P, opts: &Options) -> Result<Self> {
+        let db_name = db_name_from_path(path.as_ref());
+
+        let span = trace_span!("open", db.name = db_name.as_str(), db.system = "rocksdb");
+        let _enter = span.enter();
 
         Ok(Db {
             _k: PhantomData,
-            db: rocksdb::DB::open(&opts, path).map_err(|e| map_log_err(e, &db_name))?,
+            cas_lock: Default::default(),
+            cf: None,
+            checksums: false,
+            comparator: None,
+            db: Arc::new(rocksdb::DB::open(opts, path).map_err(|e| map_log_err(e, &db_name))?),
             db_name,
+            hooks: Default::default(),
+            merge_checksums: None,
+            temp_dir: None,
+            trace: Default::default(),
+        })
+    }
+
+    /// Opens a database in a new temporary directory, removed when the last clone of the
+    /// database is dropped.
+    ///
+    /// The writes skip the write-ahead log, since the data does not outlive the process.
+    /// Intended for tests.
+    pub fn open_temporary() -> Result<Self> {
+        Self::open_temporary_with(|path| Self::open(path))
+    }
+
+    /// Opens a temporary database with `open`, such as [`Db::open_with_merge`].
+    pub(crate) fn open_temporary_with<F>(open: F) -> Result<Self>
+    where
+        F: FnOnce(&Path) -> Result<Self>,
+    {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos());
+
+        let path = std::env::temp_dir().join(format!(
+            "rocks-tables-{}-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed),
+            nanos
+        ));
+
+        let mut db = open(&path)?;
+        db.temp_dir = Some(Arc::new(TempDir(path)));
+        Ok(db)
+    }
+
+    /// Writes the values with a trailing CRC32, verified by [`DbValue::to_inner`] and
+    /// [`DbKeyValue::value`], which return [`Error::ValueChecksum`] on a mismatch.
+    ///
+    /// Protects against partial writes and bit rot beyond the block checksums, such as an
+    /// encrypted value that would otherwise fail with a confusing decryption error.
+    /// All the views on the data must agree on the setting, and the merge operands and the
+    /// values written with [`Db::put_raw`] are stored without checksum.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the database was opened with a merge operator using the other setting, see
+    /// [`Db::open_with_merge_and_checksums`].
+    pub fn with_value_checksums(mut self, enabled: bool) -> Self {
+        assert!(
+            self.merge_checksums.map_or(true, |c| c == enabled),
+            "the value checksums must match the merge operator of the database"
+        );
+
+        self.checksums = enabled;
+        self
+    }
+
+    /// Configures the spans created by the operations on this view.
+    pub fn with_trace_config(mut self, config: TraceConfig) -> Self {
+        self.trace = config;
+        self
+    }
+
+    /// Returns the column family handle of this view, if the view is scoped to a column family.
+    fn cf_handle(&self) -> Result<Option<&ColumnFamily>> {
+        match &self.cf {
+            Some(name) => match self.db.cf_handle(name) {
+                Some(cf) => Ok(Some(cf)),
+                None => Err(log_err(
+                    Error::ColumnFamilyNotFound(name.clone()),
+                    &self.db_name,
+                )),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the order of the serialized keys.
+    pub(crate) fn compare(&self) -> CompareFn {
+        self.comparator.unwrap_or(bytewise)
+    }
+
+    /// Fails with [`Error::KeyOrderUnsupported`] if the database was opened with a comparator,
+    /// since the operation `op` relies on the keys being grouped by prefix.
+    pub(crate) fn ensure_bytewise(&self, op: &'static str) -> Result<()> {
+        match self.comparator {
+            Some(_) => Err(log_err(Error::KeyOrderUnsupported(op), &self.db_name)),
+            None => Ok(()),
+        }
+    }
+
+    /// Creates a batch of writes to be applied atomically on this database.
+    pub fn batch(&self) -> WriteBatch<K> {
+        WriteBatch {
+            batch: RawBatch::new(),
+            db: self,
+        }
+    }
+
+    /// Adds the removal of a key to a raw batch, scoped to the column family of this view.
+    pub(crate) fn batch_delete(&self, batch: &mut RawBatch, key: &K) -> Result<()> {
+        with_key_bytes(key, &self.db_name, |key| self.batch_delete_raw(batch, key))
+    }
+
+    /// Adds the removal of an already serialized key to a raw batch.
+    pub(crate) fn batch_delete_raw(&self, batch: &mut RawBatch, key: &[u8]) -> Result<()> {
+        match self.cf_handle()? {
+            Some(cf) => batch.inner.delete_cf(cf, key),
+            None => batch.inner.delete(key),
+        }
+        .map_err(|e| map_log_err(e, &self.db_name))?;
+
+        batch.journal(|| JournalOp::Delete {
+            cf: self.cf.clone(),
+            key: key.to_vec(),
+        });
+
+        batch.record(&self.hooks, ChangeKind::Delete, key, None);
+        Ok(())
+    }
+
+    /// Adds the insertion of a key / value to a raw batch, scoped to the column family of this view.
+    pub(crate) fn batch_put<V>(&self, batch: &mut RawBatch, key: &K, value: &V) -> Result<()>
+    where
+        V: Serialize,
+    {
+        with_key_bytes(key, &self.db_name, |key| {
+            self.batch_put_value(batch, key, value)
+        })
+    }
+
+    /// Adds the insertion of a value under a serialized key, with its checksum if enabled.
+    pub(crate) fn batch_put_value<V>(
+        &self,
+        batch: &mut RawBatch,
+        key: &[u8],
+        value: &V,
+    ) -> Result<()>
+    where
+        V: ?Sized + Serialize,
+    {
+        with_value_bytes(value, self.checksums, &self.db_name, |val| {
+            self.batch_put_bytes(batch, key, val, self.payload(val))
         })
     }
 
+    /// Adds the insertion of an already serialized value, appending its checksum if enabled.
+    pub(crate) fn batch_put_payload(
+        &self,
+        batch: &mut RawBatch,
+        key: &[u8],
+        payload: &[u8],
+    ) -> Result<()> {
+        if self.checksums {
+            let mut val = payload.to_vec();
+            append_checksum(&mut val);
+            self.batch_put_bytes(batch, key, &val, payload)
+        } else {
+            self.batch_put_bytes(batch, key, payload, payload)
+        }
+    }
+
+    /// Changes mutable options of the database at runtime, such as
+    /// `("disable_auto_compactions", "true")` or `("level0_slowdown_writes_trigger", "40")`.
+    ///
+    /// On a column family view, the options of the column family are changed.
+    pub fn set_options(&self, options: &[(&str, &str)]) -> Result<()> {
+        let span = db_span!(self, "set_options", options);
+        let _enter = span.enter();
+
+        match self.cf_handle()? {
+            Some(cf) => self.db.set_options_cf(cf, options),
+            None => self.db.set_options(options),
+        }
+        .map_err(|e| map_log_err(e, &self.db_name))
+    }
+
+    /// Reads all the records with checksum verification enabled, reporting the first corruption found.
+    ///
+    /// The other errors are returned as errors.
+    pub fn verify_checksums(&self) -> Result<ChecksumReport> {
+        let span = db_span!(self, "verify_checksums");
+        let _enter = span.enter();
+
+        let opts = IterOptions::bulk_scan().verify_checksums(true);
+        let mut iter = self.raw_iterator_opt(opts.read_options())?;
+        let mut last = None;
+        let mut report = ChecksumReport::default();
+
+        iter.seek_to_first();
+
+        while let Some(key) = iter.key() {
+            if key != META_KEY {
+                last = Some(key.to_vec());
+                report.checked += 1;
+            }
+
+            iter.next();
+        }
+
+        if let Err(e) = iter.status() {
+            let e = Error::from(e);
+
+            if !e.is_corruption() {
+                return Err(log_err(e, &self.db_name));
+            }
+
+            report.corruption = Some(CorruptedRange {
+                after: last,
+                message: log_err(e, &self.db_name).to_string(),
+            });
+        }
+
+        Ok(report)
+    }
+
+    /// Returns the estimated number of keys, from the `rocksdb.estimate-num-keys` property.
+    pub fn approximate_key_count(&self) -> Result<u64> {
+        self.property_u64("rocksdb.estimate-num-keys")
+    }
+
+    /// Returns the estimated size in bytes of the data, on disk and in the memtables.
+    pub fn approximate_size(&self) -> Result<u64> {
+        Ok(self.property_u64("rocksdb.total-sst-files-size")?
+            + self.property_u64("rocksdb.size-all-mem-tables")?)
+    }
+
+    /// Scans the database and returns the distribution of the sizes of the encoded keys,
+    /// recording one entry out of every `sample_every`.
+    pub fn key_size_histogram(&self, sample_every: u64) -> Result<SizeHistogram> {
+        let span = db_span!(self, "key_size_histogram");
+        let _enter = span.enter();
+        self.size_histogram(sample_every, |key, _| key.len())
+    }
+
+    /// Scans the database and returns the distribution of the sizes of the encoded values,
+    /// recording one entry out of every `sample_every`.
+    pub fn value_size_histogram(&self, sample_every: u64) -> Result<SizeHistogram> {
+        let span = db_span!(self, "value_size_histogram");
+        let _enter = span.enter();
+        self.size_histogram(sample_every, |_, value| value.len())
+    }
+
+    /// Removes all the keys of the database, keeping the metadata.
+    pub fn clear(&self) -> Result<()> {
+        let span = db_span!(self, "clear");
+        let _enter = span.enter();
+
+        let mut iter = self.raw_iterator()?;
+
+        if self.comparator.is_some() {
+            // the keys do not sort bytewise, they are removed one by one.
+            let mut batch = RawBatch::new();
+            iter.seek_to_first();
+
+            while let Some(key) = iter.key() {
+                if key != META_KEY {
+                    match self.cf_handle()? {
+                        Some(cf) => batch.inner.delete_cf(cf, key),
+                        None => batch.inner.delete(key),
+                    }
+                    .map_err(|e| map_log_err(e, &self.db_name))?;
+                }
+
+                iter.next();
+            }
+
+            iter.status().map_err(|e| map_log_err(e, &self.db_name))?;
+            batch.record(&self.hooks, ChangeKind::Clear, &[], None);
+            return self.write_raw(batch);
+        }
+
+        iter.seek_to_last();
+        iter.status().map_err(|e| map_log_err(e, &self.db_name))?;
+
+        let to = match iter.key() {
+            // the smallest key greater than the last key.
+            Some(last) if last != META_KEY => [last, &[0]].concat(),
+            _ => return Ok(()),
+        };
+
+        let mut batch = RawBatch::new();
+
+        match self.cf_handle()? {
+            Some(cf) => batch.inner.delete_range_cf(cf, &[0], &to),
+            None => batch.inner.delete_range(&[0], &to),
+        }
+        .map_err(|e| map_log_err(e, &self.db_name))?;
+
+        batch.record(&self.hooks, ChangeKind::Clear, &[], None);
+        self.write_raw(batch)
+    }
+
+    /// Compacts the keys in the range `from..=to`, an unbounded side being `None`.
+    ///
+    /// The call blocks until the compaction is done.
+    pub fn compact_range(&self, from: Option<&K>, to: Option<&K>) -> Result<()> {
+        let span = db_span!(
+            self,
+            "compact_range",
+            format_args!("from = {:?}, to = {:?}", from, to)
+        );
+        let _enter = span.enter();
+
+        let from = from.map(|k| key_to_bytes(k, &self.db_name)).transpose()?;
+        let to = to.map(|k| key_to_bytes(k, &self.db_name)).transpose()?;
+
+        match self.cf_handle()? {
+            Some(cf) => self.db.compact_range_cf(cf, from, to),
+            None => self.db.compact_range(from, to),
+        }
+
+        Ok(())
+    }
+
     pub fn contains_key(&self, key: &K) -> Result<bool> {
-        let _ = trace_span!(
-            "contains_key",
-            db.name = self.db_name.as_str(),
-            db.statement = ?key,
-            db.system = "rocksdb",
-        )
-        .enter();
+        let span = db_span!(self, "contains_key", key);
+        let _enter = span.enter();
 
         Ok(self.get_raw(key)?.is_some())
     }
 
     pub fn delete(&self, key: &K) -> Result<()> {
-        let _ = trace_span!(
-            "delete",
-            db.name = self.db_name.as_str(),
-            db.statement = ?key,
-            db.system = "rocksdb",
-        )
-        .enter();
+        self.delete_with(key, self.write_mode())
+    }
+
+    /// Removes a key, with the durability of `mode`.
+    pub fn delete_with(&self, key: &K, mode: WriteMode) -> Result<()> {
+        let span = db_span!(self, "delete", key);
+        let _enter = span.enter();
+
+        with_key_bytes(key, &self.db_name, |key| {
+            let timer = stats::timer("delete", &self.db_name);
+
+            match self.cf_handle()? {
+                Some(cf) => self.db.delete_cf_opt(cf, key, &mode.options()),
+                None => self.db.delete_opt(key, &mode.options()),
+            }
+            .map_err(|e| map_op_err(e, "delete", &self.db_name, Some(key)))?;
+
+            drop(timer);
+            stats::bytes_written(&self.db_name, key.len());
+
+            self.notify(ChangeKind::Delete, key, None);
+            Ok(())
+        })
+    }
+
+    /// Removes all the keys in the range `from..to`, `to` being excluded.
+    ///
+    /// With a [comparator](Self::open_with_comparator), the keys are removed one by one.
+    pub fn delete_range(&self, from: &K, to: &K) -> Result<()> {
+        let span = db_span!(
+            self,
+            "delete_range",
+            format_args!("from = {:?}, to = {:?}", from, to)
+        );
+        let _enter = span.enter();
+
+        let from = key_to_bytes(from, &self.db_name)?;
+        let to = key_to_bytes(to, &self.db_name)?;
+        let mut batch = RawBatch::new();
+
+        self.batch_delete_range_raw(&mut batch, &from, &to)?;
+        self.write_raw(batch)
+    }
+
+    /// Removes the keys of `range` whose record matches `f`, returning the number of keys removed.
+    ///
+    /// The range is read from a consistent view while the deletes are written, in batches of
+    /// 1000 keys, so the records can safely be tested against the state before the deletion.
+    pub fn delete_where<R, F>(&self, range: R, f: F) -> Result<u64>
+    where
+        F: FnMut(&K, &DbKeyValue<K>) -> bool,
+        R: RangeBounds<K>,
+    {
+        self.delete_where_with(range, 1000, f, |_| ())
+    }
+
+    /// Same as [`Db::delete_where`], writing batches of `batch_len` keys and calling `progress`
+    /// with the number of keys removed so far after each batch.
+    pub fn delete_where_with<R, F, P>(
+        &self,
+        range: R,
+        batch_len: usize,
+        mut f: F,
+        mut progress: P,
+    ) -> Result<u64>
+    where
+        F: FnMut(&K, &DbKeyValue<K>) -> bool,
+        P: FnMut(u64),
+        R: RangeBounds<K>,
+    {
+        assert!(batch_len > 0);
+
+        let span = db_span!(self, "delete_where");
+        let _enter = span.enter();
+
+        let mut batch = RawBatch::new();
+        let mut count = 0;
+        let mut iter = self.iter_range(range)?;
+
+        while let Some(item) = iter.next()? {
+            if f(&item.key()?, &item) {
+                self.batch_delete_raw(&mut batch, item.key_as_bytes()?)?;
+                count += 1;
+
+                if batch.len() >= batch_len {
+                    self.write_raw(replace(&mut batch, RawBatch::new()))?;
+                    progress(count);
+                }
+            }
+        }
+
+        if !batch.is_empty() {
+            self.write_raw(batch)?;
+            progress(count);
+        }
+
+        Ok(count)
+    }
+
+    /// Removes all the keys starting with the serialized bytes of `prefix`.
+    pub fn delete_prefix<P>(&self, prefix: &P) -> Result<()>
+    where
+        P: Debug + Serialize,
+    {
+        let span = db_span!(self, "delete_prefix", prefix);
+        let _enter = span.enter();
+
+        let mut batch = RawBatch::new();
+        self.batch_delete_prefix(&mut batch, prefix)?;
+        self.write_raw(batch)
+    }
+
+    /// Adds the removal of all the keys starting with the serialized bytes of `prefix` to a raw
+    /// batch.
+    pub(crate) fn batch_delete_prefix<P>(&self, batch: &mut RawBatch, prefix: &P) -> Result<()>
+    where
+        P: Debug + Serialize,
+    {
+        let from = key_prefix_to_bytes(prefix, &self.db_name)?;
+
+        match prefix_successor(&from).filter(|_| self.comparator.is_none()) {
+            Some(to) => self.batch_delete_range_raw(batch, &from, &to)?,
+            None => {
+                // the prefix has no successor, or the keys of the prefix are not contiguous
+                // with a comparator, keys are removed one by one.
+                let grouped = self.comparator.is_none();
+                let mut iter = self.raw_iterator()?;
+
+                match grouped {
+                    true => iter.seek(&from),
+                    false => iter.seek_to_first(),
+                }
+
+                while let Some(key) = iter.key() {
+                    if !key.starts_with(&from) {
+                        if grouped {
+                            break;
+                        }
+
+                        iter.next();
+                        continue;
+                    }
+
+                    match self.cf_handle()? {
+                        Some(cf) => batch.inner.delete_cf(cf, key),
+                        None => batch.inner.delete(key),
+                    }
+                    .map_err(|e| map_log_err(e, &self.db_name))?;
+
+                    batch.record(&self.hooks, ChangeKind::Delete, key, None);
+
+                    iter.next();
+                }
+
+                iter.status().map_err(|e| map_log_err(e, &self.db_name))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds the removal of the serialized keys in `from..to` to a raw batch.
+    pub(crate) fn batch_delete_range_raw(
+        &self,
+        batch: &mut RawBatch,
+        from: &[u8],
+        to: &[u8],
+    ) -> Result<()> {
+        if let Some(compare) = self.comparator {
+            // a range delete compares bytewise, the keys of the range are removed one by one.
+            let mut iter = self.raw_iterator()?;
+            iter.seek(from);
+
+            while let Some(key) = iter.key() {
+                if compare(key, to) != CmpOrdering::Less {
+                    break;
+                }
 
-        let key = serialize_to_bytes(key, &self.db_name)?;
+                self.batch_delete_raw(batch, key)?;
+                iter.next();
+            }
+
+            return iter.status().map_err(|e| map_log_err(e, &self.db_name));
+        }
+
+        match self.cf_handle()? {
+            Some(cf) => batch.inner.delete_range_cf(cf, from, to),
+            None => batch.inner.delete_range(from, to),
+        }
+        .map_err(|e| map_log_err(e, &self.db_name))?;
+
+        batch.journal(|| JournalOp::DeleteRange {
+            cf: self.cf.clone(),
+            from: from.to_vec(),
+            to: to.to_vec(),
+        });
+
+        batch.record(&self.hooks, ChangeKind::DeleteRange, from, Some(to));
+        Ok(())
+    }
+
+    /// Writes all the keys / values of this database, metadata included, to `w` in a portable
+    /// format, returning the number of entries written.
+    ///
+    /// The writer can be wrapped in an encoder to compress the export.
+    pub fn export_to_writer<W: Write>(&self, mut w: W) -> Result<u64> {
+        let span = db_span!(self, "export_to_writer");
+        let _enter = span.enter();
+
+        let mut count = 0;
+        let mut iter = self.raw_iterator()?;
+        iter.seek_to_first();
+
+        write_header(&mut w).map_err(|e| log_err(e, &self.db_name))?;
+
+        while let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+            write_entry(&mut w, key, value).map_err(|e| log_err(e, &self.db_name))?;
+            count += 1;
+            iter.next();
+        }
+
+        iter.status().map_err(|e| map_log_err(e, &self.db_name))?;
+        write_end(&mut w).map_err(|e| log_err(e, &self.db_name))?;
+        Ok(count)
+    }
+
+    /// Writes all the records to a CSV file, each one projected into a row by `projector`,
+    /// and returns the number of rows written.
+    ///
+    /// The header is made of the field names of the rows, which are usually structs.
+    #[cfg(feature = "csv")]
+    pub fn export_csv<P, V, R, F>(&self, path: P, mut projector: F) -> Result<u64>
+    where
+        F: FnMut(K, V) -> R,
+        K: DeserializeOwned,
+        P: AsRef<Path>,
+        R: Serialize,
+        V: DeserializeOwned,
+    {
+        let span = db_span!(self, "export_csv");
+        let _enter = span.enter();
+
+        let mut count = 0;
+        let mut iter = self.iter_opt(IteratorMode::Start, &IterOptions::bulk_scan())?;
+        let mut writer = csv::Writer::from_path(path).map_err(|e| log_err(e, &self.db_name))?;
+
+        while let Some(item) = iter.next()? {
+            let row = projector(item.key()?, item.value()?);
+            writer
+                .serialize(row)
+                .map_err(|e| log_err(e, &self.db_name))?;
+            count += 1;
+        }
+
+        writer.flush().map_err(|e| log_err(e, &self.db_name))?;
+        Ok(count)
+    }
+
+    /// Flushes the memtables and closes the database, releasing its file lock.
+    ///
+    /// Fails with [`Error::InUse`] if the database is still shared with clones or
+    /// other column family views, which keep it open.
+    pub fn close(self) -> Result<()> {
+        let span = db_span!(self, "close");
+        let _enter = span.enter();
 
-        self.db
-            .delete(&key)
-            .map_err(|e| map_log_err(e, &self.db_name))
+        self.flush()?;
+
+        match Arc::try_unwrap(self.db) {
+            Ok(db) => {
+                drop(db);
+                Ok(())
+            }
+            Err(_) => Err(log_err(Error::InUse, &self.db_name)),
+        }
+    }
+
+    /// Flushes the memtables to disk.
+    pub fn flush(&self) -> Result<()> {
+        let span = db_span!(self, "flush");
+        let _enter = span.enter();
+
+        match self.cf_handle()? {
+            Some(cf) => self.db.flush_cf(cf),
+            None => self.db.flush(),
+        }
+        .map_err(|e| map_log_err(e, &self.db_name))
     }
 
     /// Gets a value from the database.
     pub fn get(&self, key: &K) -> Result<Option<DbValue>> {
-        let _ = trace_span!(
-            "get",
-            db.name = self.db_name.as_str(),
-            db.statement = ?key,
-            db.system = "rocksdb",
-        )
-        .enter();
+        self.get_with(key)
+    }
+
+    /// Gets a value with tuned read options, such as a read verifying the checksums or not
+    /// filling the block cache.
+    pub fn get_opt(&self, key: &K, opts: &IterOptions) -> Result<Option<DbValue>> {
+        let span = db_span!(self, "get", key);
+        let _enter = span.enter();
+
+        let value = with_key_bytes(key, &self.db_name, |key| {
+            self.get_pinned_opt(key, &opts.read_options())
+        })?;
+
+        Ok(value.map(|bytes| DbValue {
+            bytes: ValueBytes::Pinned(bytes),
+            checksum: self.checksums,
+            db_name: &self.db_name,
+        }))
+    }
 
-        Ok(self.get_raw(key)?.map(|bytes| DbValue {
-            bytes,
+    /// Gets a value by a borrowed form of the key, such as a `&str` for a `String` key.
+    ///
+    /// `Q` must serialize to the same bytes as the key it stands for.
+    pub fn get_with<Q: ?Sized>(&self, key: &Q) -> Result<Option<DbValue>>
+    where
+        Q: Debug + Serialize,
+    {
+        let span = db_span!(self, "get", key);
+        let _enter = span.enter();
+
+        let value = with_key_bytes(key, &self.db_name, |key| self.get_pinned(key))?;
+
+        Ok(value.map(|bytes| DbValue {
+            bytes: ValueBytes::Pinned(bytes),
+            checksum: self.checksums,
             db_name: &self.db_name,
         }))
     }
 
+    /// Gets a value stored as an `Option<V>`, telling a missing key apart from a stored `None`.
+    pub fn get_or_tombstone<V>(&self, key: &K) -> Result<MaybeTombstone<V>>
+    where
+        V: DeserializeOwned,
+    {
+        Ok(match self.get(key)? {
+            Some(value) => match value.to_inner::<Option<V>>()? {
+                Some(v) => MaybeTombstone::Present(v),
+                None => MaybeTombstone::Tombstone,
+            },
+            None => MaybeTombstone::Absent,
+        })
+    }
+
+    /// Gets a value stored by [`Db::put_raw`], read with [`DbValue::as_bytes`] without deserialization.
+    pub fn get_raw_bytes(&self, key: &K) -> Result<Option<DbValue>> {
+        self.get(key)
+    }
+
     fn get_raw<'a>(&'a self, key: &K) -> Result<Option<DBPinnableSlice<'a>>> {
-        let key = serialize_to_bytes(key, &self.db_name)?;
+        with_key_bytes(key, &self.db_name, |key| self.get_pinned(key))
+    }
+
+    fn get_pinned(&self, key: &[u8]) -> Result<Option<DBPinnableSlice>> {
+        self.get_pinned_opt(key, &ReadOptions::default())
+    }
+
+    fn get_pinned_opt(&self, key: &[u8], opts: &ReadOptions) -> Result<Option<DBPinnableSlice>> {
+        let _timer = stats::timer("get", &self.db_name);
+
+        let r = match self.cf_handle()? {
+            Some(cf) => self.db.get_pinned_cf_opt(cf, key, opts),
+            None => self.db.get_pinned_opt(key, opts),
+        };
 
-        match self.db.get_pinned(&key) {
-            Ok(Some(value)) => Ok(Some(value)),
+        match r {
+            Ok(Some(value)) => {
+                stats::bytes_read(&self.db_name, value.len());
+                Ok(Some(value))
+            }
             Ok(None) => Ok(None),
-            Err(e) => Err(map_log_err(e, &self.db_name)),
+            Err(e) => Err(map_op_err(e, "get", &self.db_name, Some(key))),
         }
     }
 
-    pub fn iter(&self, mode: IteratorMode<K>) -> Result<Iter<K>> {
-        let span = Arc::new(trace_span!(
-            "iter",
-            db.name = self.db_name.as_str(),
-            db.statement = format!("mode = {:?}", mode).as_str(),
-            db.system = "rocksdb",
-        ));
-        let _ = span.enter();
+    /// Adds a merge operand to the key, combined with the existing value by the merge operator.
+    pub fn merge<O>(&self, key: &K, operand: &O) -> Result<()>
+    where
+        O: Serialize,
+    {
+        let span = db_span!(self, "merge", key);
+        let _enter = span.enter();
+
+        with_key_bytes(key, &self.db_name, |key| {
+            with_value_bytes(operand, false, &self.db_name, |val| {
+                let timer = stats::timer("merge", &self.db_name);
+
+                match self.cf_handle()? {
+                    Some(cf) => self.db.merge_cf(cf, key, val),
+                    None => self.db.merge(key, val),
+                }
+                .map_err(|e| map_op_err(e, "merge", &self.db_name, Some(key)))?;
+
+                drop(timer);
+                stats::bytes_written(&self.db_name, key.len() + val.len());
+
+                self.notify(ChangeKind::Merge, key, Some(val));
+                Ok(())
+            })
+        })
+    }
+
+    /// Gets the values of many keys in a single RocksDB call, in the same order as `keys`.
+    ///
+    /// The values are copied out of RocksDB rather than pinned.
+    pub fn multi_get(&self, keys: &[K]) -> Result<Vec<Option<DbValue>>> {
+        let span = db_span!(self, "multi_get", keys);
+        let _enter = span.enter();
+
+        let keys = keys
+            .iter()
+            .map(|key| key_to_bytes(key, &self.db_name))
+            .collect::<Result<Vec<_>>>()?;
+
+        let _timer = stats::timer("multi_get", &self.db_name);
+
+        let values = match self.cf_handle()? {
+            Some(cf) => self.db.multi_get_cf(keys.iter().map(|key| (cf, key))),
+            None => self.db.multi_get(&keys),
+        };
+
+        values
+            .into_iter()
+            .zip(&keys)
+            .map(|(value, key)| match value {
+                Ok(Some(bytes)) => {
+                    stats::bytes_read(&self.db_name, bytes.len());
+
+                    Ok(Some(DbValue {
+                        bytes: ValueBytes::Owned(bytes),
+                        checksum: self.checksums,
+                        db_name: &self.db_name,
+                    }))
+                }
+                Ok(None) => Ok(None),
+                Err(e) => Err(map_op_err(e, "multi_get", &self.db_name, Some(key))),
+            })
+            .collect()
+    }
+
+    pub fn iter(&self, mode: IteratorMode<K>) -> Result<Iter<K>> {
+        self.iter_opt(mode, &IterOptions::default())
+    }
+
+    /// Iterates the records with tuned read options, such as [`IterOptions::bulk_scan`] for full scans.
+    pub fn iter_opt(&self, mode: IteratorMode<K>, opts: &IterOptions) -> Result<Iter<K>> {
+        let span = db_span!(self, "iter", format_args!("mode = {:?}", mode));
+        let _enter = span.enter();
+
+        let mut iter = self.raw_iterator_opt(opts.read_options())?;
+
+        let dir = match mode {
+            IteratorMode::From(k, dir) => {
+                with_key_bytes(&k, &self.db_name, |key| {
+                    match dir {
+                        Direction::Forward => iter.seek(key),
+                        Direction::Reverse => iter.seek_for_prev(key),
+                    }
+
+                    Ok(())
+                })?;
+
+                dir
+            }
+            IteratorMode::End => {
+                iter.seek_to_last();
+                Direction::Reverse
+            }
+            IteratorMode::Start => {
+                iter.seek_to_first();
+                Direction::Forward
+            }
+        };
+
+        Ok(Iter {
+            _k: PhantomData,
+            checksum: self.checksums,
+            compare: self.compare(),
+            dir,
+            db_name: &self.db_name,
+            end: Bound::Unbounded,
+            iter,
+            must_call_next: false,
+            prefix: None,
+        })
+    }
+
+    /// Iterates the records, decoding each key / value into owned values.
+    ///
+    /// Unlike [`Db::iter`], the returned iterator implements [`Iterator`] and can be used with
+    /// the standard adaptors.
+    pub fn iter_decoded<V>(&self, mode: IteratorMode<K>) -> Result<DecodedIter<K, V>>
+    where
+        V: for<'de> Deserialize<'de>,
+    {
+        Ok(DecodedIter::new(self.iter(mode)?))
+    }
+
+    /// Iterates forward over all the keys starting with the serialized bytes of `prefix`.
+    ///
+    /// Since a tuple key starts with the encoding of its first fields, `prefix` can be the first fields of a tuple key.
+    pub fn iter_prefix<P>(&self, prefix: &P) -> Result<Iter<K>>
+    where
+        P: Debug + Serialize,
+    {
+        self.iter_prefix_opts(prefix, false)
+    }
+
+    /// Iterates the keys of a prefix matching the prefix extractor of the database,
+    /// allowing RocksDB to use the prefix bloom filters.
+    pub(crate) fn iter_prefix_same_as_start<P>(&self, prefix: &P) -> Result<Iter<K>>
+    where
+        P: Debug + Serialize,
+    {
+        self.iter_prefix_opts(prefix, true)
+    }
+
+    fn iter_prefix_opts<P>(&self, prefix: &P, same_as_start: bool) -> Result<Iter<K>>
+    where
+        P: Debug + Serialize,
+    {
+        let span = db_span!(self, "iter_prefix", prefix);
+        let _enter = span.enter();
+
+        self.ensure_bytewise("iter_prefix")?;
+
+        let prefix = key_prefix_to_bytes(prefix, &self.db_name)?;
+        let mut opts = ReadOptions::default();
+        opts.set_prefix_same_as_start(same_as_start);
+
+        let mut iter = match self.cf_handle()? {
+            Some(cf) => self.db.raw_iterator_cf_opt(cf, opts),
+            None => self.db.raw_iterator_opt(opts),
+        };
+
+        iter.seek(&prefix);
+
+        Ok(Iter {
+            _k: PhantomData,
+            checksum: self.checksums,
+            compare: self.compare(),
+            dir: Direction::Forward,
+            db_name: &self.db_name,
+            end: Bound::Unbounded,
+            iter,
+            must_call_next: false,
+            prefix: Some(prefix),
+        })
+    }
+
+    /// Iterates forward over all the keys contained in `range`.
+    pub fn iter_range<R>(&self, range: R) -> Result<Iter<K>>
+    where
+        R: RangeBounds<K>,
+    {
+        self.iter_range_dir(range, Direction::Forward)
+    }
+
+    /// Iterates over all the keys contained in `range` in the order of `dir`, so a reverse scan
+    /// starts from the end of the range.
+    pub fn iter_range_dir<R>(&self, range: R, dir: Direction) -> Result<Iter<K>>
+    where
+        R: RangeBounds<K>,
+    {
+        let span = db_span!(
+            self,
+            "iter_range",
+            format_args!(
+                "start = {:?}, end = {:?}, dir = {:?}",
+                range.start_bound(),
+                range.end_bound(),
+                dir
+            )
+        );
+        let _enter = span.enter();
+
+        let start = bound_to_bytes(range.start_bound(), &self.db_name)?;
+        let end = bound_to_bytes(range.end_bound(), &self.db_name)?;
+
+        self.iter_raw_range(start, end, dir)
+    }
+
+    /// Iterates over the serialized keys between `start` and `end` in the order of `dir`.
+    pub(crate) fn iter_raw_range(
+        &self,
+        start: Bound<Vec<u8>>,
+        end: Bound<Vec<u8>>,
+        dir: Direction,
+    ) -> Result<Iter<K>> {
+        let mut iter = self.raw_iterator()?;
+
+        // seeks the first key of the scan, then stops at the other bound.
+        let (first, last) = match dir {
+            Direction::Forward => (start, end),
+            Direction::Reverse => (end, start),
+        };
+
+        match &first {
+            Bound::Included(key) | Bound::Excluded(key) => {
+                match dir {
+                    Direction::Forward => iter.seek(key),
+                    Direction::Reverse => iter.seek_for_prev(key),
+                }
+
+                if let Bound::Excluded(_) = first {
+                    if iter.valid() && iter.key() == Some(&key[..]) {
+                        match dir {
+                            Direction::Forward => iter.next(),
+                            Direction::Reverse => iter.prev(),
+                        }
+                    }
+                }
+            }
+            Bound::Unbounded => match dir {
+                Direction::Forward => iter.seek_to_first(),
+                Direction::Reverse => iter.seek_to_last(),
+            },
+        }
+
+        Ok(Iter {
+            _k: PhantomData,
+            checksum: self.checksums,
+            compare: self.compare(),
+            dir,
+            db_name: &self.db_name,
+            end: last,
+            iter,
+            must_call_next: false,
+            prefix: None,
+        })
+    }
+
+    /// Reads up to `limit` records from `mode`, with a cursor to read the next page
+    /// if there may be more records.
+    pub fn page<V>(
+        &self,
+        mode: IteratorMode<K>,
+        limit: usize,
+    ) -> Result<(Vec<(K, V)>, Option<Cursor>)>
+    where
+        V: for<'de> Deserialize<'de>,
+    {
+        read_page(self.iter(mode)?, None, limit)
+    }
+
+    /// Reads up to `limit` records following the last record of the page that returned `cursor`.
+    pub fn page_after<V>(
+        &self,
+        cursor: &Cursor,
+        limit: usize,
+    ) -> Result<(Vec<(K, V)>, Option<Cursor>)>
+    where
+        V: for<'de> Deserialize<'de>,
+    {
+        let mut iter = self.iter(match cursor.dir {
+            Direction::Forward => IteratorMode::Start,
+            Direction::Reverse => IteratorMode::End,
+        })?;
+
+        iter.seek_raw(&cursor.key);
+        read_page(iter, Some(&cursor.key), limit)
+    }
+
+    #[inline]
+    pub(crate) fn raw_db(&self) -> &Arc<rocksdb::DB> {
+        &self.db
+    }
+
+    /// Writes `new` only if the stored value is still equal to `expected`, returning
+    /// [`Error::Conflict`] otherwise. An `expected` of `None` means that the key must be absent.
+    ///
+    /// The check and the write are atomic with respect to the other `put_if` calls made on the
+    /// same database; plain `put` and `delete` calls are not serialized with them.
+    pub fn put_if<V>(&self, key: &K, expected: Option<&V>, new: &V) -> Result<()>
+    where
+        V: Serialize,
+    {
+        let span = db_span!(self, "put_if", key);
+        let _enter = span.enter();
+
+        let expected = match expected {
+            Some(v) => Some(with_value_bytes(v, self.checksums, &self.db_name, |b| {
+                Ok(b.to_vec())
+            })?),
+            None => None,
+        };
+
+        if self.write_if_unchanged(key, expected.as_deref(), Some(new))? {
+            Ok(())
+        } else {
+            Err(log_err(Error::Conflict, &self.db_name))
+        }
+    }
+
+    /// Writes `new`, or deletes the key if it is `None`, only if the stored bytes are still
+    /// `expected`. Returns false on a conflict.
+    fn write_if_unchanged<V>(
+        &self,
+        key: &K,
+        expected: Option<&[u8]>,
+        new: Option<&V>,
+    ) -> Result<bool>
+    where
+        V: Serialize,
+    {
+        let _guard = self.cas_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let current = self.get_raw(key)?;
+
+        if current.as_deref() != expected {
+            return Ok(false);
+        }
+
+        drop(current);
+        self.put_opt(key, new)?;
+        Ok(true)
+    }
+
+    /// Writes the value of the key, or deletes the key if the value is `None`, so that
+    /// [`Db::contains_key`] reflects the presence of a value.
+    pub fn put_opt<V>(&self, key: &K, value: Option<&V>) -> Result<()>
+    where
+        V: Serialize,
+    {
+        match value {
+            Some(value) => self.put(key, value),
+            None => self.delete(key),
+        }
+    }
+
+    /// Writes a key / value and returns the value it replaces, if any, without a separate `get`.
+    ///
+    /// The read and the write are atomic with respect to [`Db::put_if`] and other `put_get_old` calls.
+    pub fn put_get_old<V>(&self, key: &K, value: &V) -> Result<Option<DbValue>>
+    where
+        V: Serialize,
+    {
+        let span = db_span!(self, "put_get_old", key);
+        let _enter = span.enter();
+
+        let _guard = self.cas_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let old = self.get_raw(key)?;
+
+        self.put(key, value)?;
+
+        Ok(old.map(|bytes| DbValue {
+            bytes: ValueBytes::Pinned(bytes),
+            checksum: self.checksums,
+            db_name: &self.db_name,
+        }))
+    }
+
+    /// Reads the value of the key, computes its new value with `f` and writes it, or deletes
+    /// the key if `f` returns `None`. Returns the value written.
+    ///
+    /// If the value is modified by a concurrent `rmw` or [`Db::put_if`] between the read and
+    /// the write, `f` is called again on the new value, as configured by the default
+    /// [`RetryPolicy`].
+    pub fn rmw<V, F>(&self, key: &K, f: F) -> Result<Option<V>>
+    where
+        F: FnMut(Option<V>) -> Option<V>,
+        V: DeserializeOwned + Serialize,
+    {
+        self.rmw_with(key, &RetryPolicy::default(), f)
+    }
+
+    /// Same as [`Db::rmw`], retrying the conflicts as configured by `policy`.
+    ///
+    /// Returns [`Error::Conflict`] when all the attempts conflicted.
+    pub fn rmw_with<V, F>(&self, key: &K, policy: &RetryPolicy, mut f: F) -> Result<Option<V>>
+    where
+        F: FnMut(Option<V>) -> Option<V>,
+        V: DeserializeOwned + Serialize,
+    {
+        let span = db_span!(self, "rmw", key);
+        let _enter = span.enter();
+
+        for attempt in 0..policy.attempts() {
+            if attempt > 0 {
+                sleep(policy.backoff(attempt - 1));
+            }
+
+            let current = self.get_raw(key)?.map(|v| v.to_vec());
+
+            let old = match &current {
+                Some(bytes) => Some(value_from_bytes(bytes, self.checksums, &self.db_name)?),
+                None => None,
+            };
+
+            let new = f(old);
+
+            if self.write_if_unchanged(key, current.as_deref(), new.as_ref())? {
+                return Ok(new);
+            }
+        }
+
+        Err(log_err(Error::Conflict, &self.db_name))
+    }
+
+    /// Reads the value of the key, applies `update` to it and writes the result.
+    ///
+    /// The read and the write are atomic with respect to [`Db::put_if`], [`Db::rmw`] and other
+    /// `update` calls made on the same database.
+    pub fn update<V, U>(&self, key: &K, update: U) -> Result<()>
+    where
+        U: UpdateFrom<V>,
+        V: DeserializeOwned + Serialize,
+    {
+        let span = db_span!(self, "update", key);
+        let _enter = span.enter();
+
+        let _guard = self.cas_lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        let old = match self.get_raw(key)? {
+            Some(bytes) => Some(value_from_bytes(&bytes, self.checksums, &self.db_name)?),
+            None => None,
+        };
+
+        self.put(key, &update.update_from(old))
+    }
+
+    /// Registers a callback invoked after each write on this database, batches included.
+    ///
+    /// The callbacks are shared by the clones of this `Db` and run on the writing thread.
+    /// A callback must not subscribe to the same database.
+    pub fn subscribe<F>(&self, f: F)
+    where
+        F: Fn(&ChangeEvent<K>) + Send + Sync + 'static,
+        K: 'static,
+    {
+        self.hooks.add(Box::new(move |change| {
+            f(&ChangeEvent::new(change.clone()));
+            true
+        }));
+    }
+
+    /// Registers a callback like [`subscribe`](Self::subscribe), removed once it returns false.
+    pub(crate) fn subscribe_while<F>(&self, f: F)
+    where
+        F: Fn(&ChangeEvent<K>) -> bool + Send + Sync + 'static,
+        K: 'static,
+    {
+        self.hooks
+            .add(Box::new(move |change| f(&ChangeEvent::new(change.clone()))));
+    }
+
+    /// Returns the sequence number of the last write on the database.
+    pub fn latest_sequence_number(&self) -> u64 {
+        self.db.latest_sequence_number()
+    }
+
+    /// Reads from the write-ahead log the writes made since the sequence number `sequence`,
+    /// so a replica process can follow the changes made by the primary.
+    ///
+    /// The log files must be kept long enough to be read, see the `WAL_ttl_seconds` and
+    /// `WAL_size_limit_MB` options. Only the puts and the deletes of the default column family
+    /// are read: the merges and the range deletes are skipped, and a view on a column family
+    /// fails with [`Error::ColumnFamilyUnsupported`]. The temporary databases, which skip the
+    /// log, cannot be tailed.
+    pub fn tail_from_sequence<V>(&self, sequence: u64) -> Result<Tail<K, V>> {
+        let span = db_span!(self, "tail_from_sequence");
+        let _enter = span.enter();
+
+        if self.cf.is_some() {
+            return Err(log_err(
+                Error::ColumnFamilyUnsupported("tail_from_sequence"),
+                &self.db_name,
+            ));
+        }
+
+        let iter = self
+            .db
+            .get_updates_since(sequence)
+            .map_err(|e| map_log_err(e, &self.db_name))?;
+
+        Ok(Tail::new(
+            Arc::clone(&self.db),
+            self.db_name.clone(),
+            self.checksums,
+            iter,
+            sequence,
+        ))
+    }
+
+    /// Returns a channel receiving the changes written on this database.
+    ///
+    /// The channel is unsubscribed by the first write after the receiver is dropped.
+    pub fn watch(&self) -> Receiver<ChangeEvent<K>>
+    where
+        K: 'static,
+    {
+        let (tx, rx) = channel();
+        let tx = Mutex::new(tx);
+
+        self.hooks.add(Box::new(move |change| {
+            let tx = tx.lock().unwrap_or_else(|e| e.into_inner());
+            tx.send(ChangeEvent::new(change.clone())).is_ok()
+        }));
+
+        rx
+    }
+
+    /// Returns the serialized value of bytes written by `with_value_bytes`, without their checksum.
+    fn payload<'b>(&self, bytes: &'b [u8]) -> &'b [u8] {
+        match self.checksums && bytes.len() >= 4 {
+            true => &bytes[..bytes.len() - 4],
+            false => bytes,
+        }
+    }
+
+    fn notify(&self, kind: ChangeKind, key: &[u8], value: Option<&[u8]>) {
+        if !self.hooks.is_empty() {
+            self.hooks.notify(&Change {
+                kind,
+                key: key.to_vec(),
+                value: value.map(|v| v.to_vec()),
+            });
+        }
+    }
+
+    /// Blocks until there are no more pending or running background compactions.
+    pub fn wait_for_compact(&self) -> Result<()> {
+        let span = db_span!(self, "wait_for_compact");
+        let _enter = span.enter();
+
+        while self.property_u64("rocksdb.compaction-pending")? > 0
+            || self.property_u64("rocksdb.num-running-compactions")? > 0
+        {
+            sleep(Duration::from_millis(50));
+        }
+
+        Ok(())
+    }
+
+    fn raw_iterator(&self) -> Result<DBRawIterator> {
+        Ok(match self.cf_handle()? {
+            Some(cf) => self.db.raw_iterator_cf(cf),
+            None => self.db.raw_iterator(),
+        })
+    }
+
+    fn raw_iterator_opt(&self, opts: ReadOptions) -> Result<DBRawIterator> {
+        Ok(match self.cf_handle()? {
+            Some(cf) => self.db.raw_iterator_cf_opt(cf, opts),
+            None => self.db.raw_iterator_opt(opts),
+        })
+    }
+
+    fn size_histogram<F>(&self, sample_every: u64, size: F) -> Result<SizeHistogram>
+    where
+        F: Fn(&[u8], &[u8]) -> usize,
+    {
+        let mut histogram = SizeHistogram::new(sample_every);
+        let mut iter = self.raw_iterator_opt(IterOptions::bulk_scan().read_options())?;
+        iter.seek_to_first();
+
+        while let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+            if key != META_KEY {
+                histogram.record(size(key, value));
+            }
+
+            iter.next();
+        }
+
+        iter.status().map_err(|e| map_log_err(e, &self.db_name))?;
+        Ok(histogram)
+    }
+
+    /// Reads a metadata entry stored under the reserved meta key.
+    pub(crate) fn get_meta<T>(&self, name: &str) -> Result<Option<T>>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        match self.read_meta()?.get(name) {
+            Some(bytes) => Ok(Some(deserialize_from_bytes(bytes, &self.db_name)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn read_meta(&self) -> Result<BTreeMap<String, Vec<u8>>> {
+        match self.get_pinned(META_KEY)? {
+            Some(bytes) => deserialize_from_bytes(&bytes, &self.db_name),
+            None => Ok(BTreeMap::new()),
+        }
+    }
+
+    /// Writes a metadata entry under the reserved meta key.
+    pub(crate) fn put_meta<T>(&self, name: &str, value: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let mut batch = RawBatch::new();
+        self.batch_put_meta(&mut batch, name, value)?;
+        self.write_raw(batch)
+    }
+
+    /// Adds the write of a metadata entry to a raw batch.
+    pub(crate) fn batch_put_meta<T>(
+        &self,
+        batch: &mut RawBatch,
+        name: &str,
+        value: &T,
+    ) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let mut meta = self.read_meta()?;
+        meta.insert(name.to_string(), serialize_to_bytes(value, &self.db_name)?);
+        self.batch_put_raw(batch, META_KEY, &serialize_to_bytes(&meta, &self.db_name)?)
+    }
+
+    /// Adds the insertion of already serialized key / value to a raw batch, stored as is.
+    pub(crate) fn batch_put_raw(
+        &self,
+        batch: &mut RawBatch,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<()> {
+        self.batch_put_bytes(batch, key, value, value)
+    }
+
+    /// Adds the stored bytes of a value to a raw batch, notifying the subscribers of its `payload`.
+    fn batch_put_bytes(
+        &self,
+        batch: &mut RawBatch,
+        key: &[u8],
+        value: &[u8],
+        payload: &[u8],
+    ) -> Result<()> {
+        match self.cf_handle()? {
+            Some(cf) => batch.inner.put_cf(cf, key, value),
+            None => batch.inner.put(key, value),
+        }
+        .map_err(|e| map_log_err(e, &self.db_name))?;
+
+        batch.bytes += key.len() + value.len();
+
+        batch.journal(|| JournalOp::Put {
+            cf: self.cf.clone(),
+            key: key.to_vec(),
+            value: value.to_vec(),
+        });
+
+        if key != META_KEY {
+            batch.record(&self.hooks, ChangeKind::Put, key, Some(payload));
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub(crate) fn name(&self) -> &str {
+        &self.db_name
+    }
+
+    /// Ingests external SST files, such as those written by an [`SstWriter`](crate::SstWriter).
+    ///
+    /// The keys are moved into the database without going through the write-ahead log and the
+    /// memtables, and no change is notified to the subscribers.
+    pub fn ingest<P: AsRef<Path>>(&self, paths: &[P]) -> Result<()> {
+        let span = db_span!(self, "ingest", format_args!("len = {}", paths.len()));
+        let _enter = span.enter();
+
+        let paths = paths.iter().map(|p| p.as_ref()).collect::<Vec<_>>();
+
+        match self.cf_handle()? {
+            Some(cf) => self.db.ingest_external_file_cf(cf, paths),
+            None => self.db.ingest_external_file(paths),
+        }
+        .map_err(|e| map_log_err(e, &self.db_name))
+    }
+
+    /// Writes the keys / values of an export made by [`Db::export_to_writer`], returning the
+    /// number of entries read.
+    ///
+    /// The entries are written in batches; the existing keys are overwritten but not removed.
+    pub fn import_from_reader<R: Read>(&self, mut r: R) -> Result<u64> {
+        const BATCH_LEN: usize = 1000;
+
+        let span = db_span!(self, "import_from_reader");
+        let _enter = span.enter();
+
+        let mut batch = RawBatch::new();
+        let mut count = 0;
+
+        read_header(&mut r).map_err(|e| log_err(e, &self.db_name))?;
+
+        while let Some((key, value)) = read_entry(&mut r).map_err(|e| log_err(e, &self.db_name))? {
+            self.batch_put_raw(&mut batch, &key, &value)?;
+            count += 1;
+
+            if batch.len() >= BATCH_LEN {
+                self.write_raw(replace(&mut batch, RawBatch::new()))?;
+            }
+        }
+
+        if !batch.is_empty() {
+            self.write_raw(batch)?;
+        }
+
+        Ok(count)
+    }
+
+    /// Copies all the records of a database written with the former bincode encoding of the
+    /// keys, re-encoding the keys in the order-preserving encoding.
+    ///
+    /// The values are copied as is. Returns the number of records copied.
+    pub fn migrate_keys_from(&self, legacy: &Db<K>) -> Result<u64> {
+        const BATCH_LEN: usize = 1000;
+
+        let span = db_span!(self, "migrate_keys_from", legacy.db_name);
+        let _enter = span.enter();
+
+        let mut batch = RawBatch::new();
+        let mut count = 0;
+        let mut iter = legacy.raw_iterator()?;
+        iter.seek_to_first();
+
+        while let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+            if key == META_KEY {
+                self.batch_put_raw(&mut batch, key, value)?;
+            } else {
+                let k: K = deserialize_from_bytes(key, &legacy.db_name)?;
+                self.batch_put_raw(&mut batch, &key_to_bytes(&k, &self.db_name)?, value)?;
+                count += 1;
+            }
+
+            if batch.len() >= BATCH_LEN {
+                self.write_raw(replace(&mut batch, RawBatch::new()))?;
+            }
+
+            iter.next();
+        }
+
+        iter.status().map_err(|e| map_log_err(e, &legacy.db_name))?;
+
+        if !batch.is_empty() {
+            self.write_raw(batch)?;
+        }
+
+        Ok(count)
+    }
+
+    /// Writes many keys / values atomically, in a single batch.
+    pub fn put_many<I, V>(&self, items: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        V: Serialize,
+    {
+        let span = db_span!(self, "put_many");
+        let _enter = span.enter();
+
+        let mut batch = RawBatch::new();
+
+        for (key, value) in items {
+            self.batch_put(&mut batch, &key, &value)?;
+        }
+
+        self.write_raw(batch)
+    }
+
+    /// Returns the value of a RocksDB property, such as `rocksdb.estimate-num-keys`.
+    pub fn property(&self, name: &str) -> Result<Option<String>> {
+        match self.cf_handle()? {
+            Some(cf) => self.db.property_value_cf(cf, name),
+            None => self.db.property_value(name),
+        }
+        .map_err(|e| map_log_err(e, &self.db_name))
+    }
+
+    fn property_u64(&self, name: &str) -> Result<u64> {
+        Ok(self
+            .property(name)?
+            .and_then(|v| v.parse().ok())
+            .unwrap_or_default())
+    }
+
+    pub fn put<V>(&self, key: &K, value: &V) -> Result<()>
+    where
+        V: Serialize,
+    {
+        self.put_with(key, value, self.write_mode())
+    }
+
+    /// Writes already serialized bytes as the value, such as a message received from the network.
+    ///
+    /// The value can only be read back with [`Db::get_raw_bytes`].
+    pub fn put_raw(&self, key: &K, value: &[u8]) -> Result<()> {
+        let span = db_span!(self, "put_raw", key);
+        let _enter = span.enter();
+
+        with_key_bytes(key, &self.db_name, |key| {
+            self.put_bytes(key, value, value, self.write_mode())
+        })
+    }
+
+    /// Writes a key / value, with the durability of `mode`.
+    pub fn put_with<V>(&self, key: &K, value: &V, mode: WriteMode) -> Result<()>
+    where
+        V: Serialize,
+    {
+        let span = db_span!(self, "put", key);
+        let _enter = span.enter();
+
+        with_key_bytes(key, &self.db_name, |key| {
+            with_value_bytes(value, self.checksums, &self.db_name, |val| {
+                self.put_bytes(key, val, self.payload(val), mode)
+            })
+        })
+    }
+
+    /// Writes the stored bytes of a value, notifying the subscribers of its `payload`.
+    fn put_bytes(&self, key: &[u8], val: &[u8], payload: &[u8], mode: WriteMode) -> Result<()> {
+        let timer = stats::timer("put", &self.db_name);
+
+        match self.cf_handle()? {
+            Some(cf) => self.db.put_cf_opt(cf, key, val, &mode.options()),
+            None => self.db.put_opt(key, val, &mode.options()),
+        }
+        .map_err(|e| map_op_err(e, "put", &self.db_name, Some(key)))?;
+
+        drop(timer);
+        stats::bytes_written(&self.db_name, key.len() + val.len());
+
+        self.notify(ChangeKind::Put, key, Some(payload));
+        Ok(())
+    }
+}
+
+/// The operations not needing to serialize the keys, available without bounds on the key type,
+/// e.g. in a `Drop` implementation.
+impl<K> Db<K> {
+    pub(crate) fn write_raw(&self, batch: RawBatch) -> Result<()> {
+        batch.write_opt(&self.db, &self.db_name, self.write_mode())
+    }
+
+    /// Returns the durability of the writes made without an explicit [`WriteMode`].
+    fn write_mode(&self) -> WriteMode {
+        match self.temp_dir {
+            Some(_) => WriteMode::NoWal,
+            None => WriteMode::Default,
+        }
+    }
+}
+
+/// Lifecycle operations on a database path, not tied to a key type.
+impl Db<()> {
+    /// Removes the database at `path` and all its files. The database must be closed.
+    pub fn destroy<P: AsRef<Path>>(path: P) -> Result<()> {
+        let db_name = db_name_from_path(path.as_ref());
+
+        let span = trace_span!("destroy", db.name = db_name.as_str(), db.system = "rocksdb");
+        let _enter = span.enter();
+
+        rocksdb::DB::destroy(&Options::default(), path).map_err(|e| map_log_err(e, &db_name))
+    }
+
+    /// Tries to recover as much data as possible from a corrupted database. The database must be closed.
+    pub fn repair<P: AsRef<Path>>(path: P) -> Result<()> {
+        let db_name = db_name_from_path(path.as_ref());
+
+        let span = trace_span!("repair", db.name = db_name.as_str(), db.system = "rocksdb");
+        let _enter = span.enter();
+
+        rocksdb::DB::repair(default_options(), path).map_err(|e| map_log_err(e, &db_name))
+    }
+}
+
+impl Db<u64> {
+    /// Writes a value under the key following the last one, waiting for the write to be synced.
+    pub(crate) fn append_sync<V>(&self, value: &V) -> Result<u64>
+    where
+        V: Serialize,
+    {
+        let _guard = self.cas_lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        let id = match self.iter(IteratorMode::End)?.next()? {
+            Some(kv) => kv.key()? + 1,
+            None => 1,
+        };
+
+        let mut batch = RawBatch::new();
+        self.batch_put(&mut batch, &id, value)?;
+        batch.write_opt(&self.db, &self.db_name, WriteMode::Sync)?;
+        Ok(id)
+    }
+
+    /// Removes a key, waiting for the write to be synced.
+    pub(crate) fn delete_sync(&self, key: &u64) -> Result<()> {
+        let mut batch = RawBatch::new();
+        self.batch_delete(&mut batch, key)?;
+        batch.write_opt(&self.db, &self.db_name, WriteMode::Sync)
+    }
+}
+
+impl<K> Clone for Db<K> {
+    /// Creates another view on the same database, sharing the underlying RocksDB instance.
+    fn clone(&self) -> Self {
+        Self {
+            _k: PhantomData,
+            cas_lock: Arc::clone(&self.cas_lock),
+            cf: self.cf.clone(),
+            checksums: self.checksums,
+            comparator: self.comparator,
+            db: Arc::clone(&self.db),
+            db_name: self.db_name.clone(),
+            hooks: Arc::clone(&self.hooks),
+            merge_checksums: self.merge_checksums,
+            temp_dir: self.temp_dir.clone(),
+            trace: self.trace,
+        }
+    }
+}
+
+/// Removes the directory of a temporary database when dropped.
+struct TempDir(PathBuf);
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A RocksDB batch along with the changes to notify once it has been written.
+pub struct RawBatch {
+    pub(crate) inner: rocksdb::WriteBatch,
+    bytes: usize,
+    changes: Vec<(Arc<Hooks>, Change)>,
+    journal: Option<Vec<JournalOp>>,
+    savepoints: Vec<Savepoint>,
+}
+
+/// The state of a [`RawBatch`] restored by a rollback, besides its RocksDB batch.
+struct Savepoint {
+    bytes: usize,
+    changes: usize,
+    journal: usize,
+}
+
+impl RawBatch {
+    pub(crate) fn new() -> Self {
+        Self {
+            inner: rocksdb::WriteBatch::default(),
+            bytes: 0,
+            changes: Vec::new(),
+            journal: None,
+            savepoints: Vec::new(),
+        }
+    }
+
+    /// Creates a batch that also records its writes, to be replayed by a commit group.
+    pub(crate) fn journaled() -> Self {
+        Self {
+            journal: Some(Vec::new()),
+            ..Self::new()
+        }
+    }
+
+    fn journal<F>(&mut self, f: F)
+    where
+        F: FnOnce() -> JournalOp,
+    {
+        if let Some(journal) = &mut self.journal {
+            journal.push(f());
+        }
+    }
+
+    /// Returns the writes recorded by a journaled batch.
+    pub(crate) fn journal_ops(&self) -> &[JournalOp] {
+        self.journal.as_deref().unwrap_or_default()
+    }
+
+    /// Removes the last savepoint without undoing the writes made since.
+    pub(crate) fn pop_savepoint(&mut self, db_name: &str) -> Result<()> {
+        if self.savepoints.pop().is_none() {
+            return Err(log_err(Error::NoSavepoint, db_name));
+        }
+
+        self.inner
+            .pop_save_point()
+            .map_err(|e| map_log_err(e, db_name))
+    }
+
+    /// Undoes the writes made since the last savepoint, removing it.
+    pub(crate) fn rollback_to_savepoint(&mut self, db_name: &str) -> Result<()> {
+        let savepoint = match self.savepoints.pop() {
+            Some(savepoint) => savepoint,
+            None => return Err(log_err(Error::NoSavepoint, db_name)),
+        };
+
+        self.inner
+            .rollback_to_save_point()
+            .map_err(|e| map_log_err(e, db_name))?;
+
+        self.bytes = savepoint.bytes;
+        self.changes.truncate(savepoint.changes);
+
+        if let Some(journal) = &mut self.journal {
+            journal.truncate(savepoint.journal);
+        }
+
+        Ok(())
+    }
+
+    /// Marks the state of the batch, to undo the following writes with
+    /// [`RawBatch::rollback_to_savepoint`].
+    pub(crate) fn set_savepoint(&mut self) {
+        self.inner.set_save_point();
+
+        self.savepoints.push(Savepoint {
+            bytes: self.bytes,
+            changes: self.changes.len(),
+            journal: self.journal_ops().len(),
+        });
+    }
+
+    /// Returns the number of bytes of the keys / values put in the batch.
+    #[inline]
+    pub(crate) fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    #[inline]
+    pub(crate) fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn record(&mut self, hooks: &Arc<Hooks>, kind: ChangeKind, key: &[u8], value: Option<&[u8]>) {
+        if !hooks.is_empty() {
+            let change = Change {
+                kind,
+                key: key.to_vec(),
+                value: value.map(|v| v.to_vec()),
+            };
+
+            self.changes.push((Arc::clone(hooks), change));
+        }
+    }
+
+    /// Writes the batch with the durability of `mode`, then notifies the subscribers of the changes.
+    pub(crate) fn write_opt(self, db: &rocksdb::DB, db_name: &str, mode: WriteMode) -> Result<()> {
+        let timer = stats::timer("write", db_name);
+
+        db.write_opt(self.inner, &mode.options())
+            .map_err(|e| map_op_err(e, "write", db_name, None))?;
+
+        drop(timer);
+        stats::bytes_written(db_name, self.bytes);
+
+        for (hooks, change) in &self.changes {
+            hooks.notify(change);
+        }
+
+        Ok(())
+    }
+}
+
+/// A set of writes applied atomically to the database when committed.
+///
+/// Dropping the batch without calling [`WriteBatch::commit`] discards all the writes.
+pub struct WriteBatch<'a, K> {
+    batch: RawBatch,
+    db: &'a Db<K>,
+}
+
+impl<'a, K> WriteBatch<'a, K>
+where
+    K: Debug + for<'de> Deserialize<'de> + Serialize,
+{
+    /// Applies all the writes of the batch atomically.
+    pub fn commit(self) -> Result<()> {
+        let mode = self.db.write_mode();
+        self.commit_with(mode)
+    }
+
+    /// Applies all the writes of the batch atomically, with the durability of `mode`.
+    pub fn commit_with(self, mode: WriteMode) -> Result<()> {
+        let span = trace_span!(
+            "commit",
+            db.name = self.db.db_name.as_str(),
+            db.statement = format!("len = {}", self.batch.len()).as_str(),
+            db.system = "rocksdb",
+        );
+        let _enter = span.enter();
+
+        self.batch.write_opt(&self.db.db, &self.db.db_name, mode)
+    }
+
+    /// Adds the removal of a key to the batch.
+    pub fn delete(&mut self, key: &K) -> Result<()> {
+        self.db.batch_delete(&mut self.batch, key)
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.batch.is_empty()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.batch.len()
+    }
+
+    /// Adds the insertion of a key / value to the batch.
+    pub fn put<V>(&mut self, key: &K, value: &V) -> Result<()>
+    where
+        V: Serialize,
+    {
+        self.db.batch_put(&mut self.batch, key, value)
+    }
+
+    /// Removes the last savepoint, keeping the writes made since.
+    ///
+    /// Returns [`Error::NoSavepoint`] if no savepoint is set.
+    pub fn pop_savepoint(&mut self) -> Result<()> {
+        self.batch.pop_savepoint(&self.db.db_name)
+    }
+
+    /// Undoes the writes added since the last savepoint and removes it.
+    ///
+    /// Returns [`Error::NoSavepoint`] if no savepoint is set.
+    pub fn rollback_to_savepoint(&mut self) -> Result<()> {
+        self.batch.rollback_to_savepoint(&self.db.db_name)
+    }
+
+    /// Marks the current state of the batch, so a composite operation can undo its own writes
+    /// with [`WriteBatch::rollback_to_savepoint`] when it fails. The savepoints can be nested.
+    pub fn set_savepoint(&mut self) {
+        self.batch.set_savepoint();
+    }
+}
+
+/// A RocksDB database opened with named column families.
+///
+/// Each column family can be viewed as a typed [`Db`] sharing the same underlying database.
+#[derive(Clone)]
+pub struct DbHandle {
+    cas_lock: Arc<Mutex<()>>,
+    db: Arc<rocksdb::DB>,
+    db_name: String,
+    /// The hooks of each column family, shared by all its views.
+    hooks: Arc<Mutex<HashMap<String, Arc<Hooks>>>>,
+}
+
+impl DbHandle {
+    #[inline]
+    pub(crate) fn name(&self) -> &str {
+        &self.db_name
+    }
+
+    #[inline]
+    pub(crate) fn raw_db(&self) -> &Arc<rocksdb::DB> {
+        &self.db
+    }
+
+    /// Opens the database at `path` with all its existing column families,
+    /// creating the database if needed.
+    ///
+    /// Every column family stored in the database can then be viewed with [`DbHandle::cf`],
+    /// without listing them up front.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let opts = default_options();
 
-        let mut iter = self.db.raw_iterator();
+        // a database that does not exist yet has no column family to list.
+        let cfs = rocksdb::DB::list_cf(&opts, path.as_ref()).unwrap_or_default();
 
-        let dir = match mode {
-            IteratorMode::From(k, dir) => {
-                let key = serialize_to_bytes(&k, &self.db_name)?;
+        Self::open_cf_with_options(path, cfs, opts)
+    }
 
-                match dir {
-                    Direction::Forward => iter.seek(&key),
-                    Direction::Reverse => iter.seek_for_prev(&key),
-                }
+    /// Opens the database at `path` with the specified column families,
+    /// creating the database and the missing column families if needed.
+    pub fn open_cf<P, I, N>(path: P, cfs: I) -> Result<Self>
+    where
+        P: AsRef<Path>,
+        I: IntoIterator<Item = N>,
+        N: AsRef<str>,
+    {
+        Self::open_cf_with_options(path, cfs, default_options())
+    }
 
-                dir
-            }
-            IteratorMode::End => {
-                iter.seek_to_last();
-                Direction::Reverse
-            }
-            IteratorMode::Start => {
-                iter.seek_to_first();
-                Direction::Forward
-            }
-        };
+    pub(crate) fn open_cf_with_options<P, I, N>(path: P, cfs: I, mut opts: Options) -> Result<Self>
+    where
+        P: AsRef<Path>,
+        I: IntoIterator<Item = N>,
+        N: AsRef<str>,
+    {
+        let db_name = db_name_from_path(path.as_ref());
 
-        Ok(Iter {
-            _k: PhantomData,
-            dir,
-            db_name: &self.db_name,
-            iter,
-            must_call_next: false,
+        let span = trace_span!("open_cf", db.name = db_name.as_str(), db.system = "rocksdb");
+        let _enter = span.enter();
+        opts.create_missing_column_families(true);
+
+        Ok(DbHandle {
+            cas_lock: Default::default(),
+            db: Arc::new(
+                rocksdb::DB::open_cf(&opts, path, cfs).map_err(|e| map_log_err(e, &db_name))?,
+            ),
+            db_name,
+            hooks: Default::default(),
         })
     }
 
-    pub fn put<V>(&self, key: &K, value: &V) -> Result<()>
-    where
-        V: Serialize,
-    {
-        let _ = trace_span!(
-            "put",
-            db.name = self.db_name.as_str(),
-            db.statement = format!("{:?}", key).as_str(),
-            db.system = "rocksdb",
-        )
-        .enter();
+    /// Returns a typed view on the column family `name`.
+    ///
+    /// The views of a column family share their subscribers, so a write through any of them is
+    /// notified to all.
+    pub fn cf<K>(&self, name: &str) -> Result<Db<K>> {
+        if self.db.cf_handle(name).is_none() {
+            return Err(log_err(
+                Error::ColumnFamilyNotFound(name.to_string()),
+                &self.db_name,
+            ));
+        }
 
-        let key = serialize_to_bytes(key, &self.db_name)?;
-        let val = serialize_to_bytes(value, &self.db_name)?;
+        let hooks = Arc::clone(
+            self.hooks
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .entry(name.to_string())
+                .or_default(),
+        );
 
-        self.db
-            .put(&key, &val)
-            .map_err(|e| map_log_err(e, &self.db_name))
+        Ok(Db {
+            _k: PhantomData,
+            cas_lock: Arc::clone(&self.cas_lock),
+            cf: Some(name.to_string()),
+            checksums: false,
+            comparator: None,
+            db: Arc::clone(&self.db),
+            db_name: format!("{}/{}", self.db_name, name),
+            hooks,
+            merge_checksums: None,
+            temp_dir: None,
+            trace: Default::default(),
+        })
     }
 }
 
 pub struct DbValue<'a> {
-    bytes: DBPinnableSlice<'a>,
+    bytes: ValueBytes<'a>,
+    checksum: bool,
     db_name: &'a str,
 }
 
+/// The stored bytes of a [`DbValue`], pinned in RocksDB or copied by [`Db::multi_get`].
+enum ValueBytes<'a> {
+    Owned(Vec<u8>),
+    Pinned(DBPinnableSlice<'a>),
+}
+
+impl<'a> Deref for ValueBytes<'a> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Owned(bytes) => bytes,
+            Self::Pinned(bytes) => bytes,
+        }
+    }
+}
+
 impl<'a> DbValue<'a> {
+    /// Returns the stored bytes of the value, without deserializing them, their checksum included.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Deserializes the value into an owned value, releasing the pinned slice.
+    pub fn into_owned<V>(self) -> Result<V>
+    where
+        V: DeserializeOwned,
+    {
+        value_from_bytes(&self.bytes, self.checksum, self.db_name)
+    }
+
+    /// Returns the serialized value, its checksum verified and removed.
+    pub(crate) fn payload(&self) -> Result<&[u8]> {
+        strip_checksum(&self.bytes, self.checksum, self.db_name)
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Returns the number of stored bytes of the value.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
     pub fn to_inner<'b, V>(&'b self) -> Result<V>
     where
         V: Deserialize<'b>,
     {
-        deserialize_from_bytes(&self.bytes, self.db_name)
+        value_from_bytes(&self.bytes, self.checksum, self.db_name)
     }
 }
 
 pub struct DbKeyValue<'a, K> {
     _k: PhantomData<K>,
+    checksum: bool,
     db_name: &'a str,
     iter: &'a DBRawIterator<'a>,
 }
@@ -181,30 +2230,96 @@ impl<'a, K> DbKeyValue<'a, K> {
     where
         K: for<'de> Deserialize<'de>,
     {
-        deserialize_from_bytes(self.key_as_bytes()?, self.db_name)
+        key_from_bytes(self.key_as_bytes()?, self.db_name)
     }
 
-    fn key_as_bytes(&self) -> Result<&[u8]> {
+    pub(crate) fn key_as_bytes(&self) -> Result<&[u8]> {
         self.iter
             .key()
             .ok_or_else(|| log_err(Error::NoKey, self.db_name))
     }
 
+    /// Returns the serialized value, its checksum verified and removed.
+    pub(crate) fn payload(&self) -> Result<&[u8]> {
+        strip_checksum(self.value_bytes()?, self.checksum, self.db_name)
+    }
+
     pub fn value<'de, V>(&'de self) -> Result<V>
     where
         V: Deserialize<'de>,
     {
-        deserialize_from_bytes(self.value_as_bytes()?, self.db_name)
+        value_from_bytes(self.value_bytes()?, self.checksum, self.db_name)
     }
 
-    fn value_as_bytes(&self) -> Result<&[u8]> {
+    /// Returns the serialized value, to be inspected or copied without deserializing it.
+    pub fn value_bytes(&self) -> Result<&[u8]> {
         self.iter
             .value()
             .ok_or_else(|| log_err(Error::NoValue, self.db_name))
     }
 }
 
-#[derive(Debug, Deserialize, PartialEq, PartialOrd, Serialize)]
+/// The result of [`Db::get_or_tombstone`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MaybeTombstone<V> {
+    /// The key is not in the database.
+    Absent,
+    /// The key is stored with a `None` value.
+    Tombstone,
+    Present(V),
+}
+
+impl<V> MaybeTombstone<V> {
+    /// Returns the value, `None` for both a missing key and a tombstone.
+    pub fn into_option(self) -> Option<V> {
+        match self {
+            Self::Present(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// The durability of a write, trading latency for safety.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WriteMode {
+    /// The write goes to the write-ahead log, without waiting for it to be synced to disk.
+    Default,
+    /// The write skips the write-ahead log and is lost if the process crashes before a flush.
+    NoWal,
+    /// The write waits for the write-ahead log to be synced to disk.
+    Sync,
+}
+
+impl WriteMode {
+    fn options(self) -> WriteOptions {
+        let mut opts = WriteOptions::default();
+
+        match self {
+            Self::Default => {}
+            Self::NoWal => opts.disable_wal(true),
+            Self::Sync => opts.set_sync(true),
+        }
+
+        opts
+    }
+}
+
+impl Default for WriteMode {
+    fn default() -> Self {
+        Self::Default
+    }
+}
+
+/// The position of a page returned by [`Db::page`], to resume the iteration with [`Db::page_after`].
+///
+/// A cursor can be serialized and handed to a client, which passes it back to get the next page.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Cursor {
+    dir: Direction,
+    key: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, PartialOrd, Serialize)]
 pub enum Direction {
     Forward,
     Reverse,
@@ -249,54 +2364,542 @@ where
 
 pub struct Iter<'a, K> {
     _k: PhantomData<K>,
+    checksum: bool,
+    compare: CompareFn,
     db_name: &'a str,
     dir: Direction,
+    end: Bound<Vec<u8>>,
     iter: DBRawIterator<'a>,
     must_call_next: bool,
+    prefix: Option<Vec<u8>>,
 }
 
 impl<'a, K> Iter<'a, K> {
     #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Result<Option<DbKeyValue<K>>> {
         if self.must_call_next {
-            match self.dir {
-                Direction::Forward => self.iter.next(),
-                Direction::Reverse => self.iter.prev(),
-            }
-
-            self.iter.status().map_err(|e| log_err(e, self.db_name))?;
+            self.advance()?;
         }
 
         self.must_call_next = true;
 
-        Ok(if self.iter.valid() {
+        // the meta key is reserved and never yielded to the caller.
+        if self.iter.valid() && self.iter.key() == Some(META_KEY) {
+            self.advance()?;
+        }
+
+        Ok(if self.iter.valid() && self.in_bounds() {
+            Some(DbKeyValue {
+                _k: PhantomData,
+                checksum: self.checksum,
+                db_name: self.db_name,
+                iter: &self.iter,
+            })
+        } else {
+            None
+        })
+    }
+
+    /// Decodes only the key of the next record, leaving its value untouched.
+    ///
+    /// The value of the current record can still be read with [`Iter::current`].
+    pub fn next_key(&mut self) -> Result<Option<K>>
+    where
+        K: for<'de> Deserialize<'de>,
+    {
+        match self.next()? {
+            Some(item) => Ok(Some(item.key()?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the record on which the iterator is positioned, such as the one of the last
+    /// key returned by [`Iter::next_key`].
+    pub fn current(&self) -> Option<DbKeyValue<K>> {
+        if self.must_call_next && self.iter.valid() && self.in_bounds() {
             Some(DbKeyValue {
                 _k: PhantomData,
+                checksum: self.checksum,
                 db_name: self.db_name,
                 iter: &self.iter,
             })
         } else {
             None
+        }
+    }
+
+    /// Repositions the iterator on the key, or the next one in the iteration direction.
+    ///
+    /// The next call to [`Iter::next`] returns that record.
+    pub fn seek(&mut self, key: &K) -> Result<()>
+    where
+        K: Serialize,
+    {
+        let key = key_to_bytes(key, self.db_name)?;
+        self.seek_raw(&key);
+        Ok(())
+    }
+
+    /// Repositions the iterator on the serialized key, or the next one in the iteration direction.
+    pub(crate) fn seek_raw(&mut self, key: &[u8]) {
+        match self.dir {
+            Direction::Forward => self.iter.seek(key),
+            Direction::Reverse => self.iter.seek_for_prev(key),
+        }
+
+        self.must_call_next = false;
+    }
+
+    fn advance(&mut self) -> Result<()> {
+        match self.dir {
+            Direction::Forward => self.iter.next(),
+            Direction::Reverse => self.iter.prev(),
+        }
+
+        self.iter.status().map_err(|e| log_err(e, self.db_name))
+    }
+
+    fn in_bounds(&self) -> bool {
+        let key = match self.iter.key() {
+            Some(key) => key,
+            None => return false,
+        };
+
+        if let Some(prefix) = &self.prefix {
+            if !key.starts_with(prefix) {
+                return false;
+            }
+        }
+
+        // the end of a reverse scan is its lower bound.
+        match (&self.end, self.dir) {
+            (Bound::Included(end), Direction::Forward) => {
+                (self.compare)(key, end) != CmpOrdering::Greater
+            }
+            (Bound::Included(end), Direction::Reverse) => {
+                (self.compare)(key, end) != CmpOrdering::Less
+            }
+            (Bound::Excluded(end), Direction::Forward) => {
+                (self.compare)(key, end) == CmpOrdering::Less
+            }
+            (Bound::Excluded(end), Direction::Reverse) => {
+                (self.compare)(key, end) == CmpOrdering::Greater
+            }
+            (Bound::Unbounded, _) => true,
+        }
+    }
+}
+
+fn read_page<K, V>(
+    mut iter: Iter<K>,
+    skip: Option<&[u8]>,
+    limit: usize,
+) -> Result<(Vec<(K, V)>, Option<Cursor>)>
+where
+    K: for<'de> Deserialize<'de>,
+    V: for<'de> Deserialize<'de>,
+{
+    assert!(limit > 0);
+
+    let dir = iter.dir;
+    let mut items = Vec::new();
+    let mut last = None;
+
+    while items.len() < limit {
+        let kv = match iter.next()? {
+            Some(kv) => kv,
+            None => return Ok((items, None)),
+        };
+
+        let key = kv.key_as_bytes()?;
+
+        // the last key of the previous page is excluded when it still exists.
+        if Some(key) == skip {
+            continue;
+        }
+
+        items.push((kv.key()?, kv.value()?));
+        last = Some(key.to_vec());
+    }
+
+    Ok(match iter.next()? {
+        Some(_) => (items, last.map(|key| Cursor { dir, key })),
+        None => (items, None),
+    })
+}
+
+/// Returns the smallest key greater than all the keys starting with `prefix`.
+pub(crate) fn prefix_successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut key = prefix.to_vec();
+
+    while let Some(last) = key.pop() {
+        if last < u8::MAX {
+            key.push(last + 1);
+            return Some(key);
+        }
+    }
+
+    None
+}
+
+/// An iterator decoding each key / value of the database, returned by [`Db::iter_decoded`].
+///
+/// The iteration stops after the first error.
+pub struct DecodedIter<'a, K, V> {
+    _v: PhantomData<V>,
+    done: bool,
+    iter: Iter<'a, K>,
+}
+
+impl<'a, K, V> DecodedIter<'a, K, V>
+where
+    K: for<'de> Deserialize<'de>,
+    V: for<'de> Deserialize<'de>,
+{
+    pub(crate) fn new(iter: Iter<'a, K>) -> Self {
+        Self {
+            _v: PhantomData,
+            done: false,
+            iter,
+        }
+    }
+
+    fn next_item(&mut self) -> Result<Option<(K, V)>> {
+        Ok(match self.iter.next()? {
+            Some(kv) => Some((kv.key()?, kv.value()?)),
+            None => None,
         })
     }
 }
 
+impl<'a, K, V> Iterator for DecodedIter<'a, K, V>
+where
+    K: for<'de> Deserialize<'de>,
+    V: for<'de> Deserialize<'de>,
+{
+    type Item = Result<(K, V)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.next_item() {
+            Ok(Some(item)) => Some(Ok(item)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+pub(crate) fn db_name_from_path(path: &Path) -> String {
+    path.file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_string()
+}
+
+pub(crate) fn default_options() -> Options {
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_compression_type(DBCompressionType::Zstd);
+    opts
+}
+
 fn deserialize_from_bytes<'a, T: Deserialize<'a>>(bytes: &'a [u8], db_name: &str) -> Result<T> {
     crate::deserialize_from_bytes(bytes).map_err(|e| log_err(e, db_name))
 }
 
-fn log_err<E: Display>(e: E, db_name: &str) -> E {
+pub(crate) fn log_err<E: Display>(e: E, db_name: &str) -> E {
     error!({ db.name = db_name, db.system = "rocksdb" }, "{}", e);
     e
 }
 
-fn map_log_err(e: rocksdb::Error, db_name: &str) -> Error {
+pub(crate) fn map_log_err(e: rocksdb::Error, db_name: &str) -> Error {
     Error::RocksDb(log_err(e, db_name))
 }
 
-fn serialize_to_bytes<T: Serialize>(value: &T, db_name: &str) -> Result<Vec<u8>> {
+/// Logs a RocksDB error, wrapping it with the context of the operation that failed.
+pub(crate) fn map_op_err(
+    e: rocksdb::Error,
+    op: &'static str,
+    db_name: &str,
+    key: Option<&[u8]>,
+) -> Error {
+    Error::Context(Box::new(ErrorContext {
+        db_name: db_name.to_string(),
+        key_len: key.map(|k| k.len()),
+        op,
+        source: map_log_err(e, db_name),
+    }))
+}
+
+fn key_from_bytes<'a, K: Deserialize<'a>>(bytes: &'a [u8], db_name: &str) -> Result<K> {
+    crate::key_ser::key_from_bytes(bytes).map_err(|e| log_err(e, db_name))
+}
+
+fn bound_to_bytes<K: Serialize>(bound: Bound<&K>, db_name: &str) -> Result<Bound<Vec<u8>>> {
+    Ok(match bound {
+        Bound::Included(k) => Bound::Included(key_to_bytes(k, db_name)?),
+        Bound::Excluded(k) => Bound::Excluded(key_to_bytes(k, db_name)?),
+        Bound::Unbounded => Bound::Unbounded,
+    })
+}
+
+fn key_to_bytes<K: ?Sized + Serialize>(key: &K, db_name: &str) -> Result<Vec<u8>> {
+    crate::key_ser::key_to_bytes(key).map_err(|e| log_err(e, db_name))
+}
+
+/// Calls `f` with the key encoded in a reused buffer.
+fn with_key_bytes<K, F, R>(key: &K, db_name: &str, f: F) -> Result<R>
+where
+    F: FnOnce(&[u8]) -> Result<R>,
+    K: ?Sized + Serialize,
+{
+    with_buf(|buf| {
+        crate::key_ser::key_into_bytes(key, buf).map_err(|e| log_err(e, db_name))?;
+        f(buf)
+    })
+}
+
+/// Calls `f` with the value serialized in a reused buffer, followed by its CRC32 if `checksum`.
+pub(crate) fn with_value_bytes<T, F, R>(value: &T, checksum: bool, db_name: &str, f: F) -> Result<R>
+where
+    F: FnOnce(&[u8]) -> Result<R>,
+    T: ?Sized + Serialize,
+{
+    with_buf(|buf| {
+        crate::serialize_into(value, buf).map_err(|e| log_err(e, db_name))?;
+
+        if checksum {
+            append_checksum(buf);
+        }
+
+        f(buf)
+    })
+}
+
+/// Appends the CRC32 of the serialized value in `buf`.
+pub(crate) fn append_checksum(buf: &mut Vec<u8>) {
+    let crc = crc32fast::hash(buf);
+    buf.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Verifies and removes the trailing CRC32 of a stored value if `checksum`.
+pub(crate) fn strip_checksum<'a>(
+    bytes: &'a [u8],
+    checksum: bool,
+    db_name: &str,
+) -> Result<&'a [u8]> {
+    match checksum {
+        true if bytes.len() >= 4 => {
+            let (value, crc) = bytes.split_at(bytes.len() - 4);
+
+            if crc32fast::hash(value).to_be_bytes() != crc {
+                return Err(log_err(Error::ValueChecksum, db_name));
+            }
+
+            Ok(value)
+        }
+        true => Err(log_err(Error::ValueChecksum, db_name)),
+        false => Ok(bytes),
+    }
+}
+
+/// Deserializes a stored value, verifying and removing its trailing CRC32 if `checksum`.
+pub(crate) fn value_from_bytes<'a, T>(bytes: &'a [u8], checksum: bool, db_name: &str) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    deserialize_from_bytes(strip_checksum(bytes, checksum, db_name)?, db_name)
+}
+
+fn key_prefix_to_bytes<P: ?Sized + Serialize>(prefix: &P, db_name: &str) -> Result<Vec<u8>> {
+    crate::key_ser::key_prefix_to_bytes(prefix).map_err(|e| log_err(e, db_name))
+}
+
+fn serialize_to_bytes<T: ?Sized + Serialize>(value: &T, db_name: &str) -> Result<Vec<u8>> {
     match crate::serialize_to_bytes(value) {
         Ok(o) => Ok(o),
         Err(e) => Err(log_err(e, db_name)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Db, DbHandle, RawBatch};
+    use crate::{ChangeKind, Error};
+    use std::sync::{Arc, Mutex};
+
+    /// Records the keys of the changes notified by `db`.
+    fn record_keys(db: &Db<u32>) -> Arc<Mutex<Vec<(ChangeKind, u32)>>> {
+        let keys = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&keys);
+
+        db.subscribe(move |event| {
+            let key = event.key().unwrap();
+            recorded.lock().unwrap().push((event.kind(), key));
+        });
+
+        keys
+    }
+
+    #[test]
+    fn hooks_receive_values_without_checksum() {
+        let db = Db::<u32>::open_temporary()
+            .unwrap()
+            .with_value_checksums(true);
+
+        let values = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&values);
+
+        db.subscribe(move |event| {
+            let value = event.value_bytes().unwrap_or_default().to_vec();
+            recorded.lock().unwrap().push(value);
+        });
+
+        db.put(&1, &7u32).unwrap();
+        db.put_raw(&2, b"raw").unwrap();
+
+        let expected = vec![crate::serialize_to_bytes(&7u32).unwrap(), b"raw".to_vec()];
+        assert_eq!(*values.lock().unwrap(), expected);
+        assert_eq!(db.get(&1).unwrap().unwrap().to_inner::<u32>().unwrap(), 7);
+    }
+
+    #[test]
+    fn cf_views_share_hooks() {
+        let path = std::env::temp_dir().join(format!("rocks-tables-hooks-{}", std::process::id()));
+        let handle = DbHandle::open_cf(&path, &["items"]).unwrap();
+        let keys = record_keys(&handle.cf("items").unwrap());
+
+        handle.cf::<u32>("items").unwrap().put(&1, &()).unwrap();
+        assert_eq!(*keys.lock().unwrap(), vec![(ChangeKind::Put, 1)]);
+
+        drop(handle);
+        std::fs::remove_dir_all(&path).ok();
+    }
+
+    #[test]
+    fn nested_savepoints() {
+        let db = Db::<u32>::open_temporary().unwrap();
+        let keys = record_keys(&db);
+        let mut batch = db.batch();
+
+        batch.put(&1, &"a").unwrap();
+        batch.set_savepoint();
+        batch.put(&2, &"b").unwrap();
+        batch.set_savepoint();
+        batch.put(&3, &"c").unwrap();
+        batch.delete(&1).unwrap();
+
+        batch.rollback_to_savepoint().unwrap();
+        assert_eq!(batch.len(), 2);
+
+        batch.put(&4, &"d").unwrap();
+        batch.pop_savepoint().unwrap();
+
+        let e = batch.rollback_to_savepoint().unwrap_err();
+        assert!(matches!(e.root(), Error::NoSavepoint));
+
+        batch.commit().unwrap();
+
+        assert!(db.get(&1).unwrap().is_some());
+        assert!(db.get(&2).unwrap().is_some());
+        assert!(db.get(&3).unwrap().is_none());
+        assert!(db.get(&4).unwrap().is_some());
+
+        let expected = vec![
+            (ChangeKind::Put, 1),
+            (ChangeKind::Put, 2),
+            (ChangeKind::Put, 4),
+        ];
+        assert_eq!(*keys.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn rollback_drops_changes_and_journal() {
+        let db = Db::<u32>::open_temporary().unwrap();
+        let keys = record_keys(&db);
+        let mut batch = RawBatch::journaled();
+
+        db.batch_put(&mut batch, &1, &"a").unwrap();
+        let bytes = batch.bytes();
+
+        batch.set_savepoint();
+        db.batch_put(&mut batch, &2, &"b").unwrap();
+        batch.set_savepoint();
+        db.batch_delete(&mut batch, &1).unwrap();
+        assert_eq!(batch.journal_ops().len(), 3);
+
+        batch.rollback_to_savepoint(db.name()).unwrap();
+        assert_eq!(batch.journal_ops().len(), 2);
+
+        batch.rollback_to_savepoint(db.name()).unwrap();
+        assert_eq!(batch.journal_ops().len(), 1);
+        assert_eq!(batch.bytes(), bytes);
+        assert_eq!(batch.len(), 1);
+
+        db.write_raw(batch).unwrap();
+
+        assert!(db.get(&1).unwrap().is_some());
+        assert!(db.get(&2).unwrap().is_none());
+        assert_eq!(*keys.lock().unwrap(), vec![(ChangeKind::Put, 1)]);
+    }
+
+    #[test]
+    fn no_savepoint() {
+        let db = Db::<u32>::open_temporary().unwrap();
+        let mut batch = db.batch();
+
+        let e = batch.pop_savepoint().unwrap_err();
+        assert!(matches!(e.root(), Error::NoSavepoint));
+
+        let e = batch.rollback_to_savepoint().unwrap_err();
+        assert!(matches!(e.root(), Error::NoSavepoint));
+
+        batch.set_savepoint();
+        batch.pop_savepoint().unwrap();
+
+        let e = batch.pop_savepoint().unwrap_err();
+        assert!(matches!(e.root(), Error::NoSavepoint));
+    }
+
+    #[test]
+    fn watch_unsubscribes_dropped_receiver() {
+        let db = Db::<u32>::open_temporary().unwrap();
+        let rx = db.watch();
+
+        db.put(&1, &()).unwrap();
+        assert_eq!(rx.recv().unwrap().key().unwrap(), 1);
+
+        drop(rx);
+        db.put(&2, &()).unwrap();
+        assert!(db.hooks.is_empty());
+    }
+
+    #[test]
+    fn subscribe_while_removes_hook() {
+        let db = Db::<u32>::open_temporary().unwrap();
+        let keys = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&keys);
+
+        db.subscribe_while(move |event| {
+            let key = event.key().unwrap();
+            recorded.lock().unwrap().push(key);
+            key < 2
+        });
+
+        for key in 1..4 {
+            db.put(&key, &()).unwrap();
+        }
+
+        assert_eq!(*keys.lock().unwrap(), vec![1, 2]);
+        assert!(db.hooks.is_empty());
+    }
+}
@@ -0,0 +1,199 @@
+use crate::{
+    db::default_options,
+    section_lru_table::{section_in_domain, section_prefix},
+    Db, DbHandle, Result, SharedCache, SstOptions,
+};
+use rocksdb::{BlockBasedOptions, DBCompactionStyle, DBCompressionType, Options, SliceTransform};
+use serde::{Deserialize, Serialize};
+use std::{fmt::Debug, path::Path};
+
+/// Configures the RocksDB options used to open a [`Db`] or a [`DbHandle`].
+///
+/// By default, the database is created if missing and compressed with Zstd, just like [`Db::open`].
+#[derive(Clone, Debug, Default)]
+pub struct DbBuilder {
+    block_cache: Option<SharedCache>,
+    block_cache_size: Option<usize>,
+    bloom_filter_bits: Option<i32>,
+    compaction_style: Option<DBCompactionStyle>,
+    compression: Option<DBCompressionType>,
+    fixed_prefix_len: Option<usize>,
+    max_background_jobs: Option<i32>,
+    max_open_files: Option<i32>,
+    rate_limit: Option<i64>,
+    section_prefix: Option<fn() -> SliceTransform>,
+    write_buffer_size: Option<usize>,
+}
+
+impl DbBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the size in bytes of the LRU block cache of the database.
+    ///
+    /// Ignored if a [shared block cache](Self::shared_block_cache) is set.
+    pub fn block_cache_size(mut self, size: usize) -> Self {
+        self.block_cache_size = Some(size);
+        self
+    }
+
+    /// Enables a bloom filter with the specified number of bits per key.
+    pub fn bloom_filter(mut self, bits_per_key: i32) -> Self {
+        self.bloom_filter_bits = Some(bits_per_key);
+        self
+    }
+
+    /// Sets the compaction style, level or universal. Defaults to level compaction.
+    pub fn compaction_style(mut self, style: DBCompactionStyle) -> Self {
+        self.compaction_style = Some(style);
+        self
+    }
+
+    /// Sets the compression applied on the blocks. Defaults to Zstd.
+    pub fn compression(mut self, compression: DBCompressionType) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Uses the first `len` bytes of the serialized keys as the prefix for prefix seeks and
+    /// prefix bloom filters.
+    pub fn fixed_prefix(mut self, len: usize) -> Self {
+        self.fixed_prefix_len = Some(len);
+        self
+    }
+
+    /// Sets the maximum number of concurrent background flushes and compactions.
+    pub fn max_background_jobs(mut self, count: i32) -> Self {
+        self.max_background_jobs = Some(count);
+        self
+    }
+
+    /// Sets the maximum number of files opened by the database. `-1` means no limit.
+    pub fn max_open_files(mut self, count: i32) -> Self {
+        self.max_open_files = Some(count);
+        self
+    }
+
+    /// Limits the rate of the writes of the flushes and compactions, in bytes per second,
+    /// to leave disk bandwidth to the other processes of a shared host.
+    pub fn rate_limit(mut self, bytes_per_sec: i64) -> Self {
+        self.rate_limit = Some(bytes_per_sec);
+        self
+    }
+
+    /// Uses the section `S` of `(S, K)` keys as the prefix for prefix seeks and prefix bloom filters,
+    /// intended for the databases of a [`SectionLruTable`](crate::SectionLruTable).
+    pub fn section_prefix<S>(mut self) -> Self
+    where
+        S: for<'de> Deserialize<'de>,
+    {
+        self.section_prefix = Some(section_transform::<S>);
+        self
+    }
+
+    /// Uses a block cache shared with the other databases opened with the same cache.
+    pub fn shared_block_cache(mut self, cache: &SharedCache) -> Self {
+        self.block_cache = Some(cache.clone());
+        self
+    }
+
+    /// Sets the size in bytes of a memtable before it is flushed to disk.
+    pub fn write_buffer_size(mut self, size: usize) -> Self {
+        self.write_buffer_size = Some(size);
+        self
+    }
+
+    /// Opens a typed database at `path` with the configured options.
+    pub fn open<K, P>(&self, path: P) -> Result<Db<K>>
+    where
+        K: Debug + for<'de> Deserialize<'de> + Serialize,
+        P: AsRef<Path>,
+    {
+        Db::open_with_options(path, &self.options())
+    }
+
+    /// Opens a database with the specified column families and the configured options.
+    pub fn open_cf<P, I, N>(&self, path: P, cfs: I) -> Result<DbHandle>
+    where
+        P: AsRef<Path>,
+        I: IntoIterator<Item = N>,
+        N: AsRef<str>,
+    {
+        DbHandle::open_cf_with_options(path, cfs, self.options())
+    }
+
+    /// Returns the options to write external SST files ingested in a database opened by this builder.
+    pub fn sst_options(&self) -> SstOptions {
+        SstOptions::new(self.options())
+    }
+
+    pub(crate) fn options(&self) -> Options {
+        let mut opts = default_options();
+
+        if let Some(compression) = self.compression {
+            opts.set_compression_type(compression);
+        }
+
+        if let Some(size) = self.write_buffer_size {
+            opts.set_write_buffer_size(size);
+        }
+
+        if let Some(count) = self.max_open_files {
+            opts.set_max_open_files(count);
+        }
+
+        if let Some(style) = self.compaction_style {
+            opts.set_compaction_style(style);
+        }
+
+        if let Some(count) = self.max_background_jobs {
+            opts.set_max_background_jobs(count);
+        }
+
+        if let Some(rate) = self.rate_limit {
+            // refills every 100 ms, with the default fairness between flushes and compactions.
+            opts.set_ratelimiter(rate, 100_000, 10);
+        }
+
+        if let Some(len) = self.fixed_prefix_len {
+            opts.set_prefix_extractor(SliceTransform::create_fixed_prefix(len));
+        }
+
+        if let Some(transform) = self.section_prefix {
+            opts.set_prefix_extractor(transform());
+        }
+
+        if self.block_cache.is_some()
+            || self.block_cache_size.is_some()
+            || self.bloom_filter_bits.is_some()
+        {
+            let mut block_opts = BlockBasedOptions::default();
+
+            if let Some(cache) = &self.block_cache {
+                block_opts.set_block_cache(cache.cache());
+            } else if let Some(size) = self.block_cache_size {
+                block_opts.set_lru_cache(size);
+            }
+
+            if let Some(bits) = self.bloom_filter_bits {
+                block_opts.set_bloom_filter(bits, false);
+            }
+
+            opts.set_block_based_table_factory(&block_opts);
+        }
+
+        opts
+    }
+}
+
+fn section_transform<S>() -> SliceTransform
+where
+    S: for<'de> Deserialize<'de>,
+{
+    SliceTransform::create(
+        "rocks-tables.section",
+        section_prefix::<S>,
+        Some(section_in_domain::<S>),
+    )
+}
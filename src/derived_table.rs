@@ -0,0 +1,443 @@
+use crate::{
+    db::log_err, deserialize_from_bytes, key_ser::key_to_bytes, ChangeEvent, ChangeKind, Db,
+    Encoded, Iter, IterOptions, IteratorMode, RawBatch, Result,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fmt::Debug,
+    mem::replace,
+    sync::{
+        atomic::{AtomicBool, Ordering::Relaxed},
+        Arc, Mutex, MutexGuard, Weak,
+    },
+};
+
+const BATCH_LEN: usize = 1000;
+
+/// The key of the reverse index: the source id and the serialized key of the source row.
+type IndexKey = (u32, Encoded<Vec<u8>>);
+type Mapping<K, V, Src> = Box<dyn Fn(&Src) -> Vec<(K, V)> + Send + Sync>;
+type Scan<Src> = Box<dyn Fn(&mut dyn FnMut(&[u8], Src) -> Result<()>) -> Result<()> + Send + Sync>;
+
+/// A table of rows computed from the rows of one or more source databases, such as a
+/// denormalized view, kept in sync with the writes on the sources.
+///
+/// The derived rows are stored in their own database, along with a reverse index of the
+/// derived keys of each source row, written in the same batches. The index must be a column
+/// family of the same database. Each derived key is expected to come from a single source row:
+/// when a source row changes, the derived rows it produced are replaced.
+///
+/// The sources are followed through their change hooks, so the derived rows are written on
+/// the thread writing the source. A failure to derive a write, or a merge on a source, whose
+/// result is unknown, marks the table as [stale](Self::is_stale) until it is
+/// [refreshed](Self::refresh).
+///
+/// When a table is opened again, a source whose sequence number did not change since its last
+/// derived write is resumed from the index; the other sources are derived again. The sequence
+/// number of a source stored in the same database as the table changes with the derived writes,
+/// so such a source is always derived again.
+pub struct DerivedTable<K, V, Src> {
+    shared: Arc<Shared<K, V, Src>>,
+    sources: Vec<Source<Src>>,
+}
+
+struct Shared<K, V, Src> {
+    db: Db<K>,
+    index: Db<IndexKey>,
+    /// Serializes the updates of the index.
+    lock: Mutex<()>,
+    map: Mapping<K, V, Src>,
+    stale: AtomicBool,
+}
+
+/// A source followed by the table, its key type erased.
+struct Source<Src> {
+    scan: Scan<Src>,
+    sequence: Weak<rocksdb::DB>,
+}
+
+/// The state of a source, stored in the metadata of the index.
+#[derive(Deserialize, Serialize)]
+struct SourceState {
+    /// The latest sequence number of the source when its last write was derived.
+    sequence: u64,
+    stale: bool,
+}
+
+impl<K, V, Src> DerivedTable<K, V, Src>
+where
+    K: Debug + for<'de> Deserialize<'de> + Serialize + Send + Sync + 'static,
+    V: DeserializeOwned + Serialize + 'static,
+    Src: DeserializeOwned + 'static,
+{
+    /// Creates a table storing in `db` the rows computed by `map`, and in `index` the derived
+    /// keys of each source row.
+    ///
+    /// The rows already stored are kept, to be resumed by [`add_source`](Self::add_source).
+    pub fn new<F>(db: Db<K>, index: Db<IndexKey>, map: F) -> Result<Self>
+    where
+        F: Fn(&Src) -> Vec<(K, V)> + Send + Sync + 'static,
+    {
+        assert!(
+            Arc::ptr_eq(db.raw_db(), index.raw_db()),
+            "the reverse index must be stored in the same database as the derived rows"
+        );
+
+        Ok(Self {
+            shared: Arc::new(Shared {
+                db,
+                index,
+                lock: Mutex::new(()),
+                map: Box::new(map),
+                stale: AtomicBool::new(false),
+            }),
+            sources: Vec::new(),
+        })
+    }
+
+    /// Follows the writes of `source`, deriving its rows unless they can be resumed from the
+    /// index.
+    ///
+    /// The writes on the source wait while its rows are derived.
+    pub fn add_source<SK>(&mut self, source: &Db<SK>) -> Result<()>
+    where
+        SK: Debug + for<'de> Deserialize<'de> + Serialize + Send + Sync + 'static,
+    {
+        let id = self.sources.len() as u32;
+        let weak = Arc::downgrade(&self.shared);
+        let sequence = Arc::downgrade(source.raw_db());
+        let hook_sequence = sequence.clone();
+
+        // the hook is removed by the first write after the table is dropped.
+        source.subscribe_while(move |event| match weak.upgrade() {
+            Some(shared) => {
+                shared.on_change(id, event, latest_sequence(&hook_sequence));
+                true
+            }
+            None => false,
+        });
+
+        let db = source.clone();
+        let source = Source {
+            scan: Box::new(move |f| scan_source(&db, f)),
+            sequence,
+        };
+
+        // the writes made since the subscription wait for the lock, then apply on the rows loaded.
+        let guard = self.shared.lock();
+        let state = self.shared.source_state(id)?;
+        let resume =
+            matches!(state, Some(s) if !s.stale && s.sequence == latest_sequence(&source.sequence));
+
+        if !resume {
+            self.shared.reload(id, &source)?;
+        }
+
+        drop(guard);
+
+        self.sources.push(source);
+        Ok(())
+    }
+
+    /// Returns the database of the derived rows, to iterate them.
+    pub fn db(&self) -> &Db<K> {
+        &self.shared.db
+    }
+
+    pub fn get(&self, key: &K) -> Result<Option<V>> {
+        match self.shared.db.get(key)? {
+            Some(value) => Ok(Some(value.to_inner()?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns true if a write on a source could not be derived.
+    pub fn is_stale(&self) -> bool {
+        self.shared.stale.load(Relaxed)
+    }
+
+    /// Rebuilds all the derived rows from the sources.
+    pub fn refresh(&self) -> Result<()> {
+        let _guard = self.shared.lock();
+
+        self.shared.db.clear()?;
+        self.shared.index.clear()?;
+
+        for (id, source) in self.sources.iter().enumerate() {
+            self.shared.load(id as u32, source)?;
+        }
+
+        self.shared.stale.store(false, Relaxed);
+        Ok(())
+    }
+}
+
+impl<K, V, Src> Shared<K, V, Src>
+where
+    K: Debug + for<'de> Deserialize<'de> + Serialize,
+    V: Serialize,
+    Src: DeserializeOwned,
+{
+    fn apply<SK>(&self, id: u32, event: &ChangeEvent<SK>, sequence: u64) -> Result<()> {
+        let key = event.key_bytes();
+        let mut batch = RawBatch::new();
+        let _guard = self.lock();
+
+        match event.kind() {
+            ChangeKind::Clear => {
+                self.remove_indexed(&mut batch, self.index.iter_prefix(&id)?)?;
+            }
+            ChangeKind::Delete => {
+                let index_key = (id, Encoded(key.to_vec()));
+
+                for derived in self.derived_keys(&index_key)? {
+                    self.db.batch_delete_raw(&mut batch, &derived)?;
+                }
+
+                self.index.batch_delete(&mut batch, &index_key)?;
+            }
+            ChangeKind::DeleteRange => {
+                let end = event.value_bytes().unwrap_or_default();
+                let range = (id, Encoded(key.to_vec()))..(id, Encoded(end.to_vec()));
+                self.remove_indexed(&mut batch, self.index.iter_range(range)?)?;
+            }
+            ChangeKind::Merge => {
+                self.stale.store(true, Relaxed);
+                return self.put_source_state(&mut batch, id, sequence, true);
+            }
+            ChangeKind::Put => {
+                let row = deserialize_from_bytes(event.value_bytes().unwrap_or_default())?;
+                let new = self.put_rows(&mut batch, (self.map)(&row))?;
+                let index_key = (id, Encoded(key.to_vec()));
+
+                for derived in self.derived_keys(&index_key)? {
+                    if !new.contains(&derived) {
+                        self.db.batch_delete_raw(&mut batch, &derived)?;
+                    }
+                }
+
+                if new.is_empty() {
+                    self.index.batch_delete(&mut batch, &index_key)?;
+                } else {
+                    self.index.batch_put(&mut batch, &index_key, &new)?;
+                }
+            }
+        }
+
+        self.put_source_state(&mut batch, id, sequence, false)
+    }
+
+    /// Returns the serialized derived keys of a source row.
+    fn derived_keys(&self, index_key: &IndexKey) -> Result<Vec<Vec<u8>>> {
+        match self.index.get(index_key)? {
+            Some(value) => value.to_inner(),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Derives all the rows of a source, indexing them under `id`.
+    fn load(&self, id: u32, source: &Source<Src>) -> Result<()> {
+        // a write made during the scan is derived after it, with a greater sequence number.
+        let sequence = latest_sequence(&source.sequence);
+        let mut batch = RawBatch::new();
+
+        (source.scan)(&mut |key, row| {
+            let derived = self.put_rows(&mut batch, (self.map)(&row))?;
+
+            if !derived.is_empty() {
+                self.index
+                    .batch_put(&mut batch, &(id, Encoded(key.to_vec())), &derived)?;
+            }
+
+            if batch.len() >= BATCH_LEN {
+                self.db.write_raw(replace(&mut batch, RawBatch::new()))?;
+            }
+
+            Ok(())
+        })?;
+
+        self.put_source_state(&mut batch, id, sequence, false)
+    }
+
+    fn lock(&self) -> MutexGuard<()> {
+        self.lock.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn on_change<SK>(&self, id: u32, event: &ChangeEvent<SK>, sequence: u64) {
+        if let Err(e) = self.apply(id, event, sequence) {
+            log_err(e, self.db.name());
+            self.stale.store(true, Relaxed);
+
+            if let Err(e) = self.put_source_state(&mut RawBatch::new(), id, sequence, true) {
+                log_err(e, self.db.name());
+            }
+        }
+    }
+
+    /// Adds the derived rows to the batch, returning their serialized keys.
+    fn put_rows(&self, batch: &mut RawBatch, rows: Vec<(K, V)>) -> Result<Vec<Vec<u8>>> {
+        rows.into_iter()
+            .map(|(key, value)| {
+                let key = key_to_bytes(&key)?;
+                self.db.batch_put_value(batch, &key, &value)?;
+                Ok(key)
+            })
+            .collect()
+    }
+
+    /// Writes the batch along with the state of the source `id`.
+    fn put_source_state(
+        &self,
+        batch: &mut RawBatch,
+        id: u32,
+        sequence: u64,
+        stale: bool,
+    ) -> Result<()> {
+        let state = SourceState { sequence, stale };
+        self.index
+            .batch_put_meta(batch, &source_state_name(id), &state)?;
+        self.db.write_raw(replace(batch, RawBatch::new()))
+    }
+
+    /// Removes the derived rows of the source `id`, then derives them again.
+    fn reload(&self, id: u32, source: &Source<Src>) -> Result<()> {
+        let mut batch = RawBatch::new();
+        self.remove_indexed(&mut batch, self.index.iter_prefix(&id)?)?;
+        self.db.write_raw(batch)?;
+        self.load(id, source)
+    }
+
+    /// Removes the index entries of the iteration and their derived rows.
+    fn remove_indexed(&self, batch: &mut RawBatch, mut iter: Iter<IndexKey>) -> Result<()> {
+        while let Some(item) = iter.next()? {
+            for derived in item.value::<Vec<Vec<u8>>>()? {
+                self.db.batch_delete_raw(batch, &derived)?;
+            }
+
+            self.index.batch_delete_raw(batch, item.key_as_bytes()?)?;
+        }
+
+        Ok(())
+    }
+
+    fn source_state(&self, id: u32) -> Result<Option<SourceState>> {
+        self.index.get_meta(&source_state_name(id))
+    }
+}
+
+/// Returns the latest sequence number of a source database, or 0 once it is closed.
+fn latest_sequence(db: &Weak<rocksdb::DB>) -> u64 {
+    db.upgrade().map_or(0, |db| db.latest_sequence_number())
+}
+
+fn scan_source<SK, Src>(db: &Db<SK>, f: &mut dyn FnMut(&[u8], Src) -> Result<()>) -> Result<()>
+where
+    SK: Debug + for<'de> Deserialize<'de> + Serialize,
+    Src: DeserializeOwned,
+{
+    let mut iter = db.iter_opt(IteratorMode::Start, &IterOptions::bulk_scan())?;
+
+    while let Some(item) = iter.next()? {
+        f(item.key_as_bytes()?, item.value()?)?;
+    }
+
+    Ok(())
+}
+
+fn source_state_name(id: u32) -> String {
+    format!("derived_source_{}", id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DerivedTable;
+    use crate::{Db, DbHandle};
+
+    fn by_name(name: &String) -> Vec<(String, u32)> {
+        vec![(name.clone(), name.len() as u32)]
+    }
+
+    #[test]
+    fn derived_with_checksums() {
+        let source = Db::<u32>::open_temporary()
+            .unwrap()
+            .with_value_checksums(true);
+
+        source.put(&1, &"a".to_string()).unwrap();
+
+        let path =
+            std::env::temp_dir().join(format!("rocks-tables-derived-{}", std::process::id()));
+        let handle = DbHandle::open_cf(&path, &["rows", "index"]).unwrap();
+        let rows = handle
+            .cf::<String>("rows")
+            .unwrap()
+            .with_value_checksums(true);
+
+        let mut table = DerivedTable::new(rows, handle.cf("index").unwrap(), by_name).unwrap();
+
+        table.add_source(&source).unwrap();
+        source.put(&2, &"bb".to_string()).unwrap();
+
+        assert_eq!(table.get(&"a".to_string()).unwrap(), Some(1));
+        assert_eq!(table.get(&"bb".to_string()).unwrap(), Some(2));
+        assert!(!table.is_stale());
+
+        drop(table);
+        drop(handle);
+        std::fs::remove_dir_all(&path).ok();
+    }
+
+    #[test]
+    fn resume_from_index() {
+        let source = Db::<u32>::open_temporary().unwrap();
+        source.put(&1, &"a".to_string()).unwrap();
+
+        let path = std::env::temp_dir().join(format!(
+            "rocks-tables-derived-resume-{}",
+            std::process::id()
+        ));
+        let handle = DbHandle::open_cf(&path, &["rows", "index"]).unwrap();
+        let open = || {
+            let mut table = DerivedTable::new(
+                handle.cf("rows").unwrap(),
+                handle.cf("index").unwrap(),
+                by_name,
+            )
+            .unwrap();
+
+            table.add_source(&source).unwrap();
+            table
+        };
+
+        let table = open();
+        source.put(&2, &"bb".to_string()).unwrap();
+        drop(table);
+
+        // a row removed behind the table stays removed when the source is resumed.
+        let rows = handle.cf::<String>("rows").unwrap();
+        rows.delete(&"a".to_string()).unwrap();
+
+        let table = open();
+        assert_eq!(table.get(&"a".to_string()).unwrap(), None);
+        assert_eq!(table.get(&"bb".to_string()).unwrap(), Some(2));
+
+        // the resumed index still replaces the derived rows of a source row.
+        source.put(&2, &"ccc".to_string()).unwrap();
+        assert_eq!(table.get(&"bb".to_string()).unwrap(), None);
+        assert_eq!(table.get(&"ccc".to_string()).unwrap(), Some(3));
+        drop(table);
+
+        // a source written while the table is closed is derived again.
+        source.delete(&2).unwrap();
+
+        let table = open();
+        assert_eq!(table.get(&"a".to_string()).unwrap(), Some(1));
+        assert_eq!(table.get(&"ccc".to_string()).unwrap(), None);
+        assert!(!table.is_stale());
+
+        drop(table);
+        drop(rows);
+        drop(handle);
+        std::fs::remove_dir_all(&path).ok();
+    }
+}
@@ -5,8 +5,19 @@ use aes_gcm::{
     aead::{generic_array::GenericArray, Aead},
     Aes256Gcm,
 };
+#[cfg(feature = "chacha20poly1305")]
+use chacha20poly1305::{
+    aead::{
+        generic_array::{ArrayLength, GenericArray as ChaChaArray},
+        Aead as ChaChaAead,
+    },
+    ChaCha20Poly1305, XChaCha20Poly1305,
+};
 
 pub trait Encrypt {
+    /// The length of the nonce expected by the cipher, in bytes.
+    const NONCE_LEN: usize = 12;
+
     fn decrypt(&self, data: &[u8], nonce: &[u8]) -> Result<Vec<u8>>;
     fn encrypt(&self, data: &[u8], nonce: &[u8]) -> Result<Vec<u8>>;
 }
@@ -26,6 +37,40 @@ impl Encrypt for Aes256Gcm {
     }
 }
 
+/// Uses a nonce of 12 bytes, so the nonces must not repeat for a same key.
+#[cfg(feature = "chacha20poly1305")]
+impl Encrypt for ChaCha20Poly1305 {
+    fn encrypt(&self, data: &[u8], nonce: &[u8]) -> Result<Vec<u8>> {
+        let mut fallback = Default::default();
+        let nonce = prepare_nonce_chacha(nonce, &mut fallback);
+        ChaChaAead::encrypt(self, nonce, data).map_err(crate::Error::ChaCha20Poly1305)
+    }
+
+    fn decrypt(&self, data: &[u8], nonce: &[u8]) -> Result<Vec<u8>> {
+        let mut fallback = Default::default();
+        let nonce = prepare_nonce_chacha(nonce, &mut fallback);
+        ChaChaAead::decrypt(self, nonce, data).map_err(crate::Error::ChaCha20Poly1305)
+    }
+}
+
+/// Uses a nonce of 24 bytes, large enough to be picked at random for every record.
+#[cfg(feature = "chacha20poly1305")]
+impl Encrypt for XChaCha20Poly1305 {
+    const NONCE_LEN: usize = 24;
+
+    fn encrypt(&self, data: &[u8], nonce: &[u8]) -> Result<Vec<u8>> {
+        let mut fallback = Default::default();
+        let nonce = prepare_nonce_chacha(nonce, &mut fallback);
+        ChaChaAead::encrypt(self, nonce, data).map_err(crate::Error::ChaCha20Poly1305)
+    }
+
+    fn decrypt(&self, data: &[u8], nonce: &[u8]) -> Result<Vec<u8>> {
+        let mut fallback = Default::default();
+        let nonce = prepare_nonce_chacha(nonce, &mut fallback);
+        ChaChaAead::decrypt(self, nonce, data).map_err(crate::Error::ChaCha20Poly1305)
+    }
+}
+
 #[cfg(feature = "aes-gcm")]
 fn prepare_nonce_aes_gcm<'a>(
     key: &'a [u8],
@@ -44,3 +89,20 @@ fn prepare_nonce_aes_gcm<'a>(
         GenericArray::from_slice(&*fallback)
     }
 }
+
+#[cfg(feature = "chacha20poly1305")]
+fn prepare_nonce_chacha<'a, N>(
+    key: &'a [u8],
+    fallback: &'a mut ChaChaArray<u8, N>,
+) -> &'a ChaChaArray<u8, N>
+where
+    N: ArrayLength<u8>,
+{
+    // same as aes, the nonce is truncated or padded with 0 to the size of the cipher.
+    if key.len() >= N::USIZE {
+        ChaChaArray::from_slice(&key[0..N::USIZE])
+    } else {
+        fallback[0..key.len()].copy_from_slice(&key);
+        fallback
+    }
+}
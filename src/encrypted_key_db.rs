@@ -0,0 +1,69 @@
+use crate::{serialize_to_bytes, Db, DbValue, Result};
+use serde::Serialize;
+use std::marker::PhantomData;
+
+/// Transforms the serialized keys before they are stored, so confidential identifiers
+/// never reach the SST files in plaintext.
+///
+/// The transformation must be deterministic (e.g. an HMAC or a deterministic encryption)
+/// for the point lookups to find the stored keys.
+pub trait KeyCodec {
+    fn encode(&self, key: &[u8]) -> Vec<u8>;
+}
+
+impl<F> KeyCodec for F
+where
+    F: Fn(&[u8]) -> Vec<u8>,
+{
+    #[inline]
+    fn encode(&self, key: &[u8]) -> Vec<u8> {
+        self(key)
+    }
+}
+
+/// A database where the keys are encoded by a [`KeyCodec`] before storage.
+///
+/// Since the encoded keys cannot be decoded, only point lookups are supported.
+pub struct EncryptedKeyDb<K, C> {
+    _k: PhantomData<K>,
+    codec: C,
+    db: Db<Vec<u8>>,
+}
+
+impl<K, C> EncryptedKeyDb<K, C>
+where
+    K: Serialize,
+    C: KeyCodec,
+{
+    pub fn new(db: Db<Vec<u8>>, codec: C) -> Self {
+        Self {
+            _k: PhantomData,
+            codec,
+            db,
+        }
+    }
+
+    pub fn contains_key(&self, key: &K) -> Result<bool> {
+        self.db.contains_key(&self.encode_key(key)?)
+    }
+
+    pub fn delete(&self, key: &K) -> Result<()> {
+        self.db.delete(&self.encode_key(key)?)
+    }
+
+    fn encode_key(&self, key: &K) -> Result<Vec<u8>> {
+        Ok(self.codec.encode(&serialize_to_bytes(key)?))
+    }
+
+    /// Gets a value from the database.
+    pub fn get(&self, key: &K) -> Result<Option<DbValue>> {
+        self.db.get(&self.encode_key(key)?)
+    }
+
+    pub fn put<V>(&self, key: &K, value: &V) -> Result<()>
+    where
+        V: Serialize,
+    {
+        self.db.put(&self.encode_key(key)?, value)
+    }
+}
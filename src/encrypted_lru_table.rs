@@ -0,0 +1,294 @@
+use crate::{
+    aged::AgeIndex, deserialize_from_bytes, serialize_to_bytes, stats, Aged, Db, Encrypt, Error,
+    IteratorMode, RawBatch, Result,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    convert::TryInto,
+    fmt::Debug,
+    hash::Hash,
+    io,
+    mem::replace,
+};
+
+const KEY_ID_LEN: usize = 4;
+
+/// An [`LruTable`](crate::LruTable) where the values are encrypted at rest.
+///
+/// Each record is sealed with a random nonce of [`Encrypt::NONCE_LEN`] bytes and starts with
+/// the id of the key used to encrypt it, so records of older keys can still be read after a
+/// [rotation](Self::rotate_key).
+/// The values kept in memory are in plaintext.
+pub struct EncryptedLruTable<K, V, E> {
+    ages: AgeIndex<K>,
+    current: u32,
+    db: Db<K>,
+    keys: HashMap<u32, E>,
+    map: HashMap<K, Aged<V>, RandomState>,
+}
+
+impl<K, V, E> EncryptedLruTable<K, V, E>
+where
+    E: Encrypt,
+    K: Clone + Debug + for<'de> Deserialize<'de> + Eq + Hash + Serialize,
+    V: for<'de> Deserialize<'de> + Serialize,
+{
+    const HEADER_LEN: usize = KEY_ID_LEN + E::NONCE_LEN;
+
+    /// Creates a table encrypting the new records with the cipher `key`, identified by `key_id`.
+    pub fn with_capacity(db: Db<K>, capacity: usize, key_id: u32, key: E) -> Self {
+        assert!(capacity > 0);
+
+        let mut keys = HashMap::new();
+        keys.insert(key_id, key);
+
+        Self {
+            ages: AgeIndex::new(),
+            current: key_id,
+            db,
+            keys,
+            map: HashMap::with_capacity(capacity),
+        }
+    }
+
+    /// Registers a key able to decrypt the records, without using it for the new records.
+    pub fn add_key(&mut self, key_id: u32, key: E) {
+        self.keys.insert(key_id, key);
+    }
+
+    pub fn contains_key(&self, key: &K) -> Result<bool> {
+        if self.map.contains_key(key) {
+            Ok(true)
+        } else {
+            self.db.contains_key(key)
+        }
+    }
+
+    pub fn delete(&mut self, key: &K) -> Result<()> {
+        self.db.delete(key)?;
+
+        if let Some(aged) = self.map.remove(key) {
+            self.ages.remove(aged.age);
+        }
+
+        Ok(())
+    }
+
+    fn ensure_capacity(&mut self) {
+        if self.map.capacity() == self.map.len() {
+            if let Some(key) = self.ages.oldest().cloned() {
+                if let Some(aged) = self.map.remove(&key) {
+                    self.ages.remove(aged.age);
+                    stats::eviction(self.db.name());
+                }
+            }
+        }
+    }
+
+    /// Gets a value, decrypting it from the database if it is not in memory.
+    pub fn get(&mut self, key: &K) -> Result<Option<&V>> {
+        let hit = self.map.contains_key(key);
+        stats::cache_lookup(self.db.name(), hit);
+
+        if !hit {
+            let value = match self.db.get_raw_bytes(key)? {
+                Some(record) => self.open(record.as_bytes())?,
+                None => return Ok(None),
+            };
+
+            self.ensure_capacity();
+            self.map.insert(key.clone(), Aged { age: 0, value });
+        }
+
+        let aged = self.map.get_mut(key).unwrap();
+        self.ages.touch(key, &mut aged.age);
+        Ok(Some(&aged.value))
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the id of the key encrypting the new records.
+    pub fn key_id(&self) -> u32 {
+        self.current
+    }
+
+    /// Returns the number of values kept in memory.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn put(&mut self, key: &K, value: V) -> Result<()> {
+        let record = self.seal(self.current, &serialize_to_bytes(&value)?)?;
+        self.db.put_raw(key, &record)?;
+
+        match self.map.get_mut(key) {
+            Some(aged) => aged.value = value,
+            None => {
+                self.ensure_capacity();
+                self.map.insert(key.clone(), Aged { age: 0, value });
+            }
+        }
+
+        let aged = self.map.get_mut(key).unwrap();
+        self.ages.touch(key, &mut aged.age);
+        Ok(())
+    }
+
+    /// Re-encrypts with the key `new` all the records encrypted with the key `old`, in batches,
+    /// then uses `new` for the new records. Returns the number of records re-encrypted.
+    ///
+    /// Both keys must be registered. The rotation can be resumed if interrupted, since each
+    /// record carries the id of its key.
+    pub fn rotate_key(&mut self, old: u32, new: u32) -> Result<u64> {
+        const BATCH_LEN: usize = 1000;
+
+        for id in &[old, new] {
+            if !self.keys.contains_key(id) {
+                return Err(Error::UnknownEncryptionKey(*id));
+            }
+        }
+
+        self.current = new;
+
+        if old == new {
+            return Ok(0);
+        }
+
+        let mut batch = RawBatch::new();
+        let mut count = 0;
+        let mut iter = self.db.iter(IteratorMode::Start)?;
+
+        while let Some(item) = iter.next()? {
+            let record = item.value_bytes()?;
+
+            if key_id(record, Self::HEADER_LEN)? != old {
+                continue;
+            }
+
+            let plain = self.decrypt(record)?;
+            let record = self.seal(new, &plain)?;
+
+            self.db
+                .batch_put_raw(&mut batch, item.key_as_bytes()?, &record)?;
+            count += 1;
+
+            if batch.len() >= BATCH_LEN {
+                self.db.write_raw(replace(&mut batch, RawBatch::new()))?;
+            }
+        }
+
+        if !batch.is_empty() {
+            self.db.write_raw(batch)?;
+        }
+
+        Ok(count)
+    }
+
+    fn decrypt(&self, record: &[u8]) -> Result<Vec<u8>> {
+        let id = key_id(record, Self::HEADER_LEN)?;
+        let key = self.keys.get(&id).ok_or(Error::UnknownEncryptionKey(id))?;
+        key.decrypt(
+            &record[Self::HEADER_LEN..],
+            &record[KEY_ID_LEN..Self::HEADER_LEN],
+        )
+    }
+
+    fn open(&self, record: &[u8]) -> Result<V> {
+        deserialize_from_bytes(&self.decrypt(record)?)
+    }
+
+    /// Encrypts the serialized value with a random nonce, prefixed by the key id and the nonce.
+    fn seal(&self, key_id: u32, plain: &[u8]) -> Result<Vec<u8>> {
+        let key = self
+            .keys
+            .get(&key_id)
+            .ok_or(Error::UnknownEncryptionKey(key_id))?;
+
+        let mut record = key_id.to_be_bytes().to_vec();
+        record.resize(Self::HEADER_LEN, 0);
+        getrandom::getrandom(&mut record[KEY_ID_LEN..]).map_err(io::Error::from)?;
+
+        let sealed = key.encrypt(plain, &record[KEY_ID_LEN..])?;
+        record.extend_from_slice(&sealed);
+        Ok(record)
+    }
+}
+
+/// Returns the id of the key of a record starting with a header of `header_len` bytes.
+fn key_id(record: &[u8], header_len: usize) -> Result<u32> {
+    match record.get(..KEY_ID_LEN) {
+        Some(id) if record.len() >= header_len => Ok(u32::from_be_bytes(id.try_into().unwrap())),
+        _ => Err(Error::InvalidEncryptedRecord),
+    }
+}
+
+#[cfg(all(test, any(feature = "aes-gcm", feature = "chacha20poly1305")))]
+mod tests {
+    use super::{EncryptedLruTable, KEY_ID_LEN};
+    use crate::Db;
+
+    #[cfg(feature = "chacha20poly1305")]
+    #[test]
+    fn nonce_sized_by_cipher() {
+        use chacha20poly1305::{
+            aead::{generic_array::GenericArray, NewAead},
+            XChaCha20Poly1305,
+        };
+
+        let db = Db::<u32>::open_temporary().unwrap();
+        let key = XChaCha20Poly1305::new(GenericArray::from_slice(&[7u8; 32]));
+        let mut table = EncryptedLruTable::with_capacity(db.clone(), 1, 1, key);
+
+        table.put(&1, "secret".to_string()).unwrap();
+        table.put(&2, "other".to_string()).unwrap();
+        assert_eq!(table.get(&1).unwrap().unwrap(), "secret");
+
+        let record = db.get_raw_bytes(&1).unwrap().unwrap();
+        let plain = crate::serialize_to_bytes(&"secret".to_string()).unwrap();
+        assert_eq!(record.as_bytes().len(), KEY_ID_LEN + 24 + plain.len() + 16);
+    }
+
+    #[cfg(feature = "aes-gcm")]
+    #[test]
+    fn rotate_key() {
+        use aes_gcm::{
+            aead::{generic_array::GenericArray, NewAead},
+            Aes256Gcm,
+        };
+        use std::convert::TryInto;
+
+        let cipher = |byte| Aes256Gcm::new(GenericArray::from_slice(&[byte; 32]));
+        let db = Db::<u32>::open_temporary().unwrap();
+        let mut table = EncryptedLruTable::with_capacity(db.clone(), 2, 1, cipher(1));
+
+        for key in 0..5 {
+            table.put(&key, key.to_string()).unwrap();
+        }
+
+        table.add_key(2, cipher(2));
+        assert_eq!(table.rotate_key(1, 2).unwrap(), 5);
+        assert_eq!(table.key_id(), 2);
+
+        for key in 0..5 {
+            let record = db.get_raw_bytes(&key).unwrap().unwrap();
+            let id = u32::from_be_bytes(record.as_bytes()[..KEY_ID_LEN].try_into().unwrap());
+            assert_eq!(id, 2);
+        }
+
+        // a rotation interrupted then run again has nothing left to re-encrypt.
+        assert_eq!(table.rotate_key(1, 2).unwrap(), 0);
+        drop(table);
+
+        // the old key is no longer needed to read the records.
+        let mut table = EncryptedLruTable::<u32, String, _>::with_capacity(db, 2, 2, cipher(2));
+
+        for key in 0..5 {
+            assert_eq!(table.get(&key).unwrap().unwrap(), &key.to_string());
+        }
+    }
+}
@@ -4,10 +4,41 @@ use std::{error, fmt};
 pub enum Error {
     #[cfg(feature = "aes-gcm")]
     AesGcm(aes_gcm::Error),
+    #[cfg(feature = "chacha20poly1305")]
+    ChaCha20Poly1305(chacha20poly1305::aead::Error),
+    ColumnFamilyNotFound(String),
+    ColumnFamilyUnsupported(&'static str),
+    Compression(String),
+    Conflict,
+    Context(Box<ErrorContext>),
+    #[cfg(feature = "csv")]
+    Csv(csv::Error),
+    InUse,
+    InvalidEncryptedRecord,
+    InvalidExport,
+    Io(std::io::Error),
+    JournalTargetNotFound(String),
+    #[cfg(feature = "serde_json")]
+    Json(serde_json::Error),
+    KeyEncoding(String),
+    KeyOrderUnsupported(&'static str),
+    KeyOutOfOrder(String),
+    #[cfg(feature = "tokio")]
+    Join(tokio::task::JoinError),
+    MixedDatabases,
     NoKey,
+    NoSavepoint,
     NoValue,
     RocksDb(rocksdb::Error),
+    SchemaMismatch {
+        expected: String,
+        found: String,
+    },
     Serde(Box<bincode::ErrorKind>),
+    UnknownEncryptionKey(u32),
+    UnknownExportVersion(u32),
+    UnknownSchemaVersion(u32),
+    ValueChecksum,
 }
 
 impl fmt::Display for Error {
@@ -18,21 +49,169 @@ impl fmt::Display for Error {
                 f.write_str("Encryption error: ")?;
                 e.fmt(f)
             }
+            #[cfg(feature = "chacha20poly1305")]
+            Self::ChaCha20Poly1305(e) => {
+                f.write_str("Encryption error: ")?;
+                e.fmt(f)
+            }
+            Self::ColumnFamilyNotFound(name) => {
+                f.write_str("Column family not found: ")?;
+                f.write_str(name)
+            }
+            Self::ColumnFamilyUnsupported(op) => write!(
+                f,
+                "The operation {} is not supported on a column family.",
+                op
+            ),
+            Self::Compression(e) => {
+                f.write_str("Compression error: ")?;
+                f.write_str(e)
+            }
+            Self::Conflict => f.write_str("The value has been modified concurrently."),
+            Self::Context(c) => {
+                write!(f, "Operation {} failed on {}", c.op, c.db_name)?;
+
+                if let Some(len) = c.key_len {
+                    write!(f, " (key of {} bytes)", len)?;
+                }
+
+                f.write_str(": ")?;
+                c.source.fmt(f)
+            }
+            #[cfg(feature = "csv")]
+            Self::Csv(e) => {
+                f.write_str("CSV error: ")?;
+                e.fmt(f)
+            }
+            Self::InUse => f.write_str("The database is still used by other handles."),
+            Self::InvalidEncryptedRecord => f.write_str("Invalid encrypted record."),
+            Self::InvalidExport => f.write_str("Invalid export format."),
+            Self::Io(e) => {
+                f.write_str("IO error: ")?;
+                e.fmt(f)
+            }
+            #[cfg(feature = "tokio")]
+            Self::Join(e) => {
+                f.write_str("Blocking task error: ")?;
+                e.fmt(f)
+            }
+            Self::JournalTargetNotFound(name) => {
+                f.write_str("Journal target not found: ")?;
+                f.write_str(name)
+            }
+            #[cfg(feature = "serde_json")]
+            Self::Json(e) => {
+                f.write_str("JSON error: ")?;
+                e.fmt(f)
+            }
+            Self::KeyEncoding(e) => {
+                f.write_str("Key encoding error: ")?;
+                f.write_str(e)
+            }
+            Self::KeyOrderUnsupported(op) => write!(
+                f,
+                "The operation {} requires the bytewise order of the keys.",
+                op
+            ),
+            Self::KeyOutOfOrder(key) => {
+                f.write_str("Key added out of order: ")?;
+                f.write_str(key)
+            }
+            Self::MixedDatabases => f.write_str("A transaction cannot span multiple databases."),
             Self::NoKey => f.write_str("No Key."),
+            Self::NoSavepoint => f.write_str("No savepoint is set on the batch."),
             Self::NoValue => f.write_str("No Value."),
             Self::RocksDb(e) => {
                 f.write_str("RocksDb error: ")?;
                 e.fmt(f)
             }
+            Self::SchemaMismatch { expected, found } => write!(
+                f,
+                "Schema mismatch: expected {}, found {}.",
+                expected, found
+            ),
             Self::Serde(e) => {
                 f.write_str("Serialization error: ")?;
                 e.fmt(f)
             }
+            Self::UnknownEncryptionKey(id) => write!(f, "Unknown encryption key: {}.", id),
+            Self::UnknownExportVersion(v) => write!(f, "Unknown export version: {}.", v),
+            Self::UnknownSchemaVersion(v) => write!(f, "Unknown schema version: {}.", v),
+            Self::ValueChecksum => f.write_str("The checksum of the value does not match."),
+        }
+    }
+}
+
+impl Error {
+    /// Returns the context of the failed operation, if any.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Self::Context(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    /// Returns true if RocksDB reported that the data is corrupted.
+    pub fn is_corruption(&self) -> bool {
+        self.rocksdb_status_starts_with(&["Corruption"])
+    }
+
+    /// Returns true if RocksDB reported a transient failure, such as a busy resource or a
+    /// timeout, in which case the operation can be retried.
+    pub fn is_busy(&self) -> bool {
+        self.rocksdb_status_starts_with(&[
+            "Operation aborted",
+            "Operation timed out",
+            "Resource busy",
+            "Try again",
+        ])
+    }
+
+    /// Returns true if RocksDB reported that a file or a column family was not found.
+    pub fn is_not_found(&self) -> bool {
+        matches!(self.root(), Self::ColumnFamilyNotFound(_))
+            || self.rocksdb_status_starts_with(&["NotFound"])
+    }
+
+    /// Returns the underlying error, without its context.
+    pub fn root(&self) -> &Error {
+        match self {
+            Self::Context(c) => c.source.root(),
+            e => e,
+        }
+    }
+
+    fn rocksdb_status_starts_with(&self, statuses: &[&str]) -> bool {
+        match self.root() {
+            Self::RocksDb(e) => {
+                let msg = e.to_string();
+                statuses.iter().any(|s| msg.starts_with(s))
+            }
+            _ => false,
         }
     }
 }
 
-impl error::Error for Error {}
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Context(c) => Some(&c.source),
+            Self::Io(e) => Some(e),
+            Self::RocksDb(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// The context of an operation that failed on a database.
+#[derive(Debug)]
+pub struct ErrorContext {
+    pub db_name: String,
+    /// The length of the serialized key, for the operations on a single key.
+    pub key_len: Option<usize>,
+    pub op: &'static str,
+    pub source: Error,
+}
 
 #[cfg(feature = "aes-gcm")]
 impl From<aes_gcm::Error> for Error {
@@ -41,6 +220,33 @@ impl From<aes_gcm::Error> for Error {
     }
 }
 
+#[cfg(feature = "csv")]
+impl From<csv::Error> for Error {
+    fn from(e: csv::Error) -> Self {
+        Self::Csv(e)
+    }
+}
+
+#[cfg(feature = "serde_json")]
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl From<tokio::task::JoinError> for Error {
+    fn from(e: tokio::task::JoinError) -> Self {
+        Self::Join(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
 impl From<rocksdb::Error> for Error {
     fn from(e: rocksdb::Error) -> Self {
         Self::RocksDb(e)
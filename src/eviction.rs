@@ -0,0 +1,432 @@
+use crate::aged::AgeIndex;
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
+    hash::{Hash, Hasher},
+};
+
+/// Chooses the entries removed from memory when a table is full.
+///
+/// The tables notify the policy of the keys inserted in memory, accessed and removed, then
+/// ask it for a victim when they need room. By default, the tables evict the least recently
+/// used entry, as [`Lru`] does.
+pub trait EvictionPolicy<K>: Send + Sync {
+    /// Records a lookup of a key already in memory.
+    fn access(&mut self, key: &K);
+
+    /// Records a key loaded or inserted in memory.
+    fn insert(&mut self, key: &K);
+
+    /// Records a key removed from memory, evicted or deleted.
+    fn remove(&mut self, key: &K);
+
+    /// Returns the key to evict. The table then removes it and calls [`EvictionPolicy::remove`].
+    fn victim(&mut self) -> Option<K>;
+}
+
+/// Evicts the least recently used key.
+pub struct Lru<K> {
+    ages: AgeIndex<K>,
+    keys: HashMap<K, u64>,
+}
+
+impl<K> Lru<K> {
+    pub fn new() -> Self {
+        Self {
+            ages: AgeIndex::new(),
+            keys: HashMap::new(),
+        }
+    }
+}
+
+impl<K> Default for Lru<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K> EvictionPolicy<K> for Lru<K>
+where
+    K: Clone + Eq + Hash + Send + Sync,
+{
+    fn access(&mut self, key: &K) {
+        if let Some(age) = self.keys.get_mut(key) {
+            self.ages.touch(key, age);
+        }
+    }
+
+    fn insert(&mut self, key: &K) {
+        let age = self.keys.entry(key.clone()).or_insert(0);
+        self.ages.touch(key, age);
+    }
+
+    fn remove(&mut self, key: &K) {
+        if let Some(age) = self.keys.remove(key) {
+            self.ages.remove(age);
+        }
+    }
+
+    fn victim(&mut self) -> Option<K> {
+        self.ages.oldest().cloned()
+    }
+}
+
+/// Evicts the oldest key, regardless of its accesses.
+pub struct Fifo<K> {
+    ages: AgeIndex<K>,
+    keys: HashMap<K, u64>,
+}
+
+impl<K> Fifo<K> {
+    pub fn new() -> Self {
+        Self {
+            ages: AgeIndex::new(),
+            keys: HashMap::new(),
+        }
+    }
+}
+
+impl<K> Default for Fifo<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K> EvictionPolicy<K> for Fifo<K>
+where
+    K: Clone + Eq + Hash + Send + Sync,
+{
+    fn access(&mut self, _key: &K) {}
+
+    fn insert(&mut self, key: &K) {
+        let age = self.keys.entry(key.clone()).or_insert(0);
+        self.ages.touch(key, age);
+    }
+
+    fn remove(&mut self, key: &K) {
+        if let Some(age) = self.keys.remove(key) {
+            self.ages.remove(age);
+        }
+    }
+
+    fn victim(&mut self) -> Option<K> {
+        self.ages.oldest().cloned()
+    }
+}
+
+/// Evicts the least frequently used key, the least recently used one among equals.
+///
+/// The frequency of a key is forgotten once it is evicted.
+pub struct Lfu<K> {
+    keys: HashMap<K, (u64, u64)>,
+    order: BTreeMap<(u64, u64), K>,
+    tick: u64,
+}
+
+impl<K> Lfu<K> {
+    pub fn new() -> Self {
+        Self {
+            keys: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+}
+
+impl<K> Default for Lfu<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K> EvictionPolicy<K> for Lfu<K>
+where
+    K: Clone + Eq + Hash + Send + Sync,
+{
+    fn access(&mut self, key: &K) {
+        if let Some(rank) = self.keys.get_mut(key) {
+            self.order.remove(rank);
+            self.tick += 1;
+            *rank = (rank.0 + 1, self.tick);
+            self.order.insert(*rank, key.clone());
+        }
+    }
+
+    fn insert(&mut self, key: &K) {
+        if self.keys.contains_key(key) {
+            return self.access(key);
+        }
+
+        self.tick += 1;
+        self.keys.insert(key.clone(), (1, self.tick));
+        self.order.insert((1, self.tick), key.clone());
+    }
+
+    fn remove(&mut self, key: &K) {
+        if let Some(rank) = self.keys.remove(key) {
+            self.order.remove(&rank);
+        }
+    }
+
+    fn victim(&mut self) -> Option<K> {
+        self.order.values().next().cloned()
+    }
+}
+
+/// Window TinyLFU: new keys enter a small LRU window, then are admitted in the main LRU
+/// only if they are used more often than its victim.
+///
+/// The frequencies are estimated by a compact sketch that remembers the evicted keys, so
+/// a scan of cold keys does not flush the hot ones.
+pub struct WTinyLfu<K> {
+    main: Lru<K>,
+    sketch: FrequencySketch,
+    window: Lru<K>,
+    window_capacity: usize,
+    window_len: usize,
+    windowed: HashMap<K, bool>,
+}
+
+impl<K> WTinyLfu<K> {
+    /// Creates a policy for a table of `capacity` entries, 1% of them being in the window.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            main: Lru::new(),
+            sketch: FrequencySketch::new(capacity),
+            window: Lru::new(),
+            window_capacity: (capacity / 100).max(1),
+            window_len: 0,
+            windowed: HashMap::new(),
+        }
+    }
+}
+
+impl<K> EvictionPolicy<K> for WTinyLfu<K>
+where
+    K: Clone + Eq + Hash + Send + Sync,
+{
+    fn access(&mut self, key: &K) {
+        self.sketch.increment(key);
+
+        match self.windowed.get(key) {
+            Some(true) => self.window.access(key),
+            Some(false) => self.main.access(key),
+            None => {}
+        }
+    }
+
+    fn insert(&mut self, key: &K) {
+        if self.windowed.contains_key(key) {
+            return self.access(key);
+        }
+
+        self.sketch.increment(key);
+        self.window.insert(key);
+        self.window_len += 1;
+        self.windowed.insert(key.clone(), true);
+    }
+
+    fn remove(&mut self, key: &K) {
+        match self.windowed.remove(key) {
+            Some(true) => {
+                self.window.remove(key);
+                self.window_len -= 1;
+            }
+            Some(false) => {
+                self.main.remove(key);
+            }
+            None => {}
+        }
+    }
+
+    fn victim(&mut self) -> Option<K> {
+        // the window overflows in the main space, where the candidate competes with the victim.
+        while self.window_len > self.window_capacity {
+            let candidate = self.window.victim()?;
+
+            match self.main.victim() {
+                Some(victim)
+                    if self.sketch.estimate(&candidate) <= self.sketch.estimate(&victim) =>
+                {
+                    return Some(candidate);
+                }
+                _ => {
+                    self.window.remove(&candidate);
+                    self.window_len -= 1;
+                    self.main.insert(&candidate);
+                    self.windowed.insert(candidate, false);
+                }
+            }
+        }
+
+        self.main.victim().or_else(|| self.window.victim())
+    }
+}
+
+/// A count-min sketch of 4-bit counters, halved periodically so the old accesses fade out.
+struct FrequencySketch {
+    additions: usize,
+    counters: Vec<u8>,
+    mask: usize,
+    sample_size: usize,
+}
+
+impl FrequencySketch {
+    const ROWS: u64 = 4;
+
+    fn new(capacity: usize) -> Self {
+        let width = capacity.max(16).next_power_of_two();
+
+        Self {
+            additions: 0,
+            counters: vec![0; width * Self::ROWS as usize],
+            mask: width - 1,
+            sample_size: width * 10,
+        }
+    }
+
+    fn estimate<K: Hash>(&self, key: &K) -> u8 {
+        (0..Self::ROWS)
+            .map(|row| self.counters[self.index(key, row)])
+            .min()
+            .unwrap_or(0)
+    }
+
+    fn increment<K: Hash>(&mut self, key: &K) {
+        for row in 0..Self::ROWS {
+            let i = self.index(key, row);
+
+            if self.counters[i] < 15 {
+                self.counters[i] += 1;
+            }
+        }
+
+        self.additions += 1;
+
+        if self.additions == self.sample_size {
+            self.additions /= 2;
+            self.counters.iter_mut().for_each(|c| *c /= 2);
+        }
+    }
+
+    fn index<K: Hash>(&self, key: &K, row: u64) -> usize {
+        let mut hasher = DefaultHasher::new();
+        row.hash(&mut hasher);
+        key.hash(&mut hasher);
+        row as usize * (self.mask + 1) + (hasher.finish() as usize & self.mask)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EvictionPolicy, FrequencySketch, Lfu, Lru, WTinyLfu};
+    use std::collections::HashSet;
+
+    /// Simulates a table of `capacity` entries reading `keys`, returning the keys in memory.
+    fn run<P: EvictionPolicy<u32>>(policy: &mut P, capacity: usize, keys: &[u32]) -> HashSet<u32> {
+        let mut cached = HashSet::new();
+
+        for key in keys {
+            if cached.contains(key) {
+                policy.access(key);
+                continue;
+            }
+
+            if cached.len() == capacity {
+                let victim = policy.victim().unwrap();
+                policy.remove(&victim);
+                cached.remove(&victim);
+            }
+
+            policy.insert(key);
+            cached.insert(*key);
+        }
+
+        cached
+    }
+
+    #[test]
+    fn lfu_victim() {
+        let mut lfu = Lfu::new();
+
+        for key in &[1, 2, 3] {
+            lfu.insert(key);
+        }
+
+        for key in &[1, 1, 3] {
+            lfu.access(key);
+        }
+
+        assert_eq!(lfu.victim(), Some(2));
+        lfu.remove(&2);
+
+        // the least recently used key among the least frequently used.
+        lfu.insert(&4);
+        lfu.insert(&5);
+        assert_eq!(lfu.victim(), Some(4));
+
+        // a key evicted then reinserted starts over.
+        lfu.remove(&1);
+        lfu.insert(&1);
+        lfu.remove(&4);
+        lfu.remove(&5);
+        assert_eq!(lfu.victim(), Some(1));
+    }
+
+    #[test]
+    fn sketch_saturates_and_halves() {
+        let mut sketch = FrequencySketch::new(16);
+
+        for _ in 0..20 {
+            sketch.increment(&0u32);
+        }
+
+        assert_eq!(sketch.estimate(&0u32), 15);
+
+        // the 160th addition halves the counters.
+        for key in 1..141u32 {
+            sketch.increment(&key);
+        }
+
+        assert_eq!(sketch.additions, 80);
+        assert_eq!(sketch.estimate(&0u32), 7);
+    }
+
+    #[test]
+    fn wtinylfu_window_promotion() {
+        let mut policy = WTinyLfu::new(100);
+
+        policy.insert(&1);
+        policy.insert(&2);
+
+        // the window holds one key, the first one is promoted to the empty main space.
+        assert_eq!(policy.victim(), Some(1));
+        assert_eq!(policy.windowed.get(&1), Some(&false));
+
+        // a candidate not used more often than the main victim is evicted.
+        policy.insert(&3);
+        assert_eq!(policy.victim(), Some(2));
+        policy.remove(&2);
+
+        // a candidate used more often is admitted, evicting the main victim.
+        for _ in 0..3 {
+            policy.access(&3);
+        }
+
+        policy.insert(&4);
+        assert_eq!(policy.victim(), Some(1));
+        assert_eq!(policy.windowed.get(&3), Some(&false));
+    }
+
+    #[test]
+    fn wtinylfu_scan_keeps_hot_key() {
+        let mut keys = vec![0; 15];
+        keys.extend(1..3001);
+
+        let cached = run(&mut WTinyLfu::new(1000), 1000, &keys);
+        assert!(cached.contains(&0));
+
+        // the same scan flushes the hot key out of a LRU.
+        let cached = run(&mut Lru::new(), 1000, &keys);
+        assert!(!cached.contains(&0));
+    }
+}
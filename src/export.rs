@@ -0,0 +1,80 @@
+//! The portable format of [`Db::export_to_writer`](crate::Db::export_to_writer).
+//!
+//! An export starts with the magic bytes and the version of the format, followed by the entries.
+//! Each entry is a `1` tag then the key and the value, both prefixed by their length as a
+//! little-endian `u32`. A `0` tag ends the export.
+
+use crate::{Error, Result};
+use std::{
+    convert::TryFrom,
+    io::{Read, Write},
+};
+
+const MAGIC: &[u8; 4] = b"RTBX";
+const VERSION: u32 = 1;
+
+const TAG_END: u8 = 0;
+const TAG_ENTRY: u8 = 1;
+
+pub(crate) fn write_header<W: Write>(w: &mut W) -> Result<()> {
+    w.write_all(MAGIC)?;
+    w.write_all(&VERSION.to_le_bytes())?;
+    Ok(())
+}
+
+pub(crate) fn write_entry<W: Write>(w: &mut W, key: &[u8], value: &[u8]) -> Result<()> {
+    w.write_all(&[TAG_ENTRY])?;
+    write_bytes(w, key)?;
+    write_bytes(w, value)
+}
+
+pub(crate) fn write_end<W: Write>(w: &mut W) -> Result<()> {
+    w.write_all(&[TAG_END])?;
+    w.flush()?;
+    Ok(())
+}
+
+fn write_bytes<W: Write>(w: &mut W, bytes: &[u8]) -> Result<()> {
+    let len = u32::try_from(bytes.len()).map_err(|_| Error::InvalidExport)?;
+    w.write_all(&len.to_le_bytes())?;
+    w.write_all(bytes)?;
+    Ok(())
+}
+
+pub(crate) fn read_header<R: Read>(r: &mut R) -> Result<()> {
+    let mut magic = [0; 4];
+    r.read_exact(&mut magic)?;
+
+    if &magic != MAGIC {
+        return Err(Error::InvalidExport);
+    }
+
+    match read_u32(r)? {
+        VERSION => Ok(()),
+        v => Err(Error::UnknownExportVersion(v)),
+    }
+}
+
+/// Reads the next key / value, `None` once the end of the export has been reached.
+pub(crate) fn read_entry<R: Read>(r: &mut R) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+    let mut tag = [0];
+    r.read_exact(&mut tag)?;
+
+    match tag[0] {
+        TAG_END => Ok(None),
+        TAG_ENTRY => Ok(Some((read_bytes(r)?, read_bytes(r)?))),
+        _ => Err(Error::InvalidExport),
+    }
+}
+
+fn read_bytes<R: Read>(r: &mut R) -> Result<Vec<u8>> {
+    let mut bytes = vec![0; read_u32(r)? as usize];
+    r.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_u32<R: Read>(r: &mut R) -> Result<u32> {
+    let mut buf = [0; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
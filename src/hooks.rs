@@ -0,0 +1,155 @@
+use crate::{deserialize_from_bytes, key_ser::key_from_bytes, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Debug},
+    marker::PhantomData,
+    sync::RwLock,
+};
+
+/// The kind of write notified by a [`ChangeEvent`].
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ChangeKind {
+    /// All the keys have been removed.
+    Clear,
+    Delete,
+    /// The keys from [`ChangeEvent::key`] to [`ChangeEvent::range_end`], excluded, have been removed.
+    DeleteRange,
+    Merge,
+    Put,
+}
+
+/// A serialized write, shared by the subscribers of a database.
+#[derive(Clone, Debug)]
+pub(crate) struct Change {
+    pub(crate) kind: ChangeKind,
+    pub(crate) key: Vec<u8>,
+    pub(crate) value: Option<Vec<u8>>,
+}
+
+/// A write on a [`Db`](crate::Db), notified to its subscribers once it has been written.
+pub struct ChangeEvent<K> {
+    _k: PhantomData<fn() -> K>,
+    change: Change,
+}
+
+impl<K> ChangeEvent<K> {
+    pub(crate) fn new(change: Change) -> Self {
+        Self {
+            _k: PhantomData,
+            change,
+        }
+    }
+
+    #[inline]
+    pub fn kind(&self) -> ChangeKind {
+        self.change.kind
+    }
+
+    /// Returns the serialized key, empty for a [`ChangeKind::Clear`].
+    #[inline]
+    pub fn key_bytes(&self) -> &[u8] {
+        &self.change.key
+    }
+
+    /// Returns the serialized value of a put, the operand of a merge or the end of a removed range.
+    #[inline]
+    pub fn value_bytes(&self) -> Option<&[u8]> {
+        self.change.value.as_deref()
+    }
+}
+
+impl<K> ChangeEvent<K>
+where
+    K: for<'de> Deserialize<'de>,
+{
+    /// Deserializes the changed key, the start of the range for a [`ChangeKind::DeleteRange`].
+    pub fn key(&self) -> Result<K> {
+        key_from_bytes(&self.change.key)
+    }
+
+    /// Deserializes the end of the range of a [`ChangeKind::DeleteRange`].
+    pub fn range_end(&self) -> Result<Option<K>> {
+        match (self.change.kind, &self.change.value) {
+            (ChangeKind::DeleteRange, Some(end)) => Ok(Some(key_from_bytes(end)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Deserializes the value of a [`ChangeKind::Put`] or the operand of a [`ChangeKind::Merge`].
+    pub fn value<V>(&self) -> Result<Option<V>>
+    where
+        V: for<'de> Deserialize<'de>,
+    {
+        match (self.change.kind, &self.change.value) {
+            (ChangeKind::Put, Some(v)) | (ChangeKind::Merge, Some(v)) => {
+                Ok(Some(deserialize_from_bytes(v)?))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+impl<K> Clone for ChangeEvent<K> {
+    fn clone(&self) -> Self {
+        Self::new(self.change.clone())
+    }
+}
+
+impl<K> Debug for ChangeEvent<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ChangeEvent")
+            .field("kind", &self.change.kind)
+            .field("key", &self.change.key)
+            .field("value", &self.change.value)
+            .finish()
+    }
+}
+
+/// A callback returning false once it no longer needs the changes, to be removed.
+type Hook = Box<dyn Fn(&Change) -> bool + Send + Sync>;
+
+/// The callbacks notified of the writes on a database, shared by the clones of a [`Db`](crate::Db).
+#[derive(Default)]
+pub(crate) struct Hooks(RwLock<HookList>);
+
+#[derive(Default)]
+struct HookList {
+    hooks: Vec<(u64, Hook)>,
+    next_id: u64,
+}
+
+impl Hooks {
+    pub(crate) fn add(&self, hook: Hook) {
+        let mut list = self.0.write().unwrap_or_else(|e| e.into_inner());
+        let id = list.next_id;
+
+        list.next_id += 1;
+        list.hooks.push((id, hook));
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .hooks
+            .is_empty()
+    }
+
+    pub(crate) fn notify(&self, change: &Change) {
+        let closed = self
+            .0
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .hooks
+            .iter()
+            .filter(|(_, hook)| !hook(change))
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+
+        // removed by id, since a concurrent write may have removed them already.
+        if !closed.is_empty() {
+            let mut list = self.0.write().unwrap_or_else(|e| e.into_inner());
+            list.hooks.retain(|(id, _)| !closed.contains(id));
+        }
+    }
+}
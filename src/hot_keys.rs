@@ -0,0 +1,138 @@
+use std::{
+    borrow::Borrow,
+    collections::{BTreeMap, HashMap, HashSet},
+    hash::Hash,
+};
+
+/// The metadata entry where the hottest keys of a table are persisted, hottest first.
+pub(crate) const HOT_KEYS: &str = "hot_keys";
+
+/// Tracks the most frequently accessed keys with the space-saving algorithm.
+///
+/// At most `capacity` keys are counted; a new key replaces the least counted one and
+/// inherits its count, so the counts are upper bounds of the real frequencies.
+pub(crate) struct HotKeys<K> {
+    /// The keys by count, so the least counted key is found without scanning the keys.
+    buckets: BTreeMap<u64, HashSet<K>>,
+    capacity: usize,
+    counts: HashMap<K, u64>,
+}
+
+impl<K> HotKeys<K>
+where
+    K: Clone + Eq + Hash,
+{
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            buckets: BTreeMap::new(),
+            capacity,
+            counts: HashMap::with_capacity(capacity),
+        }
+    }
+
+    pub fn record<Q: ?Sized>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ToOwned<Owned = K>,
+    {
+        let old = self.counts.get_mut(key).map(|count| {
+            *count += 1;
+            *count - 1
+        });
+
+        if let Some(old) = old {
+            if let Some(key) = self.take(old, key) {
+                self.buckets.entry(old + 1).or_default().insert(key);
+            }
+
+            return;
+        }
+
+        let count = if self.counts.len() < self.capacity {
+            1
+        } else {
+            let min = self
+                .buckets
+                .iter()
+                .next()
+                .and_then(|(count, keys)| Some((*count, keys.iter().next()?.clone())));
+
+            match min {
+                Some((min_count, min_key)) => {
+                    self.take(min_count, &min_key);
+                    self.counts.remove(&min_key);
+                    min_count + 1
+                }
+                None => return,
+            }
+        };
+
+        let key = key.to_owned();
+        self.counts.insert(key.clone(), count);
+        self.buckets.entry(count).or_default().insert(key);
+    }
+
+    /// Returns the tracked keys with their counts, hottest first.
+    pub fn top(&self) -> Vec<(K, u64)> {
+        self.buckets
+            .iter()
+            .rev()
+            .flat_map(|(count, keys)| keys.iter().map(move |k| (k.clone(), *count)))
+            .collect()
+    }
+
+    /// Removes a key from the bucket of its count, returning it.
+    fn take<Q: ?Sized>(&mut self, count: u64, key: &Q) -> Option<K>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash,
+    {
+        let keys = self.buckets.get_mut(&count)?;
+        let key = keys.take(key);
+
+        if keys.is_empty() {
+            self.buckets.remove(&count);
+        }
+
+        key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HotKeys;
+
+    #[test]
+    fn counts_hottest_first() {
+        let mut hot_keys = HotKeys::new(3);
+
+        for key in &[1, 2, 2, 3, 3, 3] {
+            hot_keys.record(key);
+        }
+
+        assert_eq!(hot_keys.top(), vec![(3, 3), (2, 2), (1, 1)]);
+    }
+
+    #[test]
+    fn replaces_least_counted() {
+        let mut hot_keys = HotKeys::new(2);
+
+        for key in &[1, 1, 1, 2, 3] {
+            hot_keys.record(key);
+        }
+
+        // 3 replaces 2 and inherits its count.
+        assert_eq!(hot_keys.top(), vec![(1, 3), (3, 2)]);
+
+        hot_keys.record(&3);
+        hot_keys.record(&3);
+        assert_eq!(hot_keys.top(), vec![(3, 4), (1, 3)]);
+    }
+
+    #[test]
+    fn zero_capacity() {
+        let mut hot_keys = HotKeys::new(0);
+        hot_keys.record(&1);
+        assert!(hot_keys.top().is_empty());
+    }
+}
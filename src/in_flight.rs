@@ -0,0 +1,53 @@
+use std::{
+    collections::HashSet,
+    hash::Hash,
+    sync::{Condvar, Mutex, MutexGuard},
+};
+
+/// The keys being loaded, so a single thread loads a key while the others wait for it.
+pub(crate) struct InFlight<K> {
+    done: Condvar,
+    keys: Mutex<HashSet<K>>,
+}
+
+impl<K: Clone + Eq + Hash> InFlight<K> {
+    pub fn new() -> Self {
+        Self {
+            done: Condvar::new(),
+            keys: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Waits until no other thread loads the key, then marks it as loaded by this thread
+    /// until the guard is dropped.
+    pub fn enter<'a>(&'a self, key: &K) -> InFlightGuard<'a, K> {
+        let mut keys = self.lock();
+
+        while keys.contains(key) {
+            keys = self.done.wait(keys).unwrap_or_else(|e| e.into_inner());
+        }
+
+        keys.insert(key.clone());
+
+        InFlightGuard {
+            in_flight: self,
+            key: key.clone(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<HashSet<K>> {
+        self.keys.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+pub(crate) struct InFlightGuard<'a, K: Clone + Eq + Hash> {
+    in_flight: &'a InFlight<K>,
+    key: K,
+}
+
+impl<'a, K: Clone + Eq + Hash> Drop for InFlightGuard<'a, K> {
+    fn drop(&mut self) {
+        self.in_flight.lock().remove(&self.key);
+        self.in_flight.done.notify_all();
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{Db, RawBatch, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Debug,
+    marker::PhantomData,
+    mem::replace,
+    time::{Duration, Instant},
+};
+
+/// Accumulates writes in memory and commits them in batches, to amortize the cost of each
+/// write and of the WAL syncs when ingesting many records.
+///
+/// A batch is committed once it holds [`max_bytes`](Self::max_bytes) or its first write is
+/// older than [`max_delay`](Self::max_delay), checked on each write, then on
+/// [`flush`](Self::flush) or when the buffer is dropped. The buffered writes are not visible
+/// to the readers until committed.
+pub struct IngestBuffer<K, V> {
+    _v: PhantomData<V>,
+    batch: RawBatch,
+    db: Db<K>,
+    max_bytes: usize,
+    max_delay: Duration,
+    oldest: Option<Instant>,
+}
+
+impl<K, V> IngestBuffer<K, V>
+where
+    K: Debug + for<'de> Deserialize<'de> + Serialize,
+    V: Serialize,
+{
+    /// Creates a buffer committing every 4 MiB or 100 ms.
+    pub fn new(db: Db<K>) -> Self {
+        Self {
+            _v: PhantomData,
+            batch: RawBatch::new(),
+            db,
+            max_bytes: 4 << 20,
+            max_delay: Duration::from_millis(100),
+            oldest: None,
+        }
+    }
+
+    /// Sets the number of bytes of keys / values that triggers a commit.
+    pub fn max_bytes(mut self, bytes: usize) -> Self {
+        self.max_bytes = bytes;
+        self
+    }
+
+    /// Sets the age of the oldest buffered write that triggers a commit.
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    pub fn delete(&mut self, key: &K) -> Result<()> {
+        self.db.batch_delete(&mut self.batch, key)?;
+        self.written()
+    }
+
+    /// Commits the buffered writes.
+    pub fn flush(&mut self) -> Result<()> {
+        self.oldest = None;
+
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+
+        self.db.write_raw(replace(&mut self.batch, RawBatch::new()))
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.batch.is_empty()
+    }
+
+    /// Returns the number of buffered writes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.batch.len()
+    }
+
+    pub fn put(&mut self, key: &K, value: &V) -> Result<()> {
+        self.db.batch_put(&mut self.batch, key, value)?;
+        self.written()
+    }
+
+    /// Commits the batch if it is full or too old.
+    fn written(&mut self) -> Result<()> {
+        let oldest = *self.oldest.get_or_insert_with(Instant::now);
+
+        if self.batch.bytes() >= self.max_bytes || oldest.elapsed() >= self.max_delay {
+            self.flush()
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<K, V> Drop for IngestBuffer<K, V> {
+    fn drop(&mut self) {
+        // the errors are logged by the database.
+        if !self.batch.is_empty() {
+            let _ = self.db.write_raw(replace(&mut self.batch, RawBatch::new()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IngestBuffer;
+    use crate::Db;
+    use std::time::Duration;
+
+    #[test]
+    fn drop_commits_buffered_writes() {
+        let db = Db::<u32>::open_temporary().unwrap();
+        let mut buffer = IngestBuffer::new(db.clone()).max_delay(Duration::from_secs(3600));
+
+        for key in 0..10 {
+            buffer.put(&key, &(key * 2)).unwrap();
+        }
+
+        assert_eq!(buffer.len(), 10);
+        assert!(db.get(&1).unwrap().is_none());
+
+        drop(buffer);
+
+        for key in 0..10 {
+            let value = db.get(&key).unwrap().unwrap().to_inner::<u32>().unwrap();
+            assert_eq!(value, key * 2);
+        }
+    }
+}
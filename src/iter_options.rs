@@ -0,0 +1,60 @@
+use rocksdb::ReadOptions;
+
+/// Configures the RocksDB read options of an iteration or a read, to tune the scans of large
+/// ranges without affecting the other readers.
+///
+/// By default, the blocks read are added to the block cache, no read-ahead is done and the
+/// checksums are not verified, just like [`Db::iter`](crate::Db::iter) and [`Db::get`](crate::Db::get).
+#[derive(Clone, Debug, Default)]
+pub struct IterOptions {
+    fill_cache: Option<bool>,
+    readahead_size: Option<usize>,
+    verify_checksums: Option<bool>,
+}
+
+impl IterOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Options for a full scan: the blocks read are not cached and are read ahead by 2 MiB.
+    pub fn bulk_scan() -> Self {
+        Self::new().fill_cache(false).readahead_size(2 << 20)
+    }
+
+    /// Sets whether the blocks read are added to the block cache. Defaults to true.
+    pub fn fill_cache(mut self, fill: bool) -> Self {
+        self.fill_cache = Some(fill);
+        self
+    }
+
+    /// Sets the number of bytes read ahead from the files, useful for sequential scans on spinning disks.
+    pub fn readahead_size(mut self, size: usize) -> Self {
+        self.readahead_size = Some(size);
+        self
+    }
+
+    /// Sets whether the checksums of the blocks read are verified, failing on a corruption.
+    pub fn verify_checksums(mut self, verify: bool) -> Self {
+        self.verify_checksums = Some(verify);
+        self
+    }
+
+    pub(crate) fn read_options(&self) -> ReadOptions {
+        let mut opts = ReadOptions::default();
+
+        if let Some(fill) = self.fill_cache {
+            opts.fill_cache(fill);
+        }
+
+        if let Some(size) = self.readahead_size {
+            opts.set_readahead_size(size);
+        }
+
+        if let Some(verify) = self.verify_checksums {
+            opts.set_verify_checksums(verify);
+        }
+
+        opts
+    }
+}
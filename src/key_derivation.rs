@@ -0,0 +1,64 @@
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+#[cfg(feature = "aes-gcm")]
+use aes_gcm::{
+    aead::{generic_array::GenericArray, NewAead},
+    Aes256Gcm,
+};
+#[cfg(feature = "chacha20poly1305")]
+use chacha20poly1305::{
+    aead::{generic_array::GenericArray as ChaChaArray, NewAead as ChaChaNewAead},
+    ChaCha20Poly1305, XChaCha20Poly1305,
+};
+
+/// Derives a distinct 256 bits key for each table from a single master key, using HKDF-SHA256
+/// with the name of the table as context.
+///
+/// A leaked table key does not expose the other tables, and the master key is never used to
+/// encrypt data directly.
+pub struct KeyDerivation {
+    hkdf: Hkdf<Sha256>,
+}
+
+impl KeyDerivation {
+    pub fn new(master_key: &[u8]) -> Self {
+        Self {
+            hkdf: Hkdf::new(None, master_key),
+        }
+    }
+
+    /// Derives the keys with a salt, such as a random value stored next to the database.
+    pub fn with_salt(master_key: &[u8], salt: &[u8]) -> Self {
+        Self {
+            hkdf: Hkdf::new(Some(salt), master_key),
+        }
+    }
+
+    /// Returns the key of the table `table`.
+    pub fn derive_key(&self, table: &str) -> [u8; 32] {
+        let mut key = [0; 32];
+
+        // 32 bytes is always a valid HKDF-SHA256 output length.
+        self.hkdf
+            .expand(table.as_bytes(), &mut key)
+            .expect("valid key length");
+
+        key
+    }
+
+    #[cfg(feature = "aes-gcm")]
+    pub fn aes_256_gcm(&self, table: &str) -> Aes256Gcm {
+        Aes256Gcm::new(GenericArray::from_slice(&self.derive_key(table)))
+    }
+
+    #[cfg(feature = "chacha20poly1305")]
+    pub fn chacha20_poly1305(&self, table: &str) -> ChaCha20Poly1305 {
+        ChaChaNewAead::new(ChaChaArray::from_slice(&self.derive_key(table)))
+    }
+
+    #[cfg(feature = "chacha20poly1305")]
+    pub fn xchacha20_poly1305(&self, table: &str) -> XChaCha20Poly1305 {
+        ChaChaNewAead::new(ChaChaArray::from_slice(&self.derive_key(table)))
+    }
+}
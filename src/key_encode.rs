@@ -0,0 +1,114 @@
+use crate::Result;
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::{borrow::Cow, fmt, marker::PhantomData};
+
+/// The name of the newtype recognized by the key encoding, which then stores the bytes as is.
+pub(crate) const ENCODED_KEY: &str = "$rocks_tables::Encoded";
+
+/// A custom byte encoding of a key, replacing the order-preserving encoding of the crate.
+///
+/// The key is used through [`Encoded`], e.g. `Db<Encoded<Uuid>>`. When it is the whole key,
+/// the bytes are stored unchanged, so the keys can be read by other languages or written in an
+/// external format; nested in a tuple or a struct, they are escaped like a byte array.
+///
+/// The iterations return the keys in the order of their encoded bytes.
+pub trait KeyEncode: Sized {
+    /// Encodes the key. The encoding cannot be empty.
+    fn encode(&self) -> Cow<[u8]>;
+
+    fn decode(bytes: &[u8]) -> Result<Self>;
+}
+
+impl KeyEncode for Vec<u8> {
+    fn encode(&self) -> Cow<[u8]> {
+        Cow::Borrowed(self)
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        Ok(bytes.to_vec())
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl KeyEncode for uuid::Uuid {
+    fn encode(&self) -> Cow<[u8]> {
+        Cow::Borrowed(self.as_bytes())
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        uuid::Uuid::from_slice(bytes).map_err(|e| crate::Error::KeyEncoding(e.to_string()))
+    }
+}
+
+/// A key serialized with its [`KeyEncode`] implementation.
+///
+/// Outside the keys, e.g. in the values, it is serialized as a byte array.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Encoded<T>(pub T);
+
+impl<T> Encoded<T> {
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for Encoded<T> {
+    #[inline]
+    fn from(key: T) -> Self {
+        Self(key)
+    }
+}
+
+impl<T: KeyEncode> Serialize for Encoded<T> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct(ENCODED_KEY, &Bytes(&self.0.encode()))
+    }
+}
+
+impl<'de, T: KeyEncode> Deserialize<'de> for Encoded<T> {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(ENCODED_KEY, EncodedVisitor(PhantomData))
+    }
+}
+
+struct Bytes<'a>(&'a [u8]);
+
+impl<'a> Serialize for Bytes<'a> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+struct EncodedVisitor<T>(PhantomData<T>);
+
+impl<'de, T: KeyEncode> Visitor<'de> for EncodedVisitor<T> {
+    type Value = Encoded<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an encoded key")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> std::result::Result<Self::Value, E> {
+        T::decode(v).map(Encoded).map_err(E::custom)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> std::result::Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(self)
+    }
+}
@@ -0,0 +1,986 @@
+//! An order-preserving encoding of the keys.
+//!
+//! The bytes of the encoded keys compare like the keys themselves, so iterating the database
+//! returns the keys in their natural order:
+//! - integers are fixed-width big endian, the sign bit of signed integers being flipped;
+//! - floats are big endian, their bits being flipped so that negative values come first;
+//!   `-0.0` is stored as `0.0` and all the NaNs as a single positive NaN, sorted last;
+//! - strings and byte arrays have their `0` bytes escaped as `0 255` and end with `0 1`;
+//! - sequences and maps prefix each element with `1` and end with `0`;
+//! - tuples and structs are the concatenation of their fields, so a tuple starts with the
+//!   encoding of its first fields;
+//! - the [`Encoded`](crate::Encoded) keys are stored as is when they are the whole key, or
+//!   escaped like byte arrays otherwise.
+
+use crate::{key_encode::ENCODED_KEY, Error, Result};
+use serde::{
+    de::{self, DeserializeSeed, IntoDeserializer, Visitor},
+    ser::{self, Serialize},
+    Deserialize,
+};
+use std::{
+    convert::TryInto,
+    fmt,
+    mem::{replace, size_of, take},
+};
+
+type KeyResult<T> = std::result::Result<T, KeyError>;
+
+#[derive(Debug)]
+struct KeyError(String);
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for KeyError {}
+
+impl ser::Error for KeyError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+impl de::Error for KeyError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+#[inline]
+pub(crate) fn key_to_bytes<T: ?Sized + Serialize>(key: &T) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    serialize_key(key, true, &mut out)?;
+    Ok(out)
+}
+
+/// Appends the encoded key to `out`, reusing its allocation.
+#[inline]
+pub(crate) fn key_into_bytes<T: ?Sized + Serialize>(key: &T, out: &mut Vec<u8>) -> Result<()> {
+    serialize_key(key, true, out)
+}
+
+/// Encodes the first fields of a key, as they are encoded in the whole key.
+#[inline]
+pub(crate) fn key_prefix_to_bytes<T: ?Sized + Serialize>(prefix: &T) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    serialize_key(prefix, false, &mut out)?;
+    Ok(out)
+}
+
+fn serialize_key<T: ?Sized + Serialize>(key: &T, root: bool, out: &mut Vec<u8>) -> Result<()> {
+    let mut ser = KeySerializer {
+        out: take(out),
+        raw: false,
+        root,
+    };
+    let res = key.serialize(&mut ser);
+    *out = ser.out;
+    res.map_err(|e| Error::KeyEncoding(e.0))
+}
+
+pub(crate) fn key_from_bytes<'a, T: Deserialize<'a>>(bytes: &'a [u8]) -> Result<T> {
+    let mut de = KeyDeserializer {
+        input: bytes,
+        root: true,
+    };
+    let key = T::deserialize(&mut de).map_err(|e| Error::KeyEncoding(e.0))?;
+
+    if de.input.is_empty() {
+        Ok(key)
+    } else {
+        Err(Error::KeyEncoding(
+            "Trailing bytes after the key.".to_string(),
+        ))
+    }
+}
+
+/// Merges the zeros and the NaNs, which compare equal in the `Ord` wrappers of floats.
+fn canonical_f32(v: f32) -> f32 {
+    if v == 0.0 {
+        0.0
+    } else if v.is_nan() {
+        f32::from_bits(0x7fc0_0000)
+    } else {
+        v
+    }
+}
+
+fn canonical_f64(v: f64) -> f64 {
+    if v == 0.0 {
+        0.0
+    } else if v.is_nan() {
+        f64::from_bits(0x7ff8_0000_0000_0000)
+    } else {
+        v
+    }
+}
+
+/// Returns the number of bytes used by the encoded `T` at the start of `bytes`, `T` being the
+/// first fields of the key.
+pub(crate) fn key_serialized_len<T: for<'de> Deserialize<'de>>(bytes: &[u8]) -> Option<usize> {
+    let mut de = KeyDeserializer {
+        input: bytes,
+        root: false,
+    };
+    T::deserialize(&mut de).ok()?;
+    Some(bytes.len() - de.input.len())
+}
+
+struct KeySerializer {
+    out: Vec<u8>,
+    /// True when the next bytes are an encoded key, stored as is.
+    raw: bool,
+    /// True until a compound value is started, while a newtype is the whole key.
+    root: bool,
+}
+
+impl KeySerializer {
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.out.push(b);
+
+            if b == 0 {
+                self.out.push(0xFF);
+            }
+        }
+
+        self.out.extend_from_slice(&[0, 1]);
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut KeySerializer {
+    type Ok = ();
+    type Error = KeyError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bool(self, v: bool) -> KeyResult<()> {
+        self.out.push(v as u8);
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> KeyResult<()> {
+        self.out.push(v as u8 ^ 0x80);
+        Ok(())
+    }
+
+    fn serialize_i16(self, v: i16) -> KeyResult<()> {
+        self.serialize_u16(v as u16 ^ (1 << 15))
+    }
+
+    fn serialize_i32(self, v: i32) -> KeyResult<()> {
+        self.serialize_u32(v as u32 ^ (1 << 31))
+    }
+
+    fn serialize_i64(self, v: i64) -> KeyResult<()> {
+        self.serialize_u64(v as u64 ^ (1 << 63))
+    }
+
+    fn serialize_i128(self, v: i128) -> KeyResult<()> {
+        self.serialize_u128(v as u128 ^ (1 << 127))
+    }
+
+    fn serialize_u8(self, v: u8) -> KeyResult<()> {
+        self.out.push(v);
+        Ok(())
+    }
+
+    fn serialize_u16(self, v: u16) -> KeyResult<()> {
+        self.out.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u32(self, v: u32) -> KeyResult<()> {
+        self.out.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u64(self, v: u64) -> KeyResult<()> {
+        self.out.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u128(self, v: u128) -> KeyResult<()> {
+        self.out.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> KeyResult<()> {
+        let bits = canonical_f32(v).to_bits();
+
+        self.serialize_u32(if bits >> 31 == 1 {
+            !bits
+        } else {
+            bits | (1 << 31)
+        })
+    }
+
+    fn serialize_f64(self, v: f64) -> KeyResult<()> {
+        let bits = canonical_f64(v).to_bits();
+
+        self.serialize_u64(if bits >> 63 == 1 {
+            !bits
+        } else {
+            bits | (1 << 63)
+        })
+    }
+
+    fn serialize_char(self, v: char) -> KeyResult<()> {
+        self.serialize_u32(v as u32)
+    }
+
+    fn serialize_str(self, v: &str) -> KeyResult<()> {
+        self.write_bytes(v.as_bytes());
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> KeyResult<()> {
+        if replace(&mut self.raw, false) {
+            self.out.extend_from_slice(v);
+        } else {
+            self.write_bytes(v);
+        }
+
+        Ok(())
+    }
+
+    fn serialize_none(self) -> KeyResult<()> {
+        self.out.push(0);
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> KeyResult<()> {
+        self.root = false;
+        self.out.push(1);
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> KeyResult<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> KeyResult<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> KeyResult<()> {
+        self.serialize_u32(variant_index)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> KeyResult<()> {
+        if name == ENCODED_KEY && self.root {
+            let start = self.out.len();
+            self.raw = true;
+            value.serialize(&mut *self)?;
+
+            if self.out.len() == start {
+                return Err(KeyError("An encoded key cannot be empty.".to_string()));
+            }
+
+            Ok(())
+        } else {
+            value.serialize(self)
+        }
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> KeyResult<()> {
+        self.root = false;
+        self.out.extend_from_slice(&variant_index.to_be_bytes());
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> KeyResult<Self> {
+        self.root = false;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> KeyResult<Self> {
+        self.root = false;
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> KeyResult<Self> {
+        self.root = false;
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> KeyResult<Self> {
+        self.root = false;
+        self.out.extend_from_slice(&variant_index.to_be_bytes());
+        Ok(self)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> KeyResult<Self> {
+        self.root = false;
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> KeyResult<Self> {
+        self.root = false;
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> KeyResult<Self> {
+        self.root = false;
+        self.out.extend_from_slice(&variant_index.to_be_bytes());
+        Ok(self)
+    }
+}
+
+impl<'a> ser::SerializeSeq for &'a mut KeySerializer {
+    type Ok = ();
+    type Error = KeyError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> KeyResult<()> {
+        self.out.push(1);
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> KeyResult<()> {
+        self.out.push(0);
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeTuple for &'a mut KeySerializer {
+    type Ok = ();
+    type Error = KeyError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> KeyResult<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> KeyResult<()> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for &'a mut KeySerializer {
+    type Ok = ();
+    type Error = KeyError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> KeyResult<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> KeyResult<()> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeTupleVariant for &'a mut KeySerializer {
+    type Ok = ();
+    type Error = KeyError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> KeyResult<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> KeyResult<()> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeMap for &'a mut KeySerializer {
+    type Ok = ();
+    type Error = KeyError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> KeyResult<()> {
+        self.out.push(1);
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> KeyResult<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> KeyResult<()> {
+        self.out.push(0);
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeStruct for &'a mut KeySerializer {
+    type Ok = ();
+    type Error = KeyError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> KeyResult<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> KeyResult<()> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeStructVariant for &'a mut KeySerializer {
+    type Ok = ();
+    type Error = KeyError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> KeyResult<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> KeyResult<()> {
+        Ok(())
+    }
+}
+
+struct KeyDeserializer<'de> {
+    input: &'de [u8],
+    /// True until a compound value is started, while a newtype is the whole key.
+    root: bool,
+}
+
+macro_rules! read_be {
+    ($de:expr, $ty:ty) => {{
+        let bytes = $de.take(size_of::<$ty>())?;
+        <$ty>::from_be_bytes(bytes.try_into().unwrap())
+    }};
+}
+
+impl<'de> KeyDeserializer<'de> {
+    fn take(&mut self, len: usize) -> KeyResult<&'de [u8]> {
+        if self.input.len() < len {
+            return Err(KeyError("Unexpected end of key.".to_string()));
+        }
+
+        let (bytes, rest) = self.input.split_at(len);
+        self.input = rest;
+        Ok(bytes)
+    }
+
+    fn read_marker(&mut self) -> KeyResult<bool> {
+        match self.take(1)?[0] {
+            0 => Ok(false),
+            1 => Ok(true),
+            b => Err(KeyError(format!("Invalid marker in key: {}.", b))),
+        }
+    }
+
+    fn read_bytes(&mut self) -> KeyResult<Vec<u8>> {
+        let mut out = Vec::new();
+
+        loop {
+            match self.take(1)?[0] {
+                0 => match self.take(1)?[0] {
+                    0xFF => out.push(0),
+                    1 => return Ok(out),
+                    b => return Err(KeyError(format!("Invalid escape in key: {}.", b))),
+                },
+                b => out.push(b),
+            }
+        }
+    }
+
+    fn read_string(&mut self) -> KeyResult<String> {
+        String::from_utf8(self.read_bytes()?).map_err(|e| KeyError(e.to_string()))
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for &'a mut KeyDeserializer<'de> {
+    type Error = KeyError;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> KeyResult<V::Value> {
+        Err(KeyError(
+            "The key encoding does not support deserialize_any.".to_string(),
+        ))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> KeyResult<V::Value> {
+        visitor.visit_bool(self.read_marker()?)
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> KeyResult<V::Value> {
+        visitor.visit_i8((read_be!(self, u8) ^ 0x80) as i8)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> KeyResult<V::Value> {
+        visitor.visit_i16((read_be!(self, u16) ^ (1 << 15)) as i16)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> KeyResult<V::Value> {
+        visitor.visit_i32((read_be!(self, u32) ^ (1 << 31)) as i32)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> KeyResult<V::Value> {
+        visitor.visit_i64((read_be!(self, u64) ^ (1 << 63)) as i64)
+    }
+
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> KeyResult<V::Value> {
+        visitor.visit_i128((read_be!(self, u128) ^ (1 << 127)) as i128)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> KeyResult<V::Value> {
+        visitor.visit_u8(read_be!(self, u8))
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> KeyResult<V::Value> {
+        visitor.visit_u16(read_be!(self, u16))
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> KeyResult<V::Value> {
+        visitor.visit_u32(read_be!(self, u32))
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> KeyResult<V::Value> {
+        visitor.visit_u64(read_be!(self, u64))
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> KeyResult<V::Value> {
+        visitor.visit_u128(read_be!(self, u128))
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> KeyResult<V::Value> {
+        let bits = read_be!(self, u32);
+
+        visitor.visit_f32(f32::from_bits(if bits >> 31 == 1 {
+            bits ^ (1 << 31)
+        } else {
+            !bits
+        }))
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> KeyResult<V::Value> {
+        let bits = read_be!(self, u64);
+
+        visitor.visit_f64(f64::from_bits(if bits >> 63 == 1 {
+            bits ^ (1 << 63)
+        } else {
+            !bits
+        }))
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> KeyResult<V::Value> {
+        let v = read_be!(self, u32);
+
+        match std::char::from_u32(v) {
+            Some(c) => visitor.visit_char(c),
+            None => Err(KeyError(format!("Invalid char in key: {}.", v))),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> KeyResult<V::Value> {
+        visitor.visit_string(self.read_string()?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> KeyResult<V::Value> {
+        visitor.visit_string(self.read_string()?)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> KeyResult<V::Value> {
+        visitor.visit_byte_buf(self.read_bytes()?)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> KeyResult<V::Value> {
+        visitor.visit_byte_buf(self.read_bytes()?)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> KeyResult<V::Value> {
+        self.root = false;
+
+        if self.read_marker()? {
+            visitor.visit_some(self)
+        } else {
+            visitor.visit_none()
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> KeyResult<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> KeyResult<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> KeyResult<V::Value> {
+        if name != ENCODED_KEY {
+            visitor.visit_newtype_struct(self)
+        } else if replace(&mut self.root, false) {
+            let bytes = self.take(self.input.len())?;
+            visitor.visit_borrowed_bytes(bytes)
+        } else {
+            visitor.visit_byte_buf(self.read_bytes()?)
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> KeyResult<V::Value> {
+        self.root = false;
+        visitor.visit_seq(Marked { de: self })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> KeyResult<V::Value> {
+        self.root = false;
+        visitor.visit_seq(Fixed { de: self, len })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> KeyResult<V::Value> {
+        self.root = false;
+        visitor.visit_seq(Fixed { de: self, len })
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> KeyResult<V::Value> {
+        self.root = false;
+        visitor.visit_map(Marked { de: self })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> KeyResult<V::Value> {
+        self.root = false;
+        visitor.visit_seq(Fixed {
+            de: self,
+            len: fields.len(),
+        })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> KeyResult<V::Value> {
+        self.root = false;
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> KeyResult<V::Value> {
+        self.deserialize_u32(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _visitor: V) -> KeyResult<V::Value> {
+        Err(KeyError(
+            "The key encoding does not support deserialize_ignored_any.".to_string(),
+        ))
+    }
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for &'a mut KeyDeserializer<'de> {
+    type Error = KeyError;
+    type Variant = Self;
+
+    fn variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> KeyResult<(T::Value, Self)> {
+        let index = read_be!(self, u32);
+        let value = seed.deserialize(IntoDeserializer::<KeyError>::into_deserializer(index))?;
+        Ok((value, self))
+    }
+}
+
+impl<'de, 'a> de::VariantAccess<'de> for &'a mut KeyDeserializer<'de> {
+    type Error = KeyError;
+
+    fn unit_variant(self) -> KeyResult<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> KeyResult<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> KeyResult<V::Value> {
+        visitor.visit_seq(Fixed { de: self, len })
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> KeyResult<V::Value> {
+        visitor.visit_seq(Fixed {
+            de: self,
+            len: fields.len(),
+        })
+    }
+}
+
+/// The elements of a tuple or a struct, whose count is known.
+struct Fixed<'a, 'de> {
+    de: &'a mut KeyDeserializer<'de>,
+    len: usize,
+}
+
+impl<'de, 'a> de::SeqAccess<'de> for Fixed<'a, 'de> {
+    type Error = KeyError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> KeyResult<Option<T::Value>> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+
+        self.len -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+/// The elements of a sequence or a map, each one preceded by a marker.
+struct Marked<'a, 'de> {
+    de: &'a mut KeyDeserializer<'de>,
+}
+
+impl<'de, 'a> de::SeqAccess<'de> for Marked<'a, 'de> {
+    type Error = KeyError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> KeyResult<Option<T::Value>> {
+        if self.de.read_marker()? {
+            seed.deserialize(&mut *self.de).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<'de, 'a> de::MapAccess<'de> for Marked<'a, 'de> {
+    type Error = KeyError;
+
+    fn next_key_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> KeyResult<Option<T::Value>> {
+        if self.de.read_marker()? {
+            seed.deserialize(&mut *self.de).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn next_value_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> KeyResult<T::Value> {
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{key_from_bytes, key_serialized_len, key_to_bytes};
+    use crate::Encoded;
+    use serde::{Deserialize, Serialize};
+    use std::fmt::Debug;
+
+    fn encode<T: Serialize>(key: &T) -> Vec<u8> {
+        key_to_bytes(key).unwrap()
+    }
+
+    fn assert_roundtrip<T>(keys: &[T])
+    where
+        T: Debug + for<'de> Deserialize<'de> + PartialEq + Serialize,
+    {
+        for key in keys {
+            assert_eq!(&key_from_bytes::<T>(&encode(key)).unwrap(), key);
+        }
+    }
+
+    fn assert_ordered<T: Debug + Serialize>(keys: &[T]) {
+        for pair in keys.windows(2) {
+            assert!(encode(&pair[0]) < encode(&pair[1]), "{:?}", pair);
+        }
+    }
+
+    #[test]
+    fn signed_integers() {
+        let keys = [i32::MIN, -256, -1, 0, 1, 256, i32::MAX];
+        assert_roundtrip(&keys);
+        assert_ordered(&keys);
+
+        let keys = [i64::MIN, -1, 0, i64::MAX];
+        assert_roundtrip(&keys);
+        assert_ordered(&keys);
+
+        let keys = [i8::MIN, -1, 0, i8::MAX];
+        assert_roundtrip(&keys);
+        assert_ordered(&keys);
+    }
+
+    #[test]
+    fn floats() {
+        let keys = [
+            f64::NEG_INFINITY,
+            -1.5,
+            -f64::MIN_POSITIVE,
+            0.0,
+            f64::MIN_POSITIVE,
+            1.5,
+            f64::INFINITY,
+        ];
+
+        assert_roundtrip(&keys);
+        assert_ordered(&keys);
+
+        assert_eq!(encode(&-0.0f64), encode(&0.0f64));
+        assert_eq!(
+            key_from_bytes::<f64>(&encode(&-0.0f64)).unwrap().to_bits(),
+            0
+        );
+
+        assert_eq!(encode(&f64::NAN), encode(&-f64::NAN));
+        assert!(encode(&f64::INFINITY) < encode(&f64::NAN));
+        assert!(key_from_bytes::<f64>(&encode(&f64::NAN)).unwrap().is_nan());
+
+        assert_eq!(encode(&-0.0f32), encode(&0.0f32));
+        assert!(encode(&f32::INFINITY) < encode(&f32::NAN));
+        assert_ordered(&[f32::NEG_INFINITY, -1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn strings_with_zeros() {
+        let keys = ["", "\0", "a", "a\0", "a\0b", "a\u{1}", "ab", "b"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+
+        assert_roundtrip(&keys);
+        assert_ordered(&keys);
+    }
+
+    #[test]
+    fn nested_tuples() {
+        let keys = [
+            (1u32, ("a".to_string(), -1i64)),
+            (1, ("a".to_string(), 0)),
+            (1, ("a\0".to_string(), i64::MIN)),
+            (1, ("b".to_string(), i64::MIN)),
+            (2, (String::new(), 0)),
+        ];
+
+        assert_roundtrip(&keys);
+        assert_ordered(&keys);
+    }
+
+    #[test]
+    fn options() {
+        let keys = [None, Some(-1i32), Some(0), Some(1)];
+        assert_roundtrip(&keys);
+        assert_ordered(&keys);
+
+        let keys = [(None, 9u8), (Some(0u8), 0), (Some(0), 1)];
+        assert_roundtrip(&keys);
+        assert_ordered(&keys);
+    }
+
+    #[test]
+    fn sequences() {
+        let keys = [vec![], vec![0u32], vec![0, 0], vec![0, 1], vec![1]];
+        assert_roundtrip(&keys);
+        assert_ordered(&keys);
+
+        let keys = [
+            (vec!["a".to_string()], 1u8),
+            (vec!["a".to_string(), String::new()], 0),
+        ];
+        assert_roundtrip(&keys);
+        assert_ordered(&keys);
+    }
+
+    #[test]
+    fn encoded_at_root() {
+        let key = Encoded(vec![1u8, 0, 2]);
+
+        assert_eq!(encode(&key), vec![1, 0, 2]);
+        assert_roundtrip(&[key]);
+        assert_ordered(&[Encoded(vec![0u8]), Encoded(vec![0, 0]), Encoded(vec![1])]);
+    }
+
+    #[test]
+    fn encoded_nested() {
+        let key = (7u8, Encoded(vec![1u8, 0, 2]), 9u8);
+
+        assert_eq!(encode(&key), vec![7, 1, 0, 255, 2, 0, 1, 9]);
+        assert_roundtrip(&[key]);
+
+        let keys = [
+            (Encoded(vec![0u8]), 9u8),
+            (Encoded(vec![0, 0]), 0),
+            (Encoded(vec![1]), 0),
+        ];
+
+        assert_roundtrip(&keys);
+        assert_ordered(&keys);
+    }
+
+    #[test]
+    fn serialized_len() {
+        let bytes = encode(&(1u32, "ab".to_string(), 5u8));
+
+        assert_eq!(key_serialized_len::<u32>(&bytes), Some(4));
+        assert_eq!(key_serialized_len::<(u32, String)>(&bytes), Some(8));
+        assert_eq!(
+            key_serialized_len::<(u32, String, u8)>(&bytes),
+            Some(bytes.len())
+        );
+        assert_eq!(key_serialized_len::<u32>(&[1, 2]), None);
+        assert_eq!(key_serialized_len::<(u32, String)>(&bytes[..6]), None);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::{
+    deserialize_from_bytes, key_ser::key_to_bytes, serialize_to_bytes, Db, RawBatch, Result,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::fmt::Debug;
+
+/// A table storing the values split into chunks of a fixed size, since RocksDB performs poorly
+/// with values of several megabytes.
+///
+/// The chunks of a key are stored under the keys `(key, 0)`, `(key, 1)`, ... and are all
+/// written or deleted in a single batch, so a value is never read partially written.
+pub struct LargeValueTable<K> {
+    chunk_size: usize,
+    db: Db<(K, u32)>,
+}
+
+impl<K> LargeValueTable<K>
+where
+    K: Debug + for<'de> Deserialize<'de> + Serialize,
+{
+    /// The default size of the chunks, 256 KiB.
+    pub const DEFAULT_CHUNK_SIZE: usize = 256 * 1024;
+
+    pub fn new(db: Db<(K, u32)>) -> Self {
+        Self::with_chunk_size(db, Self::DEFAULT_CHUNK_SIZE)
+    }
+
+    pub fn with_chunk_size(db: Db<(K, u32)>, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0);
+        Self { chunk_size, db }
+    }
+
+    pub fn contains_key(&self, key: &K) -> Result<bool> {
+        Ok(self.db.iter_prefix(key)?.next()?.is_some())
+    }
+
+    pub fn delete(&self, key: &K) -> Result<()> {
+        let mut batch = RawBatch::new();
+        self.batch_delete_chunks(&mut batch, key)?;
+        self.db.write_raw(batch)
+    }
+
+    /// Adds the removal of all the chunks of the key to the batch.
+    fn batch_delete_chunks(&self, batch: &mut RawBatch, key: &K) -> Result<()> {
+        let from = key_to_bytes(&(key, 0u32))?;
+        let to = key_to_bytes(&(key, u32::MAX))?;
+        self.db.batch_delete_range_raw(batch, &from, &to)
+    }
+
+    /// Reads and reassembles the chunks of the value.
+    pub fn get<V: DeserializeOwned>(&self, key: &K) -> Result<Option<V>> {
+        let mut bytes = Vec::new();
+        let mut found = false;
+        let mut iter = self.db.iter_prefix(key)?;
+
+        while let Some(item) = iter.next()? {
+            bytes.extend_from_slice(item.value_bytes()?);
+            found = true;
+        }
+
+        if found {
+            Ok(Some(deserialize_from_bytes(&bytes)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Serializes the value, then replaces the chunks of the key with its chunks.
+    pub fn put<V: Serialize>(&self, key: &K, value: &V) -> Result<()> {
+        let bytes = serialize_to_bytes(value)?;
+        let mut batch = RawBatch::new();
+
+        self.batch_delete_chunks(&mut batch, key)?;
+
+        // an empty value still has a chunk, so the key exists.
+        let mut chunks = bytes.chunks(self.chunk_size).collect::<Vec<_>>();
+
+        if chunks.is_empty() {
+            chunks.push(&[]);
+        }
+
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            let chunk_key = key_to_bytes(&(key, index as u32))?;
+            self.db.batch_put_raw(&mut batch, &chunk_key, chunk)?;
+        }
+
+        self.db.write_raw(batch)
+    }
+}
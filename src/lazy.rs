@@ -0,0 +1,138 @@
+use crate::{deserialize_from_bytes, serialize_to_bytes, Result};
+use once_cell::sync::OnceCell;
+use serde::{
+    de::{self, DeserializeOwned, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::fmt;
+
+/// A value kept serialized until it is first read, then decoded once.
+///
+/// Used as the value of a cached table, such as a `MemTable<K, Lazy<V>>`, the values are
+/// not decoded when the table is loaded, so the tables mostly written skip their decoding.
+/// The value is stored as a byte string holding its serialized form, like [`Binary`](crate::Binary),
+/// so a table cannot switch between `V` and `Lazy<V>` without rewriting its values.
+pub struct Lazy<V> {
+    bytes: Vec<u8>,
+    value: OnceCell<V>,
+}
+
+impl<V> Lazy<V> {
+    pub fn new(value: V) -> Result<Self>
+    where
+        V: Serialize,
+    {
+        Ok(Self {
+            bytes: serialize_to_bytes(&value)?,
+            value: OnceCell::from(value),
+        })
+    }
+
+    /// Returns the serialized value.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the value, decoding it on the first call.
+    pub fn get(&self) -> Result<&V>
+    where
+        V: DeserializeOwned,
+    {
+        self.value
+            .get_or_try_init(|| deserialize_from_bytes(&self.bytes))
+    }
+
+    pub fn into_inner(self) -> Result<V>
+    where
+        V: DeserializeOwned,
+    {
+        match self.value.into_inner() {
+            Some(value) => Ok(value),
+            None => deserialize_from_bytes(&self.bytes),
+        }
+    }
+
+    /// Returns true if the value has already been decoded.
+    #[inline]
+    pub fn is_decoded(&self) -> bool {
+        self.value.get().is_some()
+    }
+}
+
+impl<V> Clone for Lazy<V>
+where
+    V: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            bytes: self.bytes.clone(),
+            value: self.value.clone(),
+        }
+    }
+}
+
+impl<V> fmt::Debug for Lazy<V>
+where
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.value.get() {
+            Some(value) => f.debug_tuple("Lazy").field(value).finish(),
+            None => write!(f, "Lazy(<{} bytes>)", self.bytes.len()),
+        }
+    }
+}
+
+impl<'de, V> Deserialize<'de> for Lazy<V> {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(Self {
+            bytes: deserializer.deserialize_bytes(BytesVisitor)?,
+            value: OnceCell::new(),
+        })
+    }
+}
+
+impl<V> Serialize for Lazy<V> {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(&self.bytes)
+    }
+}
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a serialized value")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> std::result::Result<Self::Value, E> {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> std::result::Result<Self::Value, E> {
+        Ok(v)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> std::result::Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+
+        while let Some(b) = seq.next_element()? {
+            bytes.push(b);
+        }
+
+        Ok(bytes)
+    }
+}
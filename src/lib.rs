@@ -1,28 +1,147 @@
+mod adaptive_capacity;
 mod aged;
+#[cfg(feature = "tokio")]
+mod async_tables;
+mod audit_log;
 mod binary;
 mod binary_ser;
+#[cfg(feature = "blake3")]
+mod blob_store;
+mod bounded_section_lru_table;
+mod buf;
+mod commit_group;
+mod compaction_filter;
+mod comparator;
+mod compress;
+mod compressed_table;
+mod concurrent_lru_table;
+mod counter_table;
 mod db;
+mod db_builder;
+mod derived_table;
 mod encrypt;
+mod encrypted_key_db;
+mod encrypted_lru_table;
 mod error;
+mod eviction;
+mod export;
+mod hooks;
+mod hot_keys;
+mod in_flight;
+mod ingest_buffer;
+mod iter_options;
+#[cfg(feature = "kdf")]
+mod key_derivation;
+mod key_encode;
+mod key_ser;
+mod large_value_table;
+mod lazy;
 mod lru_table;
 mod mem_table;
+mod memory_budget;
+mod merge;
+mod migrator;
 mod min_value;
+mod multi_table;
+mod namespace;
+mod ordered_mem_table;
+mod ordered_section_lru_table;
+mod perf;
+mod projection;
+mod queue_table;
+mod raw_table;
+mod recovery;
 mod result;
+mod retry_policy;
+mod schema;
 mod section_lru_table;
+mod shared_cache;
+mod size_histogram;
+mod sst_writer;
+mod stats;
+mod table_stats;
+mod tail;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "chrono")]
+mod time_series_table;
+mod trace_config;
+mod transaction;
 mod update_from;
 
 use aged::Aged;
-pub use binary::{Binary, Crypted};
-use binary_ser::{deserialize_from_bytes, serialize_to_bytes};
-pub use db::{Db, DbKeyValue, DbValue, Direction, Iter, IteratorMode};
+#[cfg(feature = "tokio")]
+pub use async_tables::{AsyncDb, AsyncLruTable, AsyncMemTable};
+pub use audit_log::{AuditEntry, AuditKey, AuditLog};
+pub use binary::{Binary, Compressed, Crypted};
+use binary_ser::{deserialize_from_bytes, serialize_into, serialize_to_bytes};
+#[cfg(feature = "blake3")]
+pub use blob_store::{BlobHash, BlobStore};
+pub use bounded_section_lru_table::BoundedSectionLruTable;
+pub use commit_group::{CommitGroup, JournalTarget};
+pub use compaction_filter::FilterDecision;
+pub use comparator::{CaseInsensitive, KeyComparator};
+pub use compress::Compress;
+#[cfg(feature = "lz4_flex")]
+pub use compress::Lz4;
+#[cfg(feature = "zstd")]
+pub use compress::Zstd;
+pub use compressed_table::CompressedTable;
+pub use concurrent_lru_table::ConcurrentLruTable;
+pub use counter_table::{Counter, CounterTable, Incr};
+use db::RawBatch;
+pub use db::{
+    Cursor, Db, DbHandle, DbKeyValue, DbValue, DecodedIter, Direction, Iter, IteratorMode,
+    MaybeTombstone, WriteBatch, WriteMode,
+};
+pub use db_builder::DbBuilder;
+pub use derived_table::DerivedTable;
 pub use encrypt::Encrypt;
-pub use error::Error;
-pub use lru_table::LruTable;
-pub use mem_table::MemTable;
+pub use encrypted_key_db::{EncryptedKeyDb, KeyCodec};
+pub use encrypted_lru_table::EncryptedLruTable;
+pub use error::{Error, ErrorContext};
+pub use eviction::{EvictionPolicy, Fifo, Lfu, Lru, WTinyLfu};
+pub use hooks::{ChangeEvent, ChangeKind};
+pub use ingest_buffer::IngestBuffer;
+pub use iter_options::IterOptions;
+#[cfg(feature = "kdf")]
+pub use key_derivation::KeyDerivation;
+pub use key_encode::{Encoded, KeyEncode};
+pub use large_value_table::LargeValueTable;
+pub use lazy::Lazy;
+pub use lru_table::{LruIter, LruTable, LruTableBatch};
+pub use mem_table::{LazyMemTable, MemTable, MemTableBatch};
+pub use memory_budget::MemoryBudget;
+pub use merge::{MergeSerde, MergeTable};
+pub use migrator::Migrator;
 pub use min_value::MinValue;
+pub use multi_table::MultiTable;
+pub use namespace::{NamespaceIter, NamespacedDb};
+pub use ordered_mem_table::OrderedMemTable;
+pub use ordered_section_lru_table::OrderedSectionLruTable;
+pub use perf::{PerfCounters, PerfGuard};
+pub use projection::Projection;
+pub use queue_table::QueueTable;
+pub use raw_table::RawTable;
+pub use recovery::{ChecksumReport, CorruptedRange, RecoveryMode};
 pub use result::Result;
-pub use section_lru_table::SectionLruTable;
+pub use retry_policy::RetryPolicy;
+#[cfg(feature = "derive")]
+pub use rocks_tables_derive::MinValue;
+pub use rocksdb::{DBCompactionStyle, DBCompressionType};
+pub use section_lru_table::{SectionIter, SectionLruTable, SectionLruTableBatch, Sections};
+pub use shared_cache::SharedCache;
+pub use size_histogram::SizeHistogram;
+pub use sst_writer::{SstOptions, SstWriter};
+pub use table_stats::TableStats;
+pub use tail::{Operation, Tail};
+#[cfg(feature = "chrono")]
+pub use time_series_table::TimeSeriesTable;
+pub use trace_config::{StatementCapture, TraceConfig};
+pub use transaction::{Transaction, TransactionPart};
 pub use update_from::UpdateFrom;
 
 #[cfg(feature = "aes-gcm")]
 pub use aes_gcm;
+#[cfg(feature = "chacha20poly1305")]
+pub use chacha20poly1305;
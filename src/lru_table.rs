@@ -1,17 +1,56 @@
-use crate::{Aged, Db, Result, UpdateFrom};
+use crate::{
+    adaptive_capacity::AdaptiveCapacity,
+    aged::AgeIndex,
+    comparator::CompareFn,
+    db::log_err,
+    eviction::EvictionPolicy,
+    hot_keys::{HotKeys, HOT_KEYS},
+    key_ser::key_to_bytes,
+    memory_budget::BudgetMember,
+    stats,
+    table_stats::CacheCounters,
+    transaction::PartOps,
+    Aged, Db, Iter, IterOptions, IteratorMode, MemoryBudget, RawBatch, Result, TableStats,
+    Transaction, UpdateFrom,
+};
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::hash_map::{HashMap, RandomState},
+    borrow::Borrow,
+    cmp::Ordering,
+    collections::{
+        hash_map::{HashMap, RandomState},
+        HashSet, VecDeque,
+    },
     fmt::Debug,
     hash::{BuildHasher, Hash},
+    mem::size_of,
+    sync::{Arc, Mutex, MutexGuard},
+    thread::{self, JoinHandle},
 };
+use tracing::{field, trace_span};
 
 /// A table that keep in memory only a small percent of the real table.
 /// Last recent used items are discard from memory when the capacity is reached.
+/// The capacity is either a number of entries or, in byte-budget mode, an estimated number of bytes,
+/// possibly shared with other tables through a [`MemoryBudget`].
+///
+/// In write-back mode, the writes are only kept in memory and written to the database
+/// when the entry is evicted, when [`LruTable::flush`] is called or when the table is dropped.
 pub struct LruTable<K, V, S = RandomState> {
-    age: u64,
+    adaptive: Option<AdaptiveCapacity>,
+    ages: AgeIndex<K>,
+    budget: Option<ByteBudget<V>>,
+    /// The maximum number of entries, when the table is not bounded by a byte budget.
+    capacity: usize,
+    counters: CacheCounters,
     db: Db<K>,
+    dirty: HashSet<K>,
+    /// Flushes the dirty entries on drop, captured by the constructors where the bounds are known.
+    drop_flush: fn(&mut Self) -> Result<()>,
+    hot_keys: Option<HotKeys<K>>,
     map: HashMap<K, Aged<V>, S>,
+    policy: Option<Box<dyn EvictionPolicy<K>>>,
+    write_back: bool,
 }
 
 impl<K, V> LruTable<K, V, RandomState>
@@ -23,6 +62,27 @@ where
     pub fn with_capacity(db: Db<K>, capacity: usize) -> Self {
         Self::with_capacity_and_hasher(db, capacity, Default::default())
     }
+
+    /// Creates a table bounded by the estimated size of its values rather than by their count.
+    ///
+    /// `size_of` returns the estimated number of bytes used by a value.
+    pub fn with_byte_budget<F>(db: Db<K>, max_bytes: usize, size_of: F) -> Self
+    where
+        F: Fn(&V) -> usize + Send + Sync + 'static,
+    {
+        Self::with_byte_budget_and_hasher(db, max_bytes, size_of, Default::default())
+    }
+
+    /// Creates a table sharing a byte budget with other tables, evicting the least recently
+    /// used entries of all the tables.
+    ///
+    /// `size_of` returns the estimated number of bytes used by a value.
+    pub fn with_memory_budget<F>(db: Db<K>, budget: &MemoryBudget, size_of: F) -> Self
+    where
+        F: Fn(&V) -> usize + Send + Sync + 'static,
+    {
+        Self::with_memory_budget_and_hasher(db, budget, size_of, Default::default())
+    }
 }
 
 impl<K, V, S> LruTable<K, V, S>
@@ -36,136 +96,1359 @@ where
         assert!(capacity > 0);
 
         Self {
-            age: 0,
+            adaptive: None,
+            ages: AgeIndex::new(),
+            budget: None,
+            capacity,
+            counters: CacheCounters::default(),
             db,
+            dirty: HashSet::new(),
+            drop_flush: Self::flush,
+            hot_keys: None,
             map: HashMap::with_capacity_and_hasher(capacity, hash_builder),
+            policy: None,
+            write_back: false,
+        }
+    }
+
+    /// Creates a table bounded by the estimated size of its values, with a hasher.
+    pub fn with_byte_budget_and_hasher<F>(
+        db: Db<K>,
+        max_bytes: usize,
+        size_of: F,
+        hash_builder: S,
+    ) -> Self
+    where
+        F: Fn(&V) -> usize + Send + Sync + 'static,
+    {
+        Self {
+            adaptive: None,
+            ages: AgeIndex::new(),
+            budget: Some(ByteBudget {
+                max: max_bytes,
+                member: None,
+                size_of: Box::new(size_of),
+                used: 0,
+            }),
+            capacity: 0,
+            counters: CacheCounters::default(),
+            db,
+            dirty: HashSet::new(),
+            drop_flush: Self::flush,
+            hot_keys: None,
+            map: HashMap::with_hasher(hash_builder),
+            policy: None,
+            write_back: false,
+        }
+    }
+
+    /// Creates a table sharing a byte budget with other tables, with a hasher.
+    pub fn with_memory_budget_and_hasher<F>(
+        db: Db<K>,
+        budget: &MemoryBudget,
+        size_of: F,
+        hash_builder: S,
+    ) -> Self
+    where
+        F: Fn(&V) -> usize + Send + Sync + 'static,
+    {
+        let member = budget.register();
+
+        Self {
+            adaptive: None,
+            ages: AgeIndex::with_clock(member.clock()),
+            budget: Some(ByteBudget {
+                max: usize::MAX,
+                member: Some(member),
+                size_of: Box::new(size_of),
+                used: 0,
+            }),
+            capacity: 0,
+            counters: CacheCounters::default(),
+            db,
+            dirty: HashSet::new(),
+            drop_flush: Self::flush,
+            hot_keys: None,
+            map: HashMap::with_hasher(hash_builder),
+            policy: None,
+            write_back: false,
+        }
+    }
+
+    /// Replaces the least recently used eviction by `policy`, such as an [`Lfu`](crate::Lfu)
+    /// or a [`WTinyLfu`](crate::WTinyLfu) for the workloads mixing scans and hot keys.
+    ///
+    /// Must be called before the table is used. With a [`MemoryBudget`], the tables still
+    /// compete by the recency of their entries.
+    pub fn with_eviction_policy<P>(mut self, policy: P) -> Self
+    where
+        P: EvictionPolicy<K> + 'static,
+    {
+        self.policy = Some(Box::new(policy));
+        self
+    }
+
+    /// Tunes the capacity of the table within `min..=max` from its hit rate, measured over
+    /// windows of 1000 lookups: the capacity grows by a quarter when less than 90% of the
+    /// lookups hit while entries are evicted, and shrinks by a quarter from 99% of hits.
+    ///
+    /// The bounds are in the unit of [`LruTable::capacity`].
+    pub fn with_adaptive_capacity(mut self, min: usize, max: usize) -> Self {
+        self.adaptive = Some(AdaptiveCapacity::new(min, max));
+        self
+    }
+
+    /// Applies the capacity computed by the adaptive mode at the end of a window.
+    fn adapt(&mut self) -> Result<()>
+    where
+        K: Clone,
+    {
+        let capacity = self.capacity();
+
+        match self
+            .adaptive
+            .as_mut()
+            .and_then(|a| a.next_capacity(capacity))
+        {
+            Some(capacity) => self.resize(capacity),
+            None => Ok(()),
+        }
+    }
+
+    /// Creates a batch of writes applied atomically to the database and to the table on commit.
+    pub fn batch(&mut self) -> LruTableBatch<K, V, S> {
+        LruTableBatch {
+            ops: Vec::new(),
+            table: self,
+        }
+    }
+
+    fn cache_insert(&mut self, key: &K, value: V) -> Result<()>
+    where
+        K: Clone,
+    {
+        match self.map.get_mut(key) {
+            Some(aged) => {
+                self.ages.touch(key, &mut aged.age);
+
+                if let Some(policy) = &mut self.policy {
+                    policy.access(key);
+                }
+
+                if let Some(budget) = &mut self.budget {
+                    budget.replace(&aged.value, &value);
+                }
+
+                aged.value = value;
+                self.sync_budget();
+            }
+            None => {
+                self.ensure_capacity(self.value_size(&value))?;
+                self.cache_put(key.clone(), Aged { age: 0, value });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Inserts a new entry in the cache, as the most recently used.
+    fn cache_put(&mut self, key: K, mut aged: Aged<V>)
+    where
+        K: Clone,
+    {
+        if let Some(budget) = &mut self.budget {
+            budget.add((budget.size_of)(&aged.value));
+        }
+
+        self.ages.touch(&key, &mut aged.age);
+
+        if let Some(policy) = &mut self.policy {
+            policy.insert(&key);
+        }
+
+        self.map.insert(key, aged);
+        self.sync_budget();
+    }
+
+    /// Removes an entry from the cache.
+    fn cache_remove(&mut self, key: &K) -> Option<Aged<V>> {
+        let aged = self.map.remove(key)?;
+        self.ages.remove(aged.age);
+
+        if let Some(policy) = &mut self.policy {
+            policy.remove(key);
+        }
+
+        if let Some(budget) = &mut self.budget {
+            budget.sub((budget.size_of)(&aged.value));
+        }
+
+        self.sync_budget();
+        Some(aged)
+    }
+
+    /// Returns the maximum number of entries, or the maximum number of bytes in byte-budget mode.
+    pub fn capacity(&self) -> usize {
+        match &self.budget {
+            Some(budget) => budget.max,
+            None => self.capacity,
         }
     }
 
     /// Returns true if the table contains a value for the specified key.
-    pub fn contains_key(&self, key: &K) -> Result<bool> {
+    pub fn contains_key<Q: ?Sized>(&self, key: &Q) -> Result<bool>
+    where
+        K: Borrow<Q>,
+        Q: Debug + Eq + Hash + Serialize,
+    {
         Ok(if self.map.contains_key(key) {
             true
         } else {
-            self.db.contains_key(key)?
+            self.db.get_with(key)?.is_some()
         })
     }
 
     /// Removes a key from the table.
     pub fn delete(&mut self, key: &K) -> Result<()> {
         self.db.delete(key)?;
-        self.map.remove(key);
+        self.cache_remove(key);
+        self.dirty.remove(key);
         Ok(())
     }
 
-    fn ensure_capacity(&mut self)
+    /// Returns the number of entries modified in memory but not yet written to the database.
+    #[inline]
+    pub fn dirty_count(&self) -> usize {
+        self.dirty.len()
+    }
+
+    /// Evicts the least recently used entries to make room for a value of `incoming` bytes.
+    fn ensure_capacity(&mut self, incoming: usize) -> Result<()>
     where
         K: Clone,
     {
-        if self.map.capacity() == self.map.len() {
-            if let Some(key) = self.map.iter().min_by_key(|t| t.1.age).map(|t| t.0.clone()) {
-                self.map.remove(&key);
+        self.evict_pending()?;
+
+        while self.is_full(incoming) {
+            if self.evict_oldest()?.is_none() {
+                break;
             }
         }
+
+        Ok(())
     }
 
-    /// Returns a reference to the value corresponding to the key.
-    pub fn get(&mut self, key: &K) -> Result<Option<&V>>
+    /// Evicts the least recently used entry, returning its estimated size.
+    fn evict_oldest(&mut self) -> Result<Option<usize>>
     where
         K: Clone,
     {
-        if !self.map.contains_key(key) {
-            self.ensure_capacity();
+        let victim = match &mut self.policy {
+            Some(policy) => policy.victim(),
+            None => self.ages.oldest().cloned(),
+        };
 
-            match self.db.get(key)? {
-                Some(value) => {
-                    let value = value.to_inner()?;
-                    self.map.insert(key.clone(), Aged { age: 0, value });
-                }
-                None => return Ok(None),
-            }
+        let key = match victim {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+
+        if self.dirty.contains(&key) {
+            self.db.put(&key, &self.map[&key].value)?;
+            self.dirty.remove(&key);
         }
 
-        let age = &mut self.age;
+        Ok(self.cache_remove(&key).map(|aged| {
+            if let Some(adaptive) = &mut self.adaptive {
+                adaptive.eviction();
+            }
 
-        Ok(self.map.get_mut(key).map(|v| {
-            *age += 1;
-            v.age = *age;
-            &v.value
+            self.counters.eviction();
+            stats::eviction(self.db.name());
+            self.value_size(&aged.value)
         }))
     }
 
-    pub fn put(&mut self, key: &K, value: V) -> Result<()>
+    /// Evicts the bytes requested by the other tables of a shared [`MemoryBudget`].
+    fn evict_pending(&mut self) -> Result<()>
     where
         K: Clone,
     {
-        self.db.put(key, &value)?;
-        self.age += 1;
+        let mut pending = match self.budget.as_ref().and_then(|b| b.member.as_ref()) {
+            Some(member) => member.take_pending(),
+            None => return Ok(()),
+        };
 
-        let age = self.age;
+        while pending > 0 {
+            match self.evict_oldest()? {
+                Some(size) => pending = pending.saturating_sub(size.max(1)),
+                None => break,
+            }
+        }
 
-        match self.map.get_mut(key) {
-            Some(aged) => {
-                aged.age = age;
-                aged.value = value;
+        Ok(())
+    }
+
+    /// Evicts the least recently used entries until the values fit in the byte budget again,
+    /// once cached values grew.
+    fn evict_over_budget(&mut self) -> Result<()>
+    where
+        K: Clone,
+    {
+        while self.budget.as_ref().map_or(false, |b| b.is_full(0)) {
+            if self.evict_oldest()?.is_none() {
+                break;
             }
-            None => {
-                self.ensure_capacity();
-                self.map.insert(key.clone(), Aged { age, value });
+        }
+
+        self.sync_budget();
+        Ok(())
+    }
+
+    /// Writes all the dirty entries to the database in a single batch.
+    pub fn flush(&mut self) -> Result<()> {
+        if self.dirty.is_empty() {
+            return Ok(());
+        }
+
+        let mut batch = self.db.batch();
+
+        for key in &self.dirty {
+            if let Some(aged) = self.map.get(key) {
+                batch.put(key, &aged.value)?;
             }
         }
 
+        batch.commit()?;
+        self.dirty.clear();
         Ok(())
     }
 
-    pub fn update<U>(&mut self, key: &K, update: U) -> Result<()>
+    /// Returns a reference to the value corresponding to the key.
+    pub fn get<Q: ?Sized>(&mut self, key: &Q) -> Result<Option<&V>>
     where
-        K: Clone,
-        U: UpdateFrom<V>,
+        K: Borrow<Q> + Clone,
+        Q: Debug + Eq + Hash + Serialize + ToOwned<Owned = K>,
     {
-        let must_ensure_capacity;
+        let span = trace_span!(
+            "lru_get",
+            cache.hit = field::Empty,
+            db.name = self.db.name(),
+            evicted = field::Empty,
+        );
+        let _enter = span.enter();
+        let evictions = self.counters.evictions();
+
+        self.evict_pending()?;
+        self.adapt()?;
+
+        let hit = self.map.contains_key(key);
+        self.lookup(hit);
+        span.record("cache.hit", &hit);
+
+        if let Some(hot_keys) = &mut self.hot_keys {
+            hot_keys.record(key);
+        }
+
+        if !hit {
+            let value = match self.db.get_with(key)? {
+                Some(value) => value.to_inner()?,
+                None => {
+                    span.record("evicted", &(self.counters.evictions() - evictions));
+                    return Ok(None);
+                }
+            };
 
-        let mut new = match self.map.remove(key) {
-            Some(mut aged) => {
-                must_ensure_capacity = false;
-                aged.value = update.update_from(Some(aged.value));
-                aged
+            self.ensure_capacity(self.value_size(&value))?;
+            self.cache_put(key.to_owned(), Aged { age: 0, value });
+        }
+
+        if let Some(aged) = self.map.get_mut(key) {
+            self.ages.touch(key, &mut aged.age);
+        }
+
+        if hit {
+            if let (Some(policy), Some((key, _))) = (&mut self.policy, self.map.get_key_value(key))
+            {
+                policy.access(key);
             }
-            None => {
-                must_ensure_capacity = true;
+        }
 
-                let mut old = None;
+        self.sync_budget();
+        span.record("evicted", &(self.counters.evictions() - evictions));
+        Ok(self.map.get(key).map(|aged| &aged.value))
+    }
 
-                if let Some(item) = self.db.get(key)? {
-                    old = Some(item.to_inner()?);
+    /// Returns the value of the key, computing it with `loader` and storing it if it does not exist.
+    ///
+    /// See [`ConcurrentLruTable::get_or_load`](crate::ConcurrentLruTable::get_or_load) to share
+    /// a single load between threads.
+    pub fn get_or_load<F>(&mut self, key: &K, loader: F) -> Result<&V>
+    where
+        F: FnOnce() -> Result<V>,
+        K: Clone,
+    {
+        if self.get(key)?.is_none() {
+            let value = loader()?;
+            self.put(key, value)?;
+        }
+
+        Ok(&self.map.get(key).unwrap().value)
+    }
+
+    /// Returns the values corresponding to the keys, in the same order as `keys`.
+    ///
+    /// All the keys missing from the cache are read from the database in a single call, then
+    /// cached up to the capacity of the table; the values beyond are returned without being cached.
+    ///
+    /// In byte-budget mode, the values of the keys are all kept in memory, even if they exceed
+    /// the budget; the table is brought back under the budget on the next insertion.
+    pub fn get_many(&mut self, keys: &[K]) -> Result<Vec<Option<V>>>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let span = trace_span!(
+            "lru_get_many",
+            cache.hits = field::Empty,
+            cache.misses = field::Empty,
+            db.name = self.db.name(),
+            evicted = field::Empty,
+        );
+        let _enter = span.enter();
+        let evictions = self.counters.evictions();
+
+        self.adapt()?;
+
+        let mut misses = Vec::new();
+        let mut positions = Vec::new();
+        let mut values = Vec::with_capacity(keys.len());
+
+        for key in keys {
+            let hit = match self.map.get_mut(key) {
+                Some(aged) => {
+                    self.ages.touch(key, &mut aged.age);
+
+                    if let Some(policy) = &mut self.policy {
+                        policy.access(key);
+                    }
+
+                    values.push(Some(aged.value.clone()));
+                    true
+                }
+                None => {
+                    misses.push(key.clone());
+                    positions.push(values.len());
+                    values.push(None);
+                    false
                 }
+            };
+
+            self.lookup(hit);
+
+            if let Some(hot_keys) = &mut self.hot_keys {
+                hot_keys.record(key);
+            }
+        }
+
+        span.record("cache.hits", &(keys.len() - misses.len()));
+        span.record("cache.misses", &misses.len());
+
+        if !misses.is_empty() {
+            let loaded = self
+                .db
+                .multi_get(&misses)?
+                .into_iter()
+                .map(|v| v.map(|v| v.to_inner()).transpose())
+                .collect::<Result<Vec<Option<V>>>>()?;
 
-                Aged {
-                    age: 0,
-                    value: update.update_from(old),
+            // caching more values than the capacity would only evict the first ones loaded.
+            let mut room = match self.budget {
+                Some(_) => usize::MAX,
+                None => self.capacity,
+            };
+
+            for ((key, value), index) in misses.into_iter().zip(loaded).zip(positions) {
+                if let Some(value) = value {
+                    if room > 0 && !self.map.contains_key(&key) {
+                        room -= 1;
+
+                        if self.budget.is_none() {
+                            self.ensure_capacity(0)?;
+                        }
+
+                        let aged = Aged {
+                            age: 0,
+                            value: value.clone(),
+                        };
+
+                        self.cache_put(key, aged);
+                    }
+
+                    values[index] = Some(value);
                 }
             }
-        };
+        }
+
+        span.record("evicted", &(self.counters.evictions() - evictions));
+        Ok(values)
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    fn is_full(&self, incoming: usize) -> bool {
+        match &self.budget {
+            Some(budget) => !self.map.is_empty() && budget.is_full(incoming),
+            None => self.map.len() >= self.capacity,
+        }
+    }
+
+    /// Returns true if the table holds more than its capacity, after a resize.
+    fn is_over_capacity(&self) -> bool {
+        match &self.budget {
+            Some(budget) => budget.member.is_none() && budget.used > budget.max,
+            None => self.map.len() > self.capacity,
+        }
+    }
+
+    /// Iterates over all the entries of the table in key order, read from the database without
+    /// loading them in memory.
+    ///
+    /// In write-back mode, the dirty entries are yielded with their value in memory, merged in
+    /// key order with the ones read from the database.
+    pub fn iter(&self) -> Result<LruIter<K, V, S>>
+    where
+        V: Clone,
+    {
+        let compare = self.db.compare();
+        let mut pending = self
+            .dirty
+            .iter()
+            .map(|key| Ok((key_to_bytes(key)?, key)))
+            .collect::<Result<Vec<_>>>()?;
+
+        pending.sort_by(|a, b| compare(&a.0, &b.0));
+
+        Ok(LruIter {
+            compare,
+            done: false,
+            head: None,
+            iter: self
+                .db
+                .iter_opt(IteratorMode::Start, &IterOptions::bulk_scan())?,
+            pending: pending.into(),
+            table: self,
+        })
+    }
+
+    fn lookup(&mut self, hit: bool) {
+        self.counters.lookup(hit);
+        stats::cache_lookup(self.db.name(), hit);
+
+        if let Some(adaptive) = &mut self.adaptive {
+            adaptive.lookup(hit);
+        }
+    }
+
+    /// Returns the number of entries kept in memory.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns the estimated number of bytes used by the entries in memory,
+    /// excluding the heap allocations owned by the keys and values.
+    pub fn memory_usage(&self) -> usize {
+        self.map.capacity() * size_of::<(K, Aged<V>)>() + self.dirty.capacity() * size_of::<K>()
+    }
 
-        self.db.put(key, &new.value)?;
+    /// Returns the estimated number of bytes used by the values in byte-budget mode.
+    pub fn used_bytes(&self) -> Option<usize> {
+        self.budget.as_ref().map(|budget| budget.used)
+    }
+
+    pub fn put(&mut self, key: &K, value: V) -> Result<()>
+    where
+        K: Clone,
+    {
+        if self.write_back {
+            self.cache_insert(key, value)?;
+            self.dirty.insert(key.clone());
+        } else {
+            self.db.put(key, &value)?;
+            self.cache_insert(key, value)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the value of the key, or deletes the key if the value is `None`.
+    pub fn put_opt(&mut self, key: &K, value: Option<V>) -> Result<()>
+    where
+        K: Clone,
+    {
+        match value {
+            Some(value) => self.put(key, value),
+            None => self.delete(key),
+        }
+    }
+
+    /// Writes many keys / values in a single batch, then updates the cache.
+    ///
+    /// In write-back mode, the entries are only written to memory, just like [`LruTable::put`].
+    pub fn put_many<I>(&mut self, items: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Clone,
+    {
+        if self.write_back {
+            for (key, value) in items {
+                self.put(&key, value)?;
+            }
+
+            return Ok(());
+        }
 
-        if must_ensure_capacity {
-            self.ensure_capacity();
+        let items = items.into_iter().collect::<Vec<_>>();
+        let mut batch = RawBatch::new();
+
+        for (key, value) in &items {
+            self.db.batch_put(&mut batch, key, value)?;
         }
 
-        self.age += 1;
-        new.age = self.age;
+        self.db.write_raw(batch)?;
+
+        for (key, value) in items {
+            self.cache_insert(&key, value)?;
+        }
 
-        self.map.insert(key.clone(), new);
         Ok(())
     }
 
-    pub fn update_and_get<U>(&mut self, key: &K, update: U) -> Result<&mut V>
+    /// Returns the tracked keys with their access counts, hottest first.
+    pub(crate) fn hot_keys(&self) -> Vec<(K, u64)>
     where
         K: Clone,
-        U: UpdateFrom<V>,
     {
-        self.update(key, update)?;
-        Ok(&mut self.map.get_mut(key).unwrap().value)
+        self.hot_keys
+            .as_ref()
+            .map(|hot_keys| hot_keys.top())
+            .unwrap_or_default()
+    }
+
+    /// Changes the capacity of the table, evicting the least recently used entries if it shrinks.
+    ///
+    /// In byte-budget mode, `capacity` is the maximum number of bytes; a table sharing a
+    /// [`MemoryBudget`] keeps being bounded by the budget.
+    pub fn resize(&mut self, capacity: usize) -> Result<()>
+    where
+        K: Clone,
+    {
+        assert!(capacity > 0);
+
+        match &mut self.budget {
+            Some(budget) => budget.max = capacity,
+            None => self.capacity = capacity,
+        }
+
+        while self.is_over_capacity() {
+            if self.evict_oldest()?.is_none() {
+                break;
+            }
+        }
+
+        if self.budget.is_none() {
+            self.map.shrink_to(capacity);
+        }
+
+        Ok(())
+    }
+
+    /// Persists the hottest tracked keys in the database, to be reloaded by
+    /// [`LruTable::warm_up_hot_keys`] after a restart.
+    pub fn save_hot_keys(&self) -> Result<()>
+    where
+        K: Clone,
+    {
+        let keys = self
+            .hot_keys()
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect::<Vec<_>>();
+        save_hot_keys(&self.db, &keys)
+    }
+
+    /// Removes a key from the table and returns its value, read from the database if it is not
+    /// in memory.
+    pub fn take(&mut self, key: &K) -> Result<Option<V>> {
+        let stored = if self.map.contains_key(key) {
+            None
+        } else {
+            match self.db.get(key)? {
+                Some(value) => Some(value.to_inner()?),
+                None => return Ok(None),
+            }
+        };
+
+        self.db.delete(key)?;
+        self.dirty.remove(key);
+
+        Ok(match stored {
+            Some(value) => Some(value),
+            None => self.cache_remove(key).map(|aged| aged.value),
+        })
+    }
+
+    /// Starts counting the accesses of the keys, keeping track of the `count` hottest ones.
+    pub fn track_hot_keys(&mut self, count: usize)
+    where
+        K: Clone,
+    {
+        self.hot_keys = Some(HotKeys::new(count));
+    }
+
+    /// Publishes the age of the least recently used entry to the shared [`MemoryBudget`].
+    fn sync_budget(&self) {
+        if let Some(member) = self.budget.as_ref().and_then(|b| b.member.as_ref()) {
+            member.set_oldest(self.ages.oldest_age());
+        }
+    }
+
+    /// Returns the estimated size of a value in byte-budget mode, 0 otherwise.
+    fn value_size(&self, value: &V) -> usize {
+        self.budget
+            .as_ref()
+            .map_or(0, |budget| (budget.size_of)(value))
+    }
+
+    /// Enables or disables the write-back mode.
+    ///
+    /// Disabling the write-back mode flushes the dirty entries to the database.
+    pub fn set_write_back(&mut self, write_back: bool) -> Result<()> {
+        if !write_back {
+            self.flush()?;
+        }
+
+        self.write_back = write_back;
+        Ok(())
+    }
+
+    pub fn update<U>(&mut self, key: &K, update: U) -> Result<()>
+    where
+        K: Clone,
+        U: UpdateFrom<V>,
+    {
+        let new = match self.cache_remove(key) {
+            Some(aged) => update.update_from(Some(aged.value)),
+            None => {
+                let mut old = None;
+
+                if let Some(item) = self.db.get(key)? {
+                    old = Some(item.to_inner()?);
+                }
+
+                update.update_from(old)
+            }
+        };
+
+        if self.write_back {
+            self.dirty.insert(key.clone());
+        } else {
+            self.db.put(key, &new)?;
+        }
+
+        self.ensure_capacity(self.value_size(&new))?;
+        self.cache_put(key.clone(), Aged { age: 0, value: new });
+        Ok(())
+    }
+
+    /// Mutates the value of the key in place, loading it if needed, then writes it and marks it
+    /// as the most recently used.
+    ///
+    /// Returns false, without calling `f`, if the key does not exist.
+    pub fn modify<F>(&mut self, key: &K, f: F) -> Result<bool>
+    where
+        K: Clone,
+        F: FnOnce(&mut V),
+    {
+        let mut value = match self.cache_remove(key) {
+            Some(aged) => aged.value,
+            None => match self.db.get(key)? {
+                Some(value) => value.to_inner()?,
+                None => return Ok(false),
+            },
+        };
+
+        f(&mut value);
+
+        if self.write_back {
+            self.dirty.insert(key.clone());
+        } else {
+            self.db.put(key, &value)?;
+        }
+
+        self.ensure_capacity(self.value_size(&value))?;
+        self.cache_put(key.clone(), Aged { age: 0, value });
+        Ok(true)
+    }
+
+    /// Updates the value of the key, removing the key when `f` returns `None`.
+    pub fn update_or_delete<F>(&mut self, key: &K, f: F) -> Result<()>
+    where
+        K: Clone,
+        F: FnOnce(Option<V>) -> Option<V>,
+    {
+        let old = match self.cache_remove(key) {
+            Some(aged) => Some(aged.value),
+            None => match self.db.get(key)? {
+                Some(value) => Some(value.to_inner()?),
+                None => None,
+            },
+        };
+
+        match f(old) {
+            Some(value) => self.put(key, value),
+            None => {
+                self.dirty.remove(key);
+                self.db.delete(key)
+            }
+        }
+    }
+
+    pub fn update_and_get<U>(&mut self, key: &K, update: U) -> Result<&mut V>
+    where
+        K: Clone,
+        U: UpdateFrom<V>,
+    {
+        self.update(key, update)?;
+        Ok(&mut self.map.get_mut(key).unwrap().value)
+    }
+
+    /// Loads the keys missing from the cache, without evicting any entry, and returns
+    /// the number of entries loaded.
+    ///
+    /// The keys are expected hottest first; the loading stops once the table is full.
+    pub fn warm_up<I>(&mut self, keys: I) -> Result<usize>
+    where
+        I: IntoIterator<Item = K>,
+        K: Clone,
+    {
+        let mut seen = HashSet::new();
+
+        let misses = keys
+            .into_iter()
+            .filter(|k| !self.map.contains_key(k) && seen.insert(k.clone()))
+            .collect::<Vec<_>>();
+
+        if misses.is_empty() {
+            return Ok(0);
+        }
+
+        let values = self
+            .db
+            .multi_get(&misses)?
+            .into_iter()
+            .map(|v| v.map(|v| v.to_inner()).transpose())
+            .collect::<Result<Vec<Option<V>>>>()?;
+
+        let mut bytes = 0;
+        let mut loaded = Vec::new();
+
+        for (key, value) in misses.into_iter().zip(values) {
+            if let Some(value) = value {
+                let size = self.value_size(&value);
+
+                let full = match &self.budget {
+                    Some(budget) => !budget.fits(bytes + size),
+                    None => self.map.len() + loaded.len() >= self.capacity,
+                };
+
+                if full {
+                    break;
+                }
+
+                bytes += size;
+                loaded.push((key, value));
+            }
+        }
+
+        let count = loaded.len();
+
+        // the hottest keys are inserted last, to be the last ones evicted.
+        for (key, value) in loaded.into_iter().rev() {
+            self.cache_put(key, Aged { age: 0, value });
+        }
+
+        Ok(count)
+    }
+
+    /// Loads the hottest keys persisted by [`LruTable::save_hot_keys`].
+    pub fn warm_up_hot_keys(&mut self) -> Result<usize>
+    where
+        K: Clone,
+    {
+        let keys = load_hot_keys(&self.db)?;
+        self.warm_up(keys)
+    }
+
+    /// Loads the persisted hottest keys on a background thread, while the table is in use.
+    ///
+    /// The table is locked while the values of the keys are read and inserted.
+    pub fn warm_up_in_background(table: &Arc<Mutex<Self>>) -> JoinHandle<Result<usize>>
+    where
+        K: Clone + Send + 'static,
+        V: Send + 'static,
+        S: Send + 'static,
+    {
+        let table = Arc::clone(table);
+
+        thread::spawn(move || {
+            let db = lock(&table).db.clone();
+            let keys = load_hot_keys(&db)?;
+            lock(&table).warm_up(keys)
+        })
+    }
+}
+
+impl<K, V, S> Drop for LruTable<K, V, S> {
+    fn drop(&mut self) {
+        // the errors are logged by the database.
+        let _ = (self.drop_flush)(self);
+    }
+}
+
+impl<K, V, S> TableStats for LruTable<K, V, S>
+where
+    K: Debug + for<'de> Deserialize<'de> + Eq + Hash + Serialize,
+    V: for<'de> Deserialize<'de> + Serialize,
+    S: BuildHasher,
+{
+    /// In byte-budget mode, includes the estimated size of the values.
+    fn bytes_in_memory(&self) -> usize {
+        self.memory_usage() + self.used_bytes().unwrap_or(0)
+    }
+
+    fn entries_in_memory(&self) -> usize {
+        self.len()
+    }
+
+    fn evictions(&self) -> u64 {
+        self.counters.evictions()
+    }
+
+    fn hits(&self) -> u64 {
+        self.counters.hits()
+    }
+
+    fn misses(&self) -> u64 {
+        self.counters.misses()
+    }
+}
+
+/// A table of shared values, stored with the same serialized format as `V`.
+impl<K, V, S> LruTable<K, Arc<V>, S>
+where
+    K: Debug + for<'de> Deserialize<'de> + Eq + Hash + Serialize,
+    Arc<V>: for<'de> Deserialize<'de> + Serialize,
+    S: BuildHasher,
+{
+    /// Returns a shared reference to the value, which can be kept without borrowing the table.
+    pub fn get_arc<Q: ?Sized>(&mut self, key: &Q) -> Result<Option<Arc<V>>>
+    where
+        K: Borrow<Q> + Clone,
+        Q: Debug + Eq + Hash + Serialize + ToOwned<Owned = K>,
+    {
+        Ok(self.get(key)?.cloned())
+    }
+}
+
+/// Locks a table shared with a background thread.
+pub(crate) fn lock<T>(table: &Mutex<T>) -> MutexGuard<T> {
+    // a poisoned table is still consistent since the db is written before the cache.
+    table.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub(crate) fn load_hot_keys<K>(db: &Db<K>) -> Result<Vec<K>>
+where
+    K: Debug + for<'de> Deserialize<'de> + Serialize,
+{
+    Ok(db.get_meta(HOT_KEYS)?.unwrap_or_default())
+}
+
+pub(crate) fn save_hot_keys<K>(db: &Db<K>, keys: &[K]) -> Result<()>
+where
+    K: Debug + for<'de> Deserialize<'de> + Serialize,
+{
+    db.put_meta(HOT_KEYS, &keys)
+}
+
+/// Bounds the estimated number of bytes used by the values of a [`LruTable`].
+struct ByteBudget<V> {
+    max: usize,
+    member: Option<Arc<BudgetMember>>,
+    size_of: Box<dyn Fn(&V) -> usize + Send + Sync>,
+    used: usize,
+}
+
+impl<V> ByteBudget<V> {
+    fn add(&mut self, bytes: usize) {
+        self.used += bytes;
+
+        if let Some(member) = &self.member {
+            member.add(bytes);
+        }
+    }
+
+    /// Returns true if `incoming` bytes fit in the budget, without asking other tables to evict.
+    fn fits(&self, incoming: usize) -> bool {
+        match &self.member {
+            Some(member) => member.fits(incoming),
+            None => self.used + incoming <= self.max,
+        }
+    }
+
+    /// Returns true if this table must evict to make room for `incoming` bytes.
+    fn is_full(&self, incoming: usize) -> bool {
+        match &self.member {
+            Some(member) => member.is_full(incoming),
+            None => self.used + incoming > self.max,
+        }
+    }
+
+    /// Accounts for the replacement of a cached value.
+    fn replace(&mut self, old: &V, new: &V) {
+        self.sub((self.size_of)(old));
+        self.add((self.size_of)(new));
+    }
+
+    fn sub(&mut self, bytes: usize) {
+        self.used -= bytes;
+
+        if let Some(member) = &self.member {
+            member.sub(bytes);
+        }
+    }
+}
+
+/// An iterator over the entries of a [`LruTable`], overlaying the dirty entries on the database.
+pub struct LruIter<'a, K, V, S> {
+    compare: CompareFn,
+    done: bool,
+    /// The next entry read from the database, with its serialized key.
+    head: Option<(Vec<u8>, K, V)>,
+    iter: Iter<'a, K>,
+    /// The serialized keys of the dirty entries not yet yielded, in key order.
+    pending: VecDeque<(Vec<u8>, &'a K)>,
+    table: &'a LruTable<K, V, S>,
+}
+
+impl<'a, K, V, S> LruIter<'a, K, V, S>
+where
+    K: Clone + for<'de> Deserialize<'de> + Eq + Hash,
+    V: Clone + for<'de> Deserialize<'de>,
+    S: BuildHasher,
+{
+    fn next_item(&mut self) -> Result<Option<(K, V)>> {
+        if self.head.is_none() {
+            if let Some(kv) = self.iter.next()? {
+                let key: K = kv.key()?;
+
+                let value = match self.table.dirty.contains(&key) {
+                    true => self.table.map[&key].value.clone(),
+                    false => kv.value()?,
+                };
+
+                self.head = Some((kv.key_as_bytes()?.to_vec(), key, value));
+            }
+        }
+
+        let order = match (&self.head, self.pending.front()) {
+            (Some((head, ..)), Some((dirty, _))) => (self.compare)(dirty, head),
+            (None, Some(_)) => Ordering::Less,
+            (_, None) => Ordering::Greater,
+        };
+
+        if order == Ordering::Less {
+            let (_, key) = self.pending.pop_front().unwrap();
+            return Ok(Some((key.clone(), self.table.map[key].value.clone())));
+        }
+
+        // a dirty entry also in the database is yielded once, with its value in memory.
+        if order == Ordering::Equal {
+            self.pending.pop_front();
+        }
+
+        Ok(self.head.take().map(|(_, key, value)| (key, value)))
+    }
+}
+
+impl<'a, K, V, S> Iterator for LruIter<'a, K, V, S>
+where
+    K: Clone + for<'de> Deserialize<'de> + Eq + Hash,
+    V: Clone + for<'de> Deserialize<'de>,
+    S: BuildHasher,
+{
+    type Item = Result<(K, V)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.next_item() {
+            Ok(Some(item)) => Some(Ok(item)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// A batch of writes on a [`LruTable`].
+///
+/// The writes are kept in memory until [`LruTableBatch::commit`] is called.
+pub struct LruTableBatch<'a, K, V, S> {
+    ops: Vec<(K, Option<V>)>,
+    table: &'a mut LruTable<K, V, S>,
+}
+
+impl<'a, K, V, S> LruTableBatch<'a, K, V, S>
+where
+    K: Clone + Debug + for<'de> Deserialize<'de> + Eq + Hash + Serialize,
+    V: for<'de> Deserialize<'de> + Serialize,
+    S: BuildHasher,
+{
+    /// Writes the batch to the database, then applies it to the table.
+    pub fn commit(self) -> Result<()> {
+        let mut tx = Transaction::new();
+        tx.enlist(self)?;
+        tx.commit()
+    }
+
+    pub fn delete(&mut self, key: K) {
+        self.ops.push((key, None));
+    }
+
+    pub fn put(&mut self, key: K, value: V) {
+        self.ops.push((key, Some(value)));
+    }
+}
+
+impl<'a, K, V, S> PartOps for LruTableBatch<'a, K, V, S>
+where
+    K: Clone + Debug + for<'de> Deserialize<'de> + Eq + Hash + Serialize,
+    V: for<'de> Deserialize<'de> + Serialize,
+    S: BuildHasher,
+{
+    fn db(&self) -> &Arc<rocksdb::DB> {
+        self.table.db.raw_db()
+    }
+
+    fn db_name(&self) -> &str {
+        self.table.db.name()
+    }
+
+    fn write_ops(&self, batch: &mut RawBatch) -> Result<()> {
+        for (key, value) in &self.ops {
+            match value {
+                Some(value) => self.table.db.batch_put(batch, key, value)?,
+                None => self.table.db.batch_delete(batch, key)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn apply_ops(self: Box<Self>) {
+        let batch = *self;
+
+        let table = batch.table;
+
+        for (key, value) in batch.ops {
+            table.dirty.remove(&key);
+
+            match value {
+                // only the entries already cached are updated, to avoid evicting dirty entries.
+                Some(value) => {
+                    if let Some(aged) = table.map.get_mut(&key) {
+                        table.ages.touch(&key, &mut aged.age);
+
+                        if let Some(policy) = &mut table.policy {
+                            policy.access(&key);
+                        }
+
+                        if let Some(budget) = &mut table.budget {
+                            budget.replace(&aged.value, &value);
+                        }
+
+                        aged.value = value;
+                    }
+                }
+                None => {
+                    table.cache_remove(&key);
+                }
+            }
+        }
+
+        // the batch is committed, an entry failing to be written back stays cached.
+        if let Err(e) = table.evict_over_budget() {
+            log_err(e, table.db.name());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LruTable;
+    use crate::{Db, MemoryBudget};
+    use std::sync::{Arc, Mutex};
+
+    fn len(v: &String) -> usize {
+        v.len()
+    }
+
+    #[test]
+    fn drop_flushes_write_back() {
+        let db = Db::<u32>::open_temporary().unwrap();
+
+        let mut table = LruTable::<u32, String>::with_capacity(db.clone(), 10);
+        table.set_write_back(true).unwrap();
+        table.put(&1, "a".to_string()).unwrap();
+        assert!(db.get(&1).unwrap().is_none());
+        drop(table);
+
+        let mut table = LruTable::<u32, String>::with_capacity(db, 10);
+        assert_eq!(table.get(&1).unwrap(), Some(&"a".to_string()));
+    }
+
+    #[test]
+    fn warm_up_in_background() {
+        let db = Db::<u32>::open_temporary().unwrap();
+
+        for key in 0..4 {
+            db.put(&key, &key.to_string()).unwrap();
+        }
+
+        let mut table = LruTable::<u32, String>::with_capacity(db.clone(), 10);
+        table.track_hot_keys(2);
+
+        for key in &[1, 1, 2] {
+            table.get(key).unwrap();
+        }
+
+        table.save_hot_keys().unwrap();
+        drop(table);
+
+        let table = Arc::new(Mutex::new(LruTable::<u32, String>::with_capacity(db, 10)));
+        let count = LruTable::warm_up_in_background(&table).join().unwrap();
+
+        assert_eq!(count.unwrap(), 2);
+        assert_eq!(table.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn get_many_beyond_capacity() {
+        let db = Db::<u32>::open_temporary().unwrap();
+
+        for key in 0..4 {
+            db.put(&key, &key.to_string()).unwrap();
+        }
+
+        let mut table = LruTable::<u32, String>::with_capacity(db, 2);
+        assert_eq!(table.get(&0).unwrap(), Some(&"0".to_string()));
+
+        let values = table.get_many(&[0, 1, 2, 3, 9]).unwrap();
+        let expected = ["0", "1", "2", "3"].iter().map(|v| Some(v.to_string()));
+
+        assert_eq!(values, expected.chain(Some(None)).collect::<Vec<_>>());
+        assert_eq!(table.len(), 2);
+    }
+
+    #[test]
+    fn memory_budget_with_idle_table() {
+        let budget = MemoryBudget::new(100);
+        let size_of = |v: &String| v.len();
+
+        let mut idle =
+            LruTable::with_memory_budget(Db::<u32>::open_temporary().unwrap(), &budget, size_of);
+
+        for key in 0..4 {
+            idle.put(&key, "i".repeat(10)).unwrap();
+        }
+
+        let mut active =
+            LruTable::with_memory_budget(Db::<u32>::open_temporary().unwrap(), &budget, size_of);
+
+        for key in 0..50 {
+            active.put(&key, "a".repeat(10)).unwrap();
+            assert!(budget.used() <= budget.max());
+        }
+
+        assert_eq!(active.len(), 6);
+
+        // the idle table evicts its least recently used entry on its next access.
+        assert!(idle.get(&3).unwrap().is_some());
+        assert_eq!(idle.len(), 3);
+        assert!(budget.used() <= budget.max());
+
+        active.put(&50, "a".repeat(10)).unwrap();
+        assert_eq!(active.len(), 7);
+        assert!(budget.used() <= budget.max());
+    }
+
+    #[test]
+    fn batch_growing_values_evicts() {
+        let db = Db::<u32>::open_temporary().unwrap();
+        let mut table = LruTable::with_byte_budget(db, 20, len);
+
+        table.put(&1, "a".repeat(5)).unwrap();
+        table.put(&2, "b".repeat(5)).unwrap();
+
+        let mut batch = table.batch();
+        batch.put(2, "b".repeat(18));
+        batch.commit().unwrap();
+
+        assert_eq!(table.used_bytes(), Some(18));
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.get(&2).unwrap().map(String::len), Some(18));
+    }
+
+    #[test]
+    fn iter_merges_write_back_in_key_order() {
+        let db = Db::<u32>::open_temporary().unwrap();
+
+        for key in &[2, 4] {
+            db.put(key, &key.to_string()).unwrap();
+        }
+
+        let mut table = LruTable::<u32, String>::with_capacity(db, 10);
+        table.set_write_back(true).unwrap();
+
+        for key in &[5, 1, 3, 4] {
+            table.put(key, format!("dirty {}", key)).unwrap();
+        }
+
+        let items = table
+            .iter()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let expected = vec![
+            (1, "dirty 1".to_string()),
+            (2, "2".to_string()),
+            (3, "dirty 3".to_string()),
+            (4, "dirty 4".to_string()),
+            (5, "dirty 5".to_string()),
+        ];
+
+        assert_eq!(items, expected);
     }
 }
@@ -1,15 +1,40 @@
-use super::{Db, IteratorMode, Result};
-use crate::UpdateFrom;
+use super::{Db, IterOptions, IteratorMode, Result};
+#[cfg(feature = "rayon")]
+use crate::Direction;
+use crate::{
+    serialize_to_bytes, table_stats::CacheCounters, transaction::PartOps, RawBatch, TableStats,
+    Transaction, UpdateFrom,
+};
+#[cfg(feature = "rayon")]
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "rayon")]
+use std::ops::Bound;
 use std::{
     borrow::Borrow,
-    collections::hash_map::{HashMap, Iter, RandomState},
+    collections::{
+        hash_map::{HashMap, IntoIter, Iter, Keys, RandomState, Values},
+        HashSet,
+    },
     fmt::Debug,
     hash::{BuildHasher, Hash},
+    mem::{replace, size_of},
+    sync::Arc,
+};
+#[cfg(feature = "serde_json")]
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
 };
 
+/// The number of shards loaded by each thread, balancing the shards of uneven sizes.
+#[cfg(feature = "rayon")]
+const SHARDS_PER_THREAD: usize = 4;
+
 /// A fully in-memory loaded table.
 pub struct MemTable<K, V, S = RandomState> {
+    counters: CacheCounters,
     db: Db<K>,
     map: HashMap<K, V, S>,
 }
@@ -22,6 +47,22 @@ where
     pub fn new(db: Db<K>) -> Result<Self> {
         Self::with_hasher(db, Default::default())
     }
+
+    /// Creates a table loading the database on the rayon thread pool,
+    /// see [`MemTable::with_hasher_parallel`].
+    #[cfg(feature = "rayon")]
+    pub fn new_parallel(db: Db<K>) -> Result<Self>
+    where
+        K: Send + Sync,
+        V: Send,
+    {
+        Self::with_hasher_parallel(db, Default::default())
+    }
+
+    /// Creates a table that is only loaded from the database on first access.
+    pub fn new_lazy(db: Db<K>) -> LazyMemTable<K, V, RandomState> {
+        LazyMemTable::with_hasher(db, Default::default())
+    }
 }
 
 impl<K, V, S> MemTable<K, V, S>
@@ -30,18 +71,46 @@ where
     V: for<'de> Deserialize<'de> + Serialize,
     S: BuildHasher,
 {
+    /// Creates a batch of writes applied atomically to the database and to the table on commit.
+    pub fn batch(&mut self) -> MemTableBatch<K, V, S> {
+        MemTableBatch {
+            ops: Vec::new(),
+            table: self,
+        }
+    }
+
     pub fn with_hasher(db: Db<K>, hasher: S) -> Result<Self> {
         let mut map = HashMap::with_hasher(hasher);
+        load_map(&db, &mut map)?;
+        Ok(Self {
+            counters: CacheCounters::default(),
+            db,
+            map,
+        })
+    }
 
-        {
-            let mut iter = db.iter(IteratorMode::Start)?;
-
-            while let Some(kv) = iter.next()? {
-                map.insert(kv.key()?, kv.value()?);
-            }
-        }
+    /// Creates a table loading the database on the rayon thread pool, each task reading a shard
+    /// of the key space.
+    ///
+    /// The shards are split evenly between the first and the last key, so the keys crowded in
+    /// a few parts of the key space load on fewer threads. Fails with
+    /// [`Error::KeyOrderUnsupported`](crate::Error::KeyOrderUnsupported) if the database uses
+    /// a [comparator](crate::KeyComparator), since the shards split the bytewise order.
+    #[cfg(feature = "rayon")]
+    pub fn with_hasher_parallel(db: Db<K>, hasher: S) -> Result<Self>
+    where
+        K: Send + Sync,
+        V: Send,
+    {
+        db.ensure_bytewise("with_hasher_parallel")?;
 
-        Ok(Self { db, map })
+        let mut map = HashMap::with_hasher(hasher);
+        load_map_parallel(&db, &mut map)?;
+        Ok(Self {
+            counters: CacheCounters::default(),
+            db,
+            map,
+        })
     }
 
     /// Returns true if the table contains a value for the specified key.
@@ -63,13 +132,69 @@ where
         })
     }
 
+    /// Writes all the entries to a JSON Lines file, one `{"key": ..., "value": ...}` object per
+    /// line, and returns the number of lines written.
+    #[cfg(feature = "serde_json")]
+    pub fn export_jsonl<P: AsRef<Path>>(&self, path: P) -> Result<u64> {
+        #[derive(Serialize)]
+        struct Line<'a, K, V> {
+            key: &'a K,
+            value: &'a V,
+        }
+
+        let mut count = 0;
+        let mut w = BufWriter::new(File::create(path)?);
+
+        for (key, value) in &self.map {
+            serde_json::to_writer(&mut w, &Line { key, value })?;
+            w.write_all(b"\n")?;
+            count += 1;
+        }
+
+        w.flush()?;
+        Ok(count)
+    }
+
+    /// Calls `f` on every entry, then writes the values it modified in a single batch.
+    ///
+    /// If an error occurs, the table may contain values modified by `f` that are not written.
+    pub fn for_each_mut<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(&K, &mut V),
+    {
+        let mut batch = RawBatch::new();
+
+        for (key, value) in self.map.iter_mut() {
+            let old = serialize_to_bytes(value)?;
+
+            f(key, value);
+
+            if serialize_to_bytes(value)? != old {
+                self.db.batch_put(&mut batch, key, value)?;
+            }
+        }
+
+        if batch.is_empty() {
+            Ok(())
+        } else {
+            self.db.write_raw(batch)
+        }
+    }
+
     /// Returns a reference to the value corresponding to the key.
     pub fn get<Q: ?Sized>(&self, key: &Q) -> Option<&V>
     where
         K: Borrow<Q>,
         Q: Eq + Hash,
     {
-        self.map.get(key)
+        let value = self.map.get(key);
+        self.counters.lookup(value.is_some());
+        value
+    }
+
+    /// Returns the values corresponding to the keys, in the same order as `keys`.
+    pub fn get_many(&self, keys: &[K]) -> Vec<Option<&V>> {
+        keys.iter().map(|key| self.get(key)).collect()
     }
 
     pub fn get_or_init<F>(&mut self, key: &K, f: F) -> Result<&V>
@@ -98,24 +223,134 @@ where
         self.map.iter()
     }
 
-    pub fn put(&mut self, key: &K, value: V) -> Result<()>
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Iterates over the keys of the table, in arbitrary order.
+    pub fn keys(&self) -> Keys<K, V> {
+        self.map.keys()
+    }
+
+    /// Returns the number of entries in the table.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns the estimated number of bytes used by the entries in memory,
+    /// excluding the heap allocations owned by the keys and values.
+    pub fn memory_usage(&self) -> usize {
+        self.map.capacity() * size_of::<(K, V)>()
+    }
+
+    /// Inserts a key / value in the table, returning the value previously at the key, if any.
+    pub fn put(&mut self, key: &K, value: V) -> Result<Option<V>>
     where
         K: Clone,
     {
         self.db.put(&key, &value)?;
 
-        match self.map.get_mut(key) {
-            Some(v) => {
-                *v = value;
-            }
+        Ok(match self.map.get_mut(key) {
+            Some(v) => Some(replace(v, value)),
             None => {
                 self.map.insert(key.clone(), value);
+                None
             }
-        };
+        })
+    }
 
+    /// Inserts the value of the key, or deletes the key if the value is `None`,
+    /// returning the value previously at the key, if any.
+    pub fn put_opt(&mut self, key: &K, value: Option<V>) -> Result<Option<V>>
+    where
+        K: Clone,
+    {
+        match value {
+            Some(value) => self.put(key, value),
+            None => self.delete(key),
+        }
+    }
+
+    /// Writes many keys / values in a single batch, then updates the table.
+    pub fn put_many<I>(&mut self, items: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let items = items.into_iter().collect::<Vec<_>>();
+        let mut batch = RawBatch::new();
+
+        for (key, value) in &items {
+            self.db.batch_put(&mut batch, key, value)?;
+        }
+
+        self.db.write_raw(batch)?;
+        self.map.extend(items);
+        Ok(())
+    }
+
+    /// Re-reads the database, updating the changed entries and removing the deleted ones.
+    ///
+    /// If an error occurs, the table may contain a mix of the old and the reloaded entries.
+    pub fn reload(&mut self) -> Result<()>
+    where
+        K: Clone,
+    {
+        let mut seen = HashSet::with_capacity(self.map.len());
+        let mut iter = self
+            .db
+            .iter_opt(IteratorMode::Start, &IterOptions::bulk_scan())?;
+
+        while let Some(kv) = iter.next()? {
+            let key: K = kv.key()?;
+            seen.insert(key.clone());
+            self.map.insert(key, kv.value()?);
+        }
+
+        self.map.retain(|k, _| seen.contains(k));
         Ok(())
     }
 
+    /// Keeps only the entries for which `f` returns true, deleting the others from the database
+    /// in a single batch. The kept values modified by `f` are written in the same batch.
+    ///
+    /// If an error occurs, the table may contain values modified by `f` that are not written.
+    pub fn retain<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(&K, &mut V) -> bool,
+        K: Clone,
+    {
+        let mut batch = RawBatch::new();
+        let mut removed = Vec::new();
+
+        for (key, value) in self.map.iter_mut() {
+            let old = serialize_to_bytes(value)?;
+
+            if !f(key, value) {
+                self.db.batch_delete(&mut batch, key)?;
+                removed.push(key.clone());
+            } else if serialize_to_bytes(value)? != old {
+                self.db.batch_put(&mut batch, key, value)?;
+            }
+        }
+
+        if !batch.is_empty() {
+            self.db.write_raw(batch)?;
+        }
+
+        for key in &removed {
+            self.map.remove(key);
+        }
+
+        Ok(())
+    }
+
+    /// Removes a key from the table and returns its value, like [`MemTable::delete`].
+    pub fn take(&mut self, key: &K) -> Result<Option<V>> {
+        self.delete(key)
+    }
+
     pub fn update<U>(&mut self, key: K, update: U) -> Result<()>
     where
         U: UpdateFrom<V>,
@@ -137,4 +372,345 @@ where
 
         r
     }
+
+    /// Updates the value of the key, removing the key when `f` returns `None`.
+    pub fn update_or_delete<F>(&mut self, key: K, f: F) -> Result<()>
+    where
+        F: FnOnce(Option<V>) -> Option<V>,
+    {
+        let new = f(self.map.remove(&key));
+
+        let r = match &new {
+            Some(v) => self.db.put(&key, v),
+            None => self.db.delete(&key),
+        };
+
+        if r.is_err() {
+            if let Some(v) = self.db.get(&key)? {
+                self.map.insert(key, v.to_inner()?);
+            }
+        } else if let Some(v) = new {
+            self.map.insert(key, v);
+        }
+
+        r
+    }
+
+    /// Iterates over the values of the table, in arbitrary order.
+    pub fn values(&self) -> Values<K, V> {
+        self.map.values()
+    }
+}
+
+impl<'a, K, V, S> IntoIterator for &'a MemTable<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.iter()
+    }
+}
+
+impl<K, V, S> IntoIterator for MemTable<K, V, S> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    /// Consumes the table, leaving the database unchanged.
+    fn into_iter(self) -> Self::IntoIter {
+        self.map.into_iter()
+    }
+}
+
+/// A [`MemTable`] loaded from the database on first access.
+pub struct LazyMemTable<K, V, S = RandomState> {
+    state: LazyState<K, V, S>,
+}
+
+enum LazyState<K, V, S> {
+    Loaded(MemTable<K, V, S>),
+    Unloaded(Db<K>, HashMap<K, V, S>),
+    Empty,
+}
+
+impl<K, V, S> LazyMemTable<K, V, S>
+where
+    K: for<'de> Deserialize<'de> + Debug + Eq + Hash + Serialize,
+    V: for<'de> Deserialize<'de> + Serialize,
+    S: BuildHasher,
+{
+    pub fn with_hasher(db: Db<K>, hasher: S) -> Self {
+        Self {
+            state: LazyState::Unloaded(db, HashMap::with_hasher(hasher)),
+        }
+    }
+
+    /// Returns the table, loading it from the database if needed.
+    pub fn get(&mut self) -> Result<&mut MemTable<K, V, S>> {
+        if let LazyState::Unloaded(db, map) = &mut self.state {
+            if let Err(e) = load_map(db, map) {
+                map.clear();
+                return Err(e);
+            }
+        }
+
+        if let LazyState::Unloaded(..) = self.state {
+            if let LazyState::Unloaded(db, map) = replace(&mut self.state, LazyState::Empty) {
+                self.state = LazyState::Loaded(MemTable {
+                    counters: CacheCounters::default(),
+                    db,
+                    map,
+                });
+            }
+        }
+
+        match &mut self.state {
+            LazyState::Loaded(table) => Ok(table),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns true if the table has already been loaded from the database.
+    pub fn is_loaded(&self) -> bool {
+        matches!(self.state, LazyState::Loaded(_))
+    }
+
+    /// Returns the table, loading it from the database if needed.
+    pub fn into_inner(mut self) -> Result<MemTable<K, V, S>> {
+        self.get()?;
+
+        match self.state {
+            LazyState::Loaded(table) => Ok(table),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<K, V, S> TableStats for MemTable<K, V, S>
+where
+    K: for<'de> Deserialize<'de> + Debug + Eq + Hash + Serialize,
+    V: for<'de> Deserialize<'de> + Serialize,
+    S: BuildHasher,
+{
+    fn bytes_in_memory(&self) -> usize {
+        self.memory_usage()
+    }
+
+    fn entries_in_memory(&self) -> usize {
+        self.len()
+    }
+
+    fn evictions(&self) -> u64 {
+        self.counters.evictions()
+    }
+
+    fn hits(&self) -> u64 {
+        self.counters.hits()
+    }
+
+    fn misses(&self) -> u64 {
+        self.counters.misses()
+    }
+}
+
+/// A batch of writes on a [`MemTable`].
+///
+/// The writes are kept in memory until [`MemTableBatch::commit`] is called.
+pub struct MemTableBatch<'a, K, V, S> {
+    ops: Vec<(K, Option<V>)>,
+    table: &'a mut MemTable<K, V, S>,
+}
+
+impl<'a, K, V, S> MemTableBatch<'a, K, V, S>
+where
+    K: for<'de> Deserialize<'de> + Debug + Eq + Hash + Serialize,
+    V: for<'de> Deserialize<'de> + Serialize,
+    S: BuildHasher,
+{
+    /// Writes the batch to the database, then applies it to the table.
+    pub fn commit(self) -> Result<()> {
+        let mut tx = Transaction::new();
+        tx.enlist(self)?;
+        tx.commit()
+    }
+
+    pub fn delete(&mut self, key: K) {
+        self.ops.push((key, None));
+    }
+
+    pub fn put(&mut self, key: K, value: V) {
+        self.ops.push((key, Some(value)));
+    }
+}
+
+impl<'a, K, V, S> PartOps for MemTableBatch<'a, K, V, S>
+where
+    K: for<'de> Deserialize<'de> + Debug + Eq + Hash + Serialize,
+    V: for<'de> Deserialize<'de> + Serialize,
+    S: BuildHasher,
+{
+    fn db(&self) -> &Arc<rocksdb::DB> {
+        self.table.db.raw_db()
+    }
+
+    fn db_name(&self) -> &str {
+        self.table.db.name()
+    }
+
+    fn write_ops(&self, batch: &mut RawBatch) -> Result<()> {
+        for (key, value) in &self.ops {
+            match value {
+                Some(value) => self.table.db.batch_put(batch, key, value)?,
+                None => self.table.db.batch_delete(batch, key)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn apply_ops(self: Box<Self>) {
+        let batch = *self;
+
+        for (key, value) in batch.ops {
+            match value {
+                Some(value) => {
+                    batch.table.map.insert(key, value);
+                }
+                None => {
+                    batch.table.map.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+fn load_map<K, V, S>(db: &Db<K>, map: &mut HashMap<K, V, S>) -> Result<()>
+where
+    K: for<'de> Deserialize<'de> + Debug + Eq + Hash + Serialize,
+    V: for<'de> Deserialize<'de> + Serialize,
+    S: BuildHasher,
+{
+    let mut iter = db.iter_opt(IteratorMode::Start, &IterOptions::bulk_scan())?;
+
+    while let Some(kv) = iter.next()? {
+        map.insert(kv.key()?, kv.value()?);
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "rayon")]
+fn load_map_parallel<K, V, S>(db: &Db<K>, map: &mut HashMap<K, V, S>) -> Result<()>
+where
+    K: for<'de> Deserialize<'de> + Debug + Eq + Hash + Send + Serialize + Sync,
+    V: for<'de> Deserialize<'de> + Send + Serialize,
+    S: BuildHasher,
+{
+    let first = match db.iter(IteratorMode::Start)?.next()? {
+        Some(kv) => kv.key_as_bytes()?.to_vec(),
+        None => return Ok(()),
+    };
+
+    let last = match db.iter(IteratorMode::End)?.next()? {
+        Some(kv) => kv.key_as_bytes()?.to_vec(),
+        None => return Ok(()),
+    };
+
+    let count = rayon::current_num_threads() * SHARDS_PER_THREAD;
+    let bounds = shard_bounds(&first, &last, count);
+
+    let shards = (0..=bounds.len())
+        .into_par_iter()
+        .map(|i| {
+            let start = match i.checked_sub(1) {
+                Some(prev) => Bound::Included(bounds[prev].clone()),
+                None => Bound::Unbounded,
+            };
+
+            let end = bounds
+                .get(i)
+                .map_or(Bound::Unbounded, |b| Bound::Excluded(b.clone()));
+
+            load_shard(db, start, end)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    map.reserve(shards.iter().map(Vec::len).sum());
+
+    for shard in shards {
+        map.extend(shard);
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "rayon")]
+fn load_shard<K, V>(db: &Db<K>, start: Bound<Vec<u8>>, end: Bound<Vec<u8>>) -> Result<Vec<(K, V)>>
+where
+    K: for<'de> Deserialize<'de> + Debug + Serialize,
+    V: for<'de> Deserialize<'de>,
+{
+    let mut iter = db.iter_raw_range(start, end, Direction::Forward)?;
+    let mut items = Vec::new();
+
+    while let Some(kv) = iter.next()? {
+        items.push((kv.key()?, kv.value()?));
+    }
+
+    Ok(items)
+}
+
+/// Splits the keys from `first` to `last` in `count` ranges of equal width, returning the
+/// boundaries between the ranges.
+///
+/// The keys are read as big endian numbers of 8 bytes following their common prefix.
+#[cfg(feature = "rayon")]
+fn shard_bounds(first: &[u8], last: &[u8], count: usize) -> Vec<Vec<u8>> {
+    let common = first.iter().zip(last).take_while(|(a, b)| a == b).count();
+
+    let prefix = &first[..common];
+    let from = be_u64(&first[common..]);
+    let step = (be_u64(&last[common..]) - from) / count as u64;
+
+    if step == 0 {
+        return Vec::new();
+    }
+
+    (1..count as u64)
+        .map(|i| [prefix, &(from + step * i).to_be_bytes()].concat())
+        .collect()
+}
+
+/// Reads the first 8 bytes as a big endian number, padded with 0.
+#[cfg(feature = "rayon")]
+fn be_u64(bytes: &[u8]) -> u64 {
+    let mut buf = [0; 8];
+    let len = bytes.len().min(8);
+
+    buf[..len].copy_from_slice(&bytes[..len]);
+    u64::from_be_bytes(buf)
+}
+
+#[cfg(all(test, feature = "rayon"))]
+mod tests {
+    use super::MemTable;
+    use crate::{CaseInsensitive, Db, Error};
+
+    #[test]
+    fn parallel_load_refuses_comparator() {
+        let db = Db::<String>::open_temporary_with(|path| {
+            Db::open_with_comparator::<CaseInsensitive, _>(path)
+        })
+        .unwrap();
+
+        db.put(&"a".to_string(), &1u32).unwrap();
+
+        let e = MemTable::<String, u32>::with_hasher_parallel(db, Default::default())
+            .err()
+            .unwrap();
+
+        assert!(matches!(
+            e.root(),
+            Error::KeyOrderUnsupported("with_hasher_parallel")
+        ));
+    }
 }
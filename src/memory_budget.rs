@@ -0,0 +1,143 @@
+use std::sync::{
+    atomic::{AtomicU64, AtomicUsize, Ordering::Relaxed},
+    Arc, Mutex, Weak,
+};
+
+/// A number of bytes shared by several [`LruTable`](crate::LruTable)s, created with
+/// [`LruTable::with_memory_budget`](crate::LruTable::with_memory_budget).
+///
+/// When the budget is exceeded, the table holding the least recently used entry is asked to
+/// evict it. Since a table cannot be accessed by another, a table asked to evict does it on its
+/// next access; until then, the inserting table evicts its own entries, so the budget holds
+/// after every insertion. Only a table without entries can exceed it, by its first value.
+#[derive(Clone)]
+pub struct MemoryBudget {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    clock: Arc<AtomicU64>,
+    max: usize,
+    members: Mutex<Vec<Weak<BudgetMember>>>,
+    used: AtomicUsize,
+}
+
+impl MemoryBudget {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                clock: Arc::new(AtomicU64::new(0)),
+                max: max_bytes,
+                members: Mutex::new(Vec::new()),
+                used: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// Returns the maximum number of bytes of the tables.
+    pub fn max(&self) -> usize {
+        self.shared.max
+    }
+
+    /// Returns the estimated number of bytes used by the values of all the tables.
+    pub fn used(&self) -> usize {
+        self.shared.used.load(Relaxed)
+    }
+
+    pub(crate) fn register(&self) -> Arc<BudgetMember> {
+        let member = Arc::new(BudgetMember {
+            oldest: AtomicU64::new(u64::MAX),
+            pending: AtomicUsize::new(0),
+            shared: Arc::clone(&self.shared),
+            used: AtomicUsize::new(0),
+        });
+
+        let mut members = self.shared.lock_members();
+        members.retain(|m| m.strong_count() > 0);
+        members.push(Arc::downgrade(&member));
+        member
+    }
+}
+
+impl Shared {
+    fn lock_members(&self) -> std::sync::MutexGuard<Vec<Weak<BudgetMember>>> {
+        self.members.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The share of a table in a [`MemoryBudget`].
+pub(crate) struct BudgetMember {
+    /// The age of the least recently used entry of the table, `u64::MAX` when empty.
+    oldest: AtomicU64,
+    /// The number of bytes the table was asked to evict by the other tables.
+    pending: AtomicUsize,
+    shared: Arc<Shared>,
+    used: AtomicUsize,
+}
+
+impl BudgetMember {
+    pub fn add(&self, bytes: usize) {
+        self.used.fetch_add(bytes, Relaxed);
+        self.shared.used.fetch_add(bytes, Relaxed);
+    }
+
+    /// Returns the clock shared by the tables, so their ages can be compared.
+    pub fn clock(&self) -> Arc<AtomicU64> {
+        Arc::clone(&self.shared.clock)
+    }
+
+    /// Returns true if `incoming` bytes fit in the budget.
+    pub fn fits(&self, incoming: usize) -> bool {
+        self.shared.used.load(Relaxed) + incoming <= self.shared.max
+    }
+
+    /// Returns true if this table must evict an entry to make room for `incoming` bytes.
+    ///
+    /// If another table holds the least recently used entry, it is asked to evict the excess on
+    /// its next access; until then, this table evicts its own entries to stay in the budget.
+    pub fn is_full(self: &Arc<Self>, incoming: usize) -> bool {
+        let used = self.shared.used.load(Relaxed);
+
+        if used + incoming <= self.shared.max {
+            return false;
+        }
+
+        let coldest = self
+            .shared
+            .lock_members()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .filter(|m| m.used.load(Relaxed) > 0)
+            .min_by_key(|m| m.oldest.load(Relaxed));
+
+        if let Some(m) = coldest {
+            if !Arc::ptr_eq(&m, self) {
+                // the request is not cumulated, a table cannot evict more than it holds.
+                let excess = (used + incoming - self.shared.max).min(m.used.load(Relaxed));
+                m.pending.fetch_max(excess, Relaxed);
+            }
+        }
+
+        true
+    }
+
+    pub fn set_oldest(&self, age: Option<u64>) {
+        self.oldest.store(age.unwrap_or(u64::MAX), Relaxed);
+    }
+
+    pub fn sub(&self, bytes: usize) {
+        self.used.fetch_sub(bytes, Relaxed);
+        self.shared.used.fetch_sub(bytes, Relaxed);
+    }
+
+    /// Returns and clears the number of bytes this table was asked to evict.
+    pub fn take_pending(&self) -> usize {
+        self.pending.swap(0, Relaxed)
+    }
+}
+
+impl Drop for BudgetMember {
+    fn drop(&mut self) {
+        self.shared.used.fetch_sub(*self.used.get_mut(), Relaxed);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{
+    db::{append_checksum, strip_checksum},
+    deserialize_from_bytes, serialize_to_bytes, Db, Result, UpdateFrom,
+};
+use rocksdb::MergeOperands;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fmt::Debug, marker::PhantomData};
+use tracing::error;
+
+/// Defines a RocksDB merge operator in Rust.
+///
+/// The updates are serialized with bincode and stored as merge operands, then applied
+/// on the existing value by RocksDB during reads and compactions.
+pub trait MergeSerde {
+    /// The name of the merge operator, persisted by RocksDB.
+    const NAME: &'static str;
+
+    type Update: DeserializeOwned + Serialize + UpdateFrom<Self::Value>;
+    type Value: DeserializeOwned + Serialize;
+}
+
+pub(crate) fn full_merge<M: MergeSerde>(
+    _key: &[u8],
+    existing: Option<&[u8]>,
+    operands: &mut MergeOperands,
+) -> Option<Vec<u8>> {
+    log_merge::<M>(apply_merge::<M>(existing, operands, false))
+}
+
+/// The merge operator of the databases storing the values with a trailing CRC32.
+pub(crate) fn full_merge_checksummed<M: MergeSerde>(
+    _key: &[u8],
+    existing: Option<&[u8]>,
+    operands: &mut MergeOperands,
+) -> Option<Vec<u8>> {
+    log_merge::<M>(apply_merge::<M>(existing, operands, true))
+}
+
+fn log_merge<M: MergeSerde>(result: Result<Vec<u8>>) -> Option<Vec<u8>> {
+    match result {
+        Ok(v) => Some(v),
+        Err(e) => {
+            error!({ merge.name = M::NAME }, "{}", e);
+            None
+        }
+    }
+}
+
+fn apply_merge<M: MergeSerde>(
+    existing: Option<&[u8]>,
+    operands: &mut MergeOperands,
+    checksum: bool,
+) -> Result<Vec<u8>> {
+    let mut value: Option<M::Value> = match existing {
+        Some(bytes) => {
+            let bytes = strip_checksum(bytes, checksum, M::NAME)?;
+            Some(deserialize_from_bytes(bytes)?)
+        }
+        None => None,
+    };
+
+    for op in operands {
+        let update: M::Update = deserialize_from_bytes(op)?;
+        value = Some(update.update_from(value));
+    }
+
+    // there is always at least one operand on a full merge.
+    let mut bytes = match value {
+        Some(v) => serialize_to_bytes(&v)?,
+        None => Vec::new(),
+    };
+
+    if checksum {
+        append_checksum(&mut bytes);
+    }
+
+    Ok(bytes)
+}
+
+/// A table where updates are issued as RocksDB merges instead of a read followed by a write.
+///
+/// The [`Db`] must be opened with [`Db::open_with_merge`], or [`Db::open_with_merge_and_checksums`],
+/// using the same merge operator.
+pub struct MergeTable<K, M> {
+    _m: PhantomData<M>,
+    db: Db<K>,
+}
+
+impl<K, M> MergeTable<K, M>
+where
+    K: Debug + DeserializeOwned + Serialize,
+    M: MergeSerde,
+{
+    pub fn new(db: Db<K>) -> Self {
+        Self {
+            _m: PhantomData,
+            db,
+        }
+    }
+
+    /// Returns true if the table contains a value for the specified key.
+    pub fn contains_key(&self, key: &K) -> Result<bool> {
+        self.db.contains_key(key)
+    }
+
+    /// Removes a key from the table.
+    pub fn delete(&self, key: &K) -> Result<()> {
+        self.db.delete(key)
+    }
+
+    /// Returns the value corresponding to the key, with all the pending merges applied.
+    pub fn get(&self, key: &K) -> Result<Option<M::Value>> {
+        match self.db.get(key)? {
+            Some(v) => Ok(Some(v.to_inner()?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn put(&self, key: &K, value: &M::Value) -> Result<()> {
+        self.db.put(key, value)
+    }
+
+    /// Records an update that will be merged with the existing value by RocksDB.
+    pub fn update(&self, key: &K, update: &M::Update) -> Result<()> {
+        self.db.merge(key, update)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Counter, CounterTable, Db};
+
+    #[test]
+    fn counter_with_checksums() {
+        let db = Db::<u32>::open_temporary_with(|path| {
+            Db::open_with_merge_and_checksums::<Counter, _>(path)
+        })
+        .unwrap();
+
+        let table = CounterTable::new(db);
+
+        table.put(&1, 10).unwrap();
+        assert_eq!(table.incr(&1, 5).unwrap(), 15);
+        assert_eq!(table.incr(&2, 3).unwrap(), 3);
+        assert_eq!(table.incr(&2, 3).unwrap(), 6);
+        assert_eq!(table.get(&1).unwrap(), 15);
+    }
+
+    #[test]
+    #[should_panic]
+    fn checksums_must_match_merge_operator() {
+        let db =
+            Db::<u32>::open_temporary_with(|path| Db::open_with_merge::<Counter, _>(path)).unwrap();
+
+        let _ = db.with_value_checksums(true);
+    }
+}
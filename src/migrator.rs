@@ -0,0 +1,268 @@
+use crate::{key_ser::key_to_bytes, Db, Error, IteratorMode, RawBatch, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{borrow::Cow, cmp::Ordering, fmt::Debug, mem::replace};
+use tracing::trace_span;
+
+const BATCH_LEN: usize = 1000;
+const MIGRATION_PROGRESS: &str = "schema_migration";
+const SCHEMA_VERSION: &str = "schema_version";
+
+type Step<'a> = Box<dyn Fn(&[u8]) -> Result<Vec<u8>> + 'a>;
+
+/// Migrates the serialized values of a database from one schema version to the next.
+///
+/// The schema version is stored in the reserved meta key of the database. A database without
+/// a stored version is at version 1, and each registered step migrates a value to the next version.
+///
+/// Values can be migrated lazily on read with [`Migrator::get`] or eagerly for the whole database
+/// with [`Migrator::run`], which should be called before writing values with the new schema.
+pub struct Migrator<'a, K> {
+    db: &'a Db<K>,
+    steps: Vec<Step<'a>>,
+}
+
+/// The progress of an interrupted [`Migrator::run`], written with each of its batches.
+///
+/// It applies while its version is greater than the stored version.
+#[derive(Deserialize, Serialize)]
+struct Progress {
+    /// The serialized key of the last migrated value.
+    last_key: Vec<u8>,
+    version: u32,
+}
+
+impl<'a, K> Migrator<'a, K>
+where
+    K: Debug + DeserializeOwned + Serialize,
+{
+    pub fn new(db: &'a Db<K>) -> Self {
+        Self {
+            db,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Returns the value corresponding to the key, migrated to the latest version.
+    pub fn get<V>(&self, key: &K) -> Result<Option<V>>
+    where
+        V: DeserializeOwned,
+    {
+        let version = self.stored_version()?;
+        let progress = self.progress(version)?;
+
+        match self.db.get(key)? {
+            Some(value) => {
+                let version = self.key_version(version, &progress, &key_to_bytes(key)?);
+                let bytes = self.migrate(version, value.payload()?)?;
+                Ok(Some(crate::deserialize_from_bytes(&bytes)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the version of the value stored under the serialized `key`.
+    fn key_version(&self, stored: u32, progress: &Option<Progress>, key: &[u8]) -> u32 {
+        match progress {
+            Some(p) if (self.db.compare())(key, &p.last_key) != Ordering::Greater => p.version,
+            _ => stored,
+        }
+    }
+
+    /// Returns the version reached once all the steps are applied.
+    #[inline]
+    pub fn latest_version(&self) -> u32 {
+        self.steps.len() as u32 + 1
+    }
+
+    fn migrate<'b>(&self, version: u32, bytes: &'b [u8]) -> Result<Cow<'b, [u8]>> {
+        let mut bytes = Cow::Borrowed(bytes);
+
+        for step in &self.steps[version as usize - 1..] {
+            bytes = Cow::Owned(step(&bytes)?);
+        }
+
+        Ok(bytes)
+    }
+
+    /// Migrates all the values to the latest version, in batches of 1000 values.
+    ///
+    /// Returns the number of migrated values. An interrupted migration is resumed by the next
+    /// run with the same steps, after the last value migrated.
+    pub fn run(&self) -> Result<usize> {
+        let span = trace_span!(
+            "migrate",
+            db.name = self.db.name(),
+            db.statement = format!("latest_version = {}", self.latest_version()).as_str(),
+            db.system = "rocksdb",
+        );
+        let _enter = span.enter();
+
+        let version = self.stored_version()?;
+        let latest = self.latest_version();
+
+        if version == latest {
+            return Ok(0);
+        }
+
+        let progress = self.progress(version)?;
+
+        if let Some(p) = &progress {
+            if p.version != latest {
+                return Err(Error::UnknownSchemaVersion(p.version));
+            }
+        }
+
+        let mut batch = RawBatch::new();
+        let mut count = 0;
+
+        {
+            let mut iter = self.db.iter(IteratorMode::Start)?;
+
+            if let Some(p) = &progress {
+                iter.seek_raw(&p.last_key);
+            }
+
+            while let Some(kv) = iter.next()? {
+                let key = kv.key_as_bytes()?;
+
+                if self.key_version(version, &progress, key) == latest {
+                    continue;
+                }
+
+                let bytes = self.migrate(version, kv.payload()?)?;
+                self.db.batch_put_payload(&mut batch, key, &bytes)?;
+                count += 1;
+
+                if batch.len() >= BATCH_LEN {
+                    let progress = Progress {
+                        last_key: key.to_vec(),
+                        version: latest,
+                    };
+
+                    self.db
+                        .batch_put_meta(&mut batch, MIGRATION_PROGRESS, &progress)?;
+                    self.db.write_raw(replace(&mut batch, RawBatch::new()))?;
+                }
+            }
+        }
+
+        self.db
+            .batch_put_meta(&mut batch, SCHEMA_VERSION, &latest)?;
+        self.db.write_raw(batch)?;
+        Ok(count)
+    }
+
+    /// Returns the progress of an interrupted run from the stored version.
+    fn progress(&self, stored: u32) -> Result<Option<Progress>> {
+        Ok(self
+            .db
+            .get_meta::<Progress>(MIGRATION_PROGRESS)?
+            .filter(|p| p.version > stored))
+    }
+
+    /// Adds the step migrating a serialized value from the current latest version to the next one.
+    pub fn step<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&[u8]) -> Result<Vec<u8>> + 'a,
+    {
+        self.steps.push(Box::new(f));
+        self
+    }
+
+    /// Returns the version of the values stored in the database.
+    ///
+    /// Fails with [`Error::UnknownSchemaVersion`] for a version 0 or later than the latest one.
+    pub fn stored_version(&self) -> Result<u32> {
+        let version = self.db.get_meta(SCHEMA_VERSION)?.unwrap_or(1);
+
+        if version == 0 || version > self.latest_version() {
+            Err(Error::UnknownSchemaVersion(version))
+        } else {
+            Ok(version)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Migrator, Progress, MIGRATION_PROGRESS, SCHEMA_VERSION};
+    use crate::{deserialize_from_bytes, key_ser::key_to_bytes, serialize_to_bytes, Db, Error};
+
+    fn times_ten(db: &Db<u32>) -> Migrator<u32> {
+        Migrator::new(db).step(|bytes| {
+            let v: u32 = deserialize_from_bytes(bytes)?;
+            serialize_to_bytes(&(u64::from(v) * 10))
+        })
+    }
+
+    #[test]
+    fn migrate_with_checksums() {
+        let db = Db::<u32>::open_temporary()
+            .unwrap()
+            .with_value_checksums(true);
+
+        db.put(&1, &5u32).unwrap();
+
+        let migrator = times_ten(&db);
+
+        assert_eq!(migrator.get::<u64>(&1).unwrap(), Some(50));
+        assert_eq!(migrator.run().unwrap(), 1);
+        assert_eq!(db.get(&1).unwrap().unwrap().to_inner::<u64>().unwrap(), 50);
+    }
+
+    #[test]
+    fn resume_interrupted_run() {
+        let db = Db::<u32>::open_temporary().unwrap();
+
+        for key in 0..1500 {
+            db.put(&key, &key).unwrap();
+        }
+
+        // a run interrupted after its first batch.
+        for key in 0..1000 {
+            db.put(&key, &(u64::from(key) * 10)).unwrap();
+        }
+
+        let progress = Progress {
+            last_key: key_to_bytes(&999u32).unwrap(),
+            version: 2,
+        };
+
+        db.put_meta(MIGRATION_PROGRESS, &progress).unwrap();
+
+        let migrator = times_ten(&db);
+        assert_eq!(migrator.get::<u64>(&5).unwrap(), Some(50));
+        assert_eq!(migrator.get::<u64>(&1200).unwrap(), Some(12000));
+        assert_eq!(migrator.run().unwrap(), 500);
+
+        for key in [5, 999, 1000, 1499].iter() {
+            let value = db.get(key).unwrap().unwrap().to_inner::<u64>().unwrap();
+            assert_eq!(value, u64::from(*key) * 10);
+        }
+
+        assert_eq!(migrator.stored_version().unwrap(), 2);
+        assert_eq!(migrator.run().unwrap(), 0);
+    }
+
+    #[test]
+    fn run_in_batches() {
+        let db = Db::<u32>::open_temporary().unwrap();
+
+        for key in 0..2500 {
+            db.put(&key, &key).unwrap();
+        }
+
+        let migrator = times_ten(&db);
+        assert_eq!(migrator.run().unwrap(), 2500);
+        assert_eq!(migrator.get::<u64>(&2499).unwrap(), Some(24990));
+    }
+
+    #[test]
+    fn version_zero_rejected() {
+        let db = Db::<u32>::open_temporary().unwrap();
+        db.put_meta(SCHEMA_VERSION, &0u32).unwrap();
+
+        let e = times_ten(&db).stored_version().unwrap_err();
+        assert!(matches!(e.root(), Error::UnknownSchemaVersion(0)));
+    }
+}
@@ -1,3 +1,84 @@
+/// The smallest value of a type, used to seek to the start of a section.
+///
+/// Can be derived with the `derive` feature for structs where every field implements `MinValue`.
 pub trait MinValue {
     fn min_value() -> Self;
 }
+
+macro_rules! impl_min_value {
+    ($($t:ty => $min:expr),* $(,)?) => {
+        $(
+            impl MinValue for $t {
+                #[inline]
+                fn min_value() -> Self {
+                    $min
+                }
+            }
+        )*
+    };
+}
+
+impl_min_value! {
+    bool => false,
+    char => '\0',
+    i8 => i8::MIN,
+    i16 => i16::MIN,
+    i32 => i32::MIN,
+    i64 => i64::MIN,
+    i128 => i128::MIN,
+    isize => isize::MIN,
+    u8 => u8::MIN,
+    u16 => u16::MIN,
+    u32 => u32::MIN,
+    u64 => u64::MIN,
+    u128 => u128::MIN,
+    usize => usize::MIN,
+    String => String::new(),
+    () => (),
+}
+
+impl<T> MinValue for Option<T> {
+    #[inline]
+    fn min_value() -> Self {
+        None
+    }
+}
+
+impl<T> MinValue for Vec<T> {
+    #[inline]
+    fn min_value() -> Self {
+        Vec::new()
+    }
+}
+
+macro_rules! impl_min_value_tuple {
+    ($($t:ident),+) => {
+        impl<$($t: MinValue),+> MinValue for ($($t,)+) {
+            #[inline]
+            fn min_value() -> Self {
+                ($($t::min_value(),)+)
+            }
+        }
+    };
+}
+
+impl_min_value_tuple!(A);
+impl_min_value_tuple!(A, B);
+impl_min_value_tuple!(A, B, C);
+impl_min_value_tuple!(A, B, C, D);
+
+#[cfg(feature = "uuid")]
+impl MinValue for uuid::Uuid {
+    #[inline]
+    fn min_value() -> Self {
+        uuid::Uuid::nil()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl_min_value! {
+    chrono::DateTime<chrono::Utc> => chrono::DateTime::<chrono::Utc>::MIN_UTC,
+    chrono::NaiveDate => chrono::NaiveDate::MIN,
+    chrono::NaiveDateTime => chrono::NaiveDate::MIN.and_hms_opt(0, 0, 0).unwrap(),
+    chrono::NaiveTime => chrono::NaiveTime::MIN,
+}
@@ -0,0 +1,107 @@
+use crate::{Db, DecodedIter, Direction, IteratorMode, RawBatch, Result};
+use serde::{Deserialize, Serialize};
+use std::{fmt::Debug, marker::PhantomData, sync::Mutex};
+
+/// A table holding many values per key, in the order they were appended.
+///
+/// Each value is stored under a `(K, seq)` key, so appending or removing a value
+/// does not rewrite the other values of the key.
+pub struct MultiTable<K, V> {
+    _v: PhantomData<V>,
+    append_lock: Mutex<()>,
+    db: Db<(K, u64)>,
+}
+
+impl<K, V> MultiTable<K, V>
+where
+    K: Clone + Debug + for<'de> Deserialize<'de> + PartialEq + Serialize,
+    V: for<'de> Deserialize<'de> + Serialize,
+{
+    pub fn new(db: Db<(K, u64)>) -> Self {
+        Self {
+            _v: PhantomData,
+            append_lock: Mutex::new(()),
+            db,
+        }
+    }
+
+    /// Adds a value after the existing values of the key, returning its sequence number.
+    ///
+    /// Appends are serialized within this table; other tables must not append to the same database.
+    pub fn append(&self, key: &K, value: &V) -> Result<u64> {
+        let _guard = self.append_lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        let seq = match self.last_seq(key)? {
+            Some(seq) => seq + 1,
+            None => 0,
+        };
+
+        self.db.put(&(key.clone(), seq), value)?;
+        Ok(seq)
+    }
+
+    /// Returns true if the key has at least one value.
+    pub fn contains_key(&self, key: &K) -> Result<bool> {
+        Ok(self.db.iter_prefix(key)?.next()?.is_some())
+    }
+
+    /// Removes all the values of the key.
+    pub fn delete(&self, key: &K) -> Result<()> {
+        self.db.delete_prefix(key)
+    }
+
+    /// Returns all the values of the key, in the order they were appended.
+    pub fn get_all(&self, key: &K) -> Result<Vec<V>> {
+        self.iter(key)?.map(|r| r.map(|(_, v)| v)).collect()
+    }
+
+    /// Iterates the values of the key with their sequence numbers, in the order they were appended.
+    pub fn iter(&self, key: &K) -> Result<DecodedIter<(K, u64), V>> {
+        Ok(DecodedIter::new(self.db.iter_prefix(key)?))
+    }
+
+    fn last_seq(&self, key: &K) -> Result<Option<u64>> {
+        let mut iter = self.db.iter(IteratorMode::From(
+            (key.clone(), u64::MAX),
+            Direction::Reverse,
+        ))?;
+
+        Ok(match iter.next()? {
+            Some(kv) => {
+                let (k, seq) = kv.key()?;
+                if &k == key {
+                    Some(seq)
+                } else {
+                    None
+                }
+            }
+            None => None,
+        })
+    }
+
+    /// Removes the values of the key matching the predicate, returning the number of values removed.
+    pub fn remove_where<F>(&self, key: &K, mut pred: F) -> Result<usize>
+    where
+        F: FnMut(&V) -> bool,
+    {
+        let mut batch = RawBatch::new();
+        let mut count = 0;
+
+        {
+            let mut iter = self.db.iter_prefix(key)?;
+
+            while let Some(kv) = iter.next()? {
+                if pred(&kv.value()?) {
+                    self.db.batch_delete(&mut batch, &kv.key()?)?;
+                    count += 1;
+                }
+            }
+        }
+
+        if count > 0 {
+            self.db.write_raw(batch)?;
+        }
+
+        Ok(count)
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{key_ser::key_to_bytes, Db, DbValue, DecodedIter, RawBatch, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+
+impl<T, K> Db<(T, K)>
+where
+    K: Debug + for<'de> Deserialize<'de> + Serialize,
+    T: Debug + for<'de> Deserialize<'de> + Serialize,
+{
+    /// Returns a view on the keys of the tenant `tenant`, stored as `(tenant, key)`.
+    pub fn namespace(&self, tenant: T) -> NamespacedDb<T, K> {
+        NamespacedDb {
+            db: self.clone(),
+            tenant,
+        }
+    }
+
+    /// Removes all the keys of a tenant with a range deletion.
+    pub fn drop_namespace(&self, tenant: &T) -> Result<()> {
+        self.delete_prefix(tenant)
+    }
+}
+
+/// The keys of a tenant in a multi-tenant database, created by [`Db::namespace`].
+///
+/// The keys are prefixed by the tenant, so the keys of a tenant are contiguous and in order.
+pub struct NamespacedDb<T, K> {
+    db: Db<(T, K)>,
+    tenant: T,
+}
+
+impl<T, K> NamespacedDb<T, K>
+where
+    K: Debug + for<'de> Deserialize<'de> + Serialize,
+    T: Debug + for<'de> Deserialize<'de> + Serialize,
+{
+    /// Removes all the keys of the tenant.
+    pub fn clear(&self) -> Result<()> {
+        self.db.drop_namespace(&self.tenant)
+    }
+
+    pub fn contains_key(&self, key: &K) -> Result<bool> {
+        Ok(self.get(key)?.is_some())
+    }
+
+    pub fn delete(&self, key: &K) -> Result<()> {
+        let mut batch = RawBatch::new();
+        self.db
+            .batch_delete_raw(&mut batch, &self.key_bytes(key)?)?;
+        self.db.write_raw(batch)
+    }
+
+    pub fn get(&self, key: &K) -> Result<Option<DbValue>> {
+        self.db.get_with(&(&self.tenant, key))
+    }
+
+    /// Iterates the keys / values of the tenant, in key order.
+    pub fn iter<V>(&self) -> Result<NamespaceIter<T, K, V>>
+    where
+        V: for<'de> Deserialize<'de>,
+    {
+        Ok(NamespaceIter {
+            iter: DecodedIter::new(self.db.iter_prefix(&self.tenant)?),
+        })
+    }
+
+    fn key_bytes(&self, key: &K) -> Result<Vec<u8>> {
+        key_to_bytes(&(&self.tenant, key))
+    }
+
+    pub fn put<V>(&self, key: &K, value: &V) -> Result<()>
+    where
+        V: Serialize,
+    {
+        let mut batch = RawBatch::new();
+        self.db
+            .batch_put_value(&mut batch, &self.key_bytes(key)?, value)?;
+        self.db.write_raw(batch)
+    }
+
+    #[inline]
+    pub fn tenant(&self) -> &T {
+        &self.tenant
+    }
+}
+
+/// An iterator over the keys / values of a [`NamespacedDb`], in key order.
+pub struct NamespaceIter<'a, T, K, V> {
+    iter: DecodedIter<'a, (T, K), V>,
+}
+
+impl<'a, T, K, V> Iterator for NamespaceIter<'a, T, K, V>
+where
+    K: for<'de> Deserialize<'de>,
+    T: for<'de> Deserialize<'de>,
+    V: for<'de> Deserialize<'de>,
+{
+    type Item = Result<(K, V)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.iter.next()?.map(|((_, key), value)| (key, value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Db, Result};
+
+    #[test]
+    fn namespace_with_checksums() {
+        let db = Db::<(u32, u32)>::open_temporary()
+            .unwrap()
+            .with_value_checksums(true);
+
+        let ns = db.namespace(1);
+        ns.put(&2, &"a".to_string()).unwrap();
+
+        let value = ns.get(&2).unwrap().unwrap();
+        assert_eq!(value.to_inner::<String>().unwrap(), "a");
+
+        let items = ns.iter::<String>().unwrap().collect::<Result<Vec<_>>>();
+        assert_eq!(items.unwrap(), vec![(2, "a".to_string())]);
+    }
+}
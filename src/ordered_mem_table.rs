@@ -0,0 +1,166 @@
+use crate::{Db, IterOptions, IteratorMode, Result, UpdateFrom};
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Borrow,
+    collections::{
+        btree_map::{Iter, Range},
+        BTreeMap,
+    },
+    fmt::Debug,
+    ops::RangeBounds,
+};
+
+/// A fully in-memory loaded table, ordered by key.
+///
+/// The iteration order matches the order of the database when the [`Ord`] of `K` agrees with
+/// the order of its encoding, as for integers, strings and tuples of them.
+pub struct OrderedMemTable<K, V> {
+    db: Db<K>,
+    map: BTreeMap<K, V>,
+}
+
+impl<K, V> OrderedMemTable<K, V>
+where
+    K: for<'de> Deserialize<'de> + Debug + Ord + Serialize,
+    V: for<'de> Deserialize<'de> + Serialize,
+{
+    pub fn new(db: Db<K>) -> Result<Self> {
+        let map = load_map(&db)?;
+        Ok(Self { db, map })
+    }
+
+    /// Returns true if the table contains a value for the specified key.
+    pub fn contains_key<Q: ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord,
+    {
+        self.map.contains_key(key)
+    }
+
+    /// Removes a key from the table, returning the value at the key if the key was previously in the map.
+    pub fn delete(&mut self, key: &K) -> Result<Option<V>> {
+        Ok(if self.map.contains_key(key) {
+            self.db.delete(key)?;
+            self.map.remove(key)
+        } else {
+            None
+        })
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    pub fn get<Q: ?Sized>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord,
+    {
+        self.map.get(key)
+    }
+
+    pub fn get_or_init<F>(&mut self, key: &K, f: F) -> Result<&V>
+    where
+        F: FnOnce() -> V,
+        K: Clone,
+    {
+        if !self.map.contains_key(key) {
+            let v = f();
+            self.db.put(key, &v)?;
+            self.map.insert(key.clone(), v);
+        }
+
+        Ok(self.map.get(key).unwrap())
+    }
+
+    pub fn get_or_default(&mut self, key: &K) -> Result<&V>
+    where
+        K: Clone,
+        V: Default,
+    {
+        self.get_or_init(key, Default::default)
+    }
+
+    /// Iterates the entries in ascending order of key.
+    pub fn iter(&self) -> Iter<K, V> {
+        self.map.iter()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the number of entries in the table.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn put(&mut self, key: &K, value: V) -> Result<()>
+    where
+        K: Clone,
+    {
+        self.db.put(key, &value)?;
+
+        match self.map.get_mut(key) {
+            Some(v) => {
+                *v = value;
+            }
+            None => {
+                self.map.insert(key.clone(), value);
+            }
+        };
+
+        Ok(())
+    }
+
+    /// Iterates the entries of a range of keys, in ascending order.
+    pub fn range<Q: ?Sized, R>(&self, range: R) -> Range<K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord,
+        R: RangeBounds<Q>,
+    {
+        self.map.range(range)
+    }
+
+    /// Re-reads the database, replacing all the entries.
+    ///
+    /// If an error occurs, the table is left untouched.
+    pub fn reload(&mut self) -> Result<()> {
+        self.map = load_map(&self.db)?;
+        Ok(())
+    }
+
+    pub fn update<U>(&mut self, key: K, update: U) -> Result<()>
+    where
+        U: UpdateFrom<V>,
+    {
+        let v = update.update_from(self.map.remove(&key));
+        let r = self.db.put(&key, &v);
+
+        if r.is_err() {
+            if let Some(v) = self.db.get(&key)? {
+                self.map.insert(key, v.to_inner()?);
+            }
+        } else {
+            self.map.insert(key, v);
+        }
+
+        r
+    }
+}
+
+fn load_map<K, V>(db: &Db<K>) -> Result<BTreeMap<K, V>>
+where
+    K: for<'de> Deserialize<'de> + Debug + Ord + Serialize,
+    V: for<'de> Deserialize<'de> + Serialize,
+{
+    let mut iter = db.iter_opt(IteratorMode::Start, &IterOptions::bulk_scan())?;
+    let mut map = BTreeMap::new();
+
+    while let Some(kv) = iter.next()? {
+        map.insert(kv.key()?, kv.value()?);
+    }
+
+    Ok(map)
+}
@@ -0,0 +1,146 @@
+use crate::{aged::AgeIndex, section_lru_table::load_map, stats, Aged, Db, MinValue, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Borrow,
+    collections::{btree_map::Range, hash_map::RandomState, BTreeMap, HashMap},
+    fmt::Debug,
+    hash::Hash,
+    ops::RangeBounds,
+};
+
+/// A [`SectionLruTable`](crate::SectionLruTable) keeping the records of each section ordered by key.
+///
+/// The records of a section can be scanned by range, in the order of the database when the
+/// [`Ord`] of `K` agrees with the order of its encoding, as for integers, strings and tuples of them.
+pub struct OrderedSectionLruTable<S, K, V> {
+    ages: AgeIndex<S>,
+    db: Db<(S, K)>,
+    map: HashMap<S, Aged<BTreeMap<K, V>>, RandomState>,
+}
+
+impl<S, K, V> OrderedSectionLruTable<S, K, V>
+where
+    S: for<'de> Deserialize<'de> + Clone + Debug + Eq + Hash + Serialize,
+    K: for<'de> Deserialize<'de> + Debug + MinValue + Ord + Serialize,
+    V: for<'de> Deserialize<'de> + Serialize,
+{
+    pub fn with_capacity(db: Db<(S, K)>, capacity: usize) -> Self {
+        assert!(capacity > 0);
+
+        Self {
+            ages: AgeIndex::new(),
+            db,
+            map: HashMap::with_capacity(capacity),
+        }
+    }
+
+    pub fn contains_key(&self, section: S, key: &K) -> Result<bool>
+    where
+        K: Clone,
+    {
+        match self.map.get(&section) {
+            Some(section) => Ok(section.value.contains_key(key)),
+            None => self.db.contains_key(&(section, key.clone())),
+        }
+    }
+
+    pub fn delete(&mut self, section: S, key: &K) -> Result<()>
+    where
+        K: Clone,
+    {
+        match self.map.get_mut(&section) {
+            Some(aged) => {
+                if aged.value.contains_key(key) {
+                    self.db.delete(&(section.clone(), key.clone()))?;
+                    aged.value.remove(key);
+                }
+            }
+            None => self.db.delete(&(section, key.clone()))?,
+        }
+
+        Ok(())
+    }
+
+    /// Removes a whole section from the database and from memory.
+    pub fn delete_section(&mut self, section: S) -> Result<()> {
+        self.db.delete_prefix(&section)?;
+
+        if let Some(aged) = self.map.remove(&section) {
+            self.ages.remove(aged.age);
+        }
+
+        Ok(())
+    }
+
+    fn ensure_capacity(&mut self) {
+        if self.map.capacity() == self.map.len() {
+            if let Some(section) = self.ages.oldest().cloned() {
+                if let Some(aged) = self.map.remove(&section) {
+                    self.ages.remove(aged.age);
+                    stats::eviction(self.db.name());
+                }
+            }
+        }
+    }
+
+    fn ensure_section_loaded(&mut self, section: S) -> Result<&mut BTreeMap<K, V>> {
+        let hit = self.map.contains_key(&section);
+        stats::cache_lookup(self.db.name(), hit);
+
+        if !hit {
+            self.ensure_capacity();
+
+            self.map.insert(
+                section.clone(),
+                Aged {
+                    age: 0,
+                    value: load_map(section.clone(), &self.db)?,
+                },
+            );
+        }
+
+        let aged = self.map.get_mut(&section).unwrap();
+        self.ages.touch(&section, &mut aged.age);
+        Ok(&mut aged.value)
+    }
+
+    pub fn get(&mut self, section: S, key: &K) -> Result<Option<&V>> {
+        Ok(self.ensure_section_loaded(section)?.get(key))
+    }
+
+    pub fn get_section(&mut self, section: S) -> Result<&BTreeMap<K, V>> {
+        Ok(&*self.ensure_section_loaded(section)?)
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the number of sections kept in memory.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn put(&mut self, section: S, key: &K, value: V) -> Result<()>
+    where
+        K: Clone,
+    {
+        self.db.put(&(section.clone(), key.clone()), &value)?;
+
+        self.ensure_section_loaded(section)?
+            .insert(key.clone(), value);
+        Ok(())
+    }
+
+    /// Iterates the records of a section within a range of keys, in ascending order.
+    pub fn range<Q: ?Sized, R>(&mut self, section: S, range: R) -> Result<Range<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord,
+        R: RangeBounds<Q>,
+    {
+        Ok(self.ensure_section_loaded(section)?.range(range))
+    }
+}
@@ -0,0 +1,113 @@
+use crate::Db;
+use rocksdb::perf::{set_perf_stats, PerfContext, PerfMetric, PerfStatsLevel};
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use tracing::{field, trace_span};
+
+/// The RocksDB perf context counters of the operations run on a thread, see [`PerfGuard`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PerfCounters {
+    pub block_cache_hits: u64,
+    /// The number of blocks read from the disk, or from the OS page cache.
+    pub block_reads: u64,
+    pub block_read_bytes: u64,
+    pub block_read_nanos: u64,
+    pub bloom_memtable_hits: u64,
+    pub bloom_memtable_misses: u64,
+    pub bloom_sst_hits: u64,
+    /// The number of SST files read because their bloom filter could not exclude the key.
+    pub bloom_sst_misses: u64,
+    /// The number of tombstones skipped, a high value hinting at a range that needs compaction.
+    pub deletes_skipped: u64,
+    pub get_from_memtable: u64,
+    pub keys_skipped: u64,
+}
+
+/// Captures the RocksDB perf context counters of the operations run on the current thread
+/// while the guard is alive, such as the blocks read by a slow `get`.
+///
+/// Enabling the perf context slows down the operations, so the guard is meant for
+/// investigations rather than every call. The guards must not be nested: the first one
+/// dropped disables the counters for the thread.
+pub struct PerfGuard {
+    ctx: PerfContext,
+}
+
+impl PerfGuard {
+    /// Enables the counters of the current thread and resets them.
+    pub fn start() -> Self {
+        set_perf_stats(PerfStatsLevel::EnableTime);
+
+        let mut ctx = PerfContext::default();
+        ctx.reset();
+
+        Self { ctx }
+    }
+
+    /// Returns the counters accumulated since the guard started.
+    pub fn counters(&self) -> PerfCounters {
+        let m = |metric| self.ctx.metric(metric);
+
+        PerfCounters {
+            block_cache_hits: m(PerfMetric::BlockCacheHitCount),
+            block_reads: m(PerfMetric::BlockReadCount),
+            block_read_bytes: m(PerfMetric::BlockReadByte),
+            block_read_nanos: m(PerfMetric::BlockReadTime),
+            bloom_memtable_hits: m(PerfMetric::BloomMemtableHitCount),
+            bloom_memtable_misses: m(PerfMetric::BloomMemtableMissCount),
+            bloom_sst_hits: m(PerfMetric::BloomSstHitCount),
+            bloom_sst_misses: m(PerfMetric::BloomSstMissCount),
+            deletes_skipped: m(PerfMetric::InternalDeleteSkippedCount),
+            get_from_memtable: m(PerfMetric::GetFromMemtableCount),
+            keys_skipped: m(PerfMetric::InternalKeySkippedCount),
+        }
+    }
+
+    /// Returns the full report of RocksDB, listing the non-zero counters.
+    pub fn report(&self) -> String {
+        self.ctx.report(true)
+    }
+}
+
+impl Drop for PerfGuard {
+    fn drop(&mut self) {
+        set_perf_stats(PerfStatsLevel::Disable);
+    }
+}
+
+impl<K> Db<K>
+where
+    K: Debug + for<'de> Deserialize<'de> + Serialize,
+{
+    /// Runs `f` with the perf context enabled, returning its result with the counters of the
+    /// operations it ran on this thread.
+    ///
+    /// The counters are also recorded in a `perf` span wrapping `f`.
+    pub fn with_perf<F, R>(&self, f: F) -> (R, PerfCounters)
+    where
+        F: FnOnce() -> R,
+    {
+        let span = trace_span!(
+            "perf",
+            db.name = self.name(),
+            perf.block_cache_hits = field::Empty,
+            perf.block_read_bytes = field::Empty,
+            perf.block_reads = field::Empty,
+            perf.bloom_sst_misses = field::Empty,
+            perf.keys_skipped = field::Empty,
+        );
+        let _enter = span.enter();
+
+        let guard = PerfGuard::start();
+        let result = f();
+        let counters = guard.counters();
+
+        span.record("perf.block_cache_hits", &counters.block_cache_hits);
+        span.record("perf.block_read_bytes", &counters.block_read_bytes);
+        span.record("perf.block_reads", &counters.block_reads);
+        span.record("perf.bloom_sst_misses", &counters.bloom_sst_misses);
+        span.record("perf.keys_skipped", &counters.keys_skipped);
+
+        (result, counters)
+    }
+}
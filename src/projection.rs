@@ -0,0 +1,405 @@
+use crate::{
+    binary_ser::deserialize_seed, db::log_err, deserialize_from_bytes, serialize_to_bytes, Db,
+    Result,
+};
+use serde::{
+    de::{self, value::BorrowedStrDeserializer, DeserializeSeed, MapAccess, Visitor},
+    forward_to_deserialize_any,
+    ser::{self, Impossible, SerializeStruct},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::fmt::Debug;
+
+type FieldResult<T> = std::result::Result<T, bincode::Error>;
+
+/// A struct stored field by field, so a single field can be read with [`Db::get_field`]
+/// without decoding the others.
+///
+/// Each field is serialized on its own with its name, which costs a few bytes per field but
+/// pays off for wide rows read partially. The fields unknown to the struct are ignored when
+/// the whole value is read, so fields can be added or removed over time.
+///
+/// Only the structs are supported, without `#[serde(flatten)]`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Projection<V>(pub V);
+
+impl<V> Projection<V> {
+    #[inline]
+    pub fn into_inner(self) -> V {
+        self.0
+    }
+}
+
+impl<V> From<V> for Projection<V> {
+    #[inline]
+    fn from(value: V) -> Self {
+        Self(value)
+    }
+}
+
+impl<V: Serialize> Serialize for Projection<V> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0
+            .serialize(FieldsSerializer)
+            .map_err(ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+impl<'de, V> Deserialize<'de> for Projection<V>
+where
+    V: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let fields = Vec::<(&'de str, &'de [u8])>::deserialize(deserializer)?;
+
+        V::deserialize(FieldsDeserializer { fields })
+            .map(Self)
+            .map_err(de::Error::custom)
+    }
+}
+
+impl<K> Db<K>
+where
+    K: Debug + for<'de> Deserialize<'de> + Serialize,
+{
+    /// Reads a single field of a value stored as a [`Projection`], skipping the other fields.
+    ///
+    /// Returns `None` if the key or the field is missing.
+    pub fn get_field<F>(&self, key: &K, field: &str) -> Result<Option<F>>
+    where
+        F: for<'de> Deserialize<'de>,
+    {
+        let value = match self.get(key)? {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        let fields: Vec<(&str, &[u8])> =
+            deserialize_from_bytes(value.payload()?).map_err(|e| log_err(e, self.name()))?;
+
+        match fields.into_iter().find(|(name, _)| *name == field) {
+            Some((_, bytes)) => Ok(Some(
+                deserialize_from_bytes(bytes).map_err(|e| log_err(e, self.name()))?,
+            )),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Serializes each field of a struct on its own.
+struct FieldsSerializer;
+
+struct Fields(Vec<(&'static str, Vec<u8>)>);
+
+fn not_a_struct<T>() -> FieldResult<T> {
+    Err(ser::Error::custom("a projection must be a struct"))
+}
+
+impl Serializer for FieldsSerializer {
+    type Ok = Vec<(&'static str, Vec<u8>)>;
+    type Error = bincode::Error;
+    type SerializeSeq = Impossible<Self::Ok, Self::Error>;
+    type SerializeTuple = Impossible<Self::Ok, Self::Error>;
+    type SerializeTupleStruct = Impossible<Self::Ok, Self::Error>;
+    type SerializeTupleVariant = Impossible<Self::Ok, Self::Error>;
+    type SerializeMap = Impossible<Self::Ok, Self::Error>;
+    type SerializeStruct = Fields;
+    type SerializeStructVariant = Impossible<Self::Ok, Self::Error>;
+
+    fn serialize_bool(self, _v: bool) -> FieldResult<Self::Ok> {
+        not_a_struct()
+    }
+
+    fn serialize_i8(self, _v: i8) -> FieldResult<Self::Ok> {
+        not_a_struct()
+    }
+
+    fn serialize_i16(self, _v: i16) -> FieldResult<Self::Ok> {
+        not_a_struct()
+    }
+
+    fn serialize_i32(self, _v: i32) -> FieldResult<Self::Ok> {
+        not_a_struct()
+    }
+
+    fn serialize_i64(self, _v: i64) -> FieldResult<Self::Ok> {
+        not_a_struct()
+    }
+
+    fn serialize_u8(self, _v: u8) -> FieldResult<Self::Ok> {
+        not_a_struct()
+    }
+
+    fn serialize_u16(self, _v: u16) -> FieldResult<Self::Ok> {
+        not_a_struct()
+    }
+
+    fn serialize_u32(self, _v: u32) -> FieldResult<Self::Ok> {
+        not_a_struct()
+    }
+
+    fn serialize_u64(self, _v: u64) -> FieldResult<Self::Ok> {
+        not_a_struct()
+    }
+
+    fn serialize_f32(self, _v: f32) -> FieldResult<Self::Ok> {
+        not_a_struct()
+    }
+
+    fn serialize_f64(self, _v: f64) -> FieldResult<Self::Ok> {
+        not_a_struct()
+    }
+
+    fn serialize_char(self, _v: char) -> FieldResult<Self::Ok> {
+        not_a_struct()
+    }
+
+    fn serialize_str(self, _v: &str) -> FieldResult<Self::Ok> {
+        not_a_struct()
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> FieldResult<Self::Ok> {
+        not_a_struct()
+    }
+
+    fn serialize_none(self) -> FieldResult<Self::Ok> {
+        not_a_struct()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> FieldResult<Self::Ok> {
+        not_a_struct()
+    }
+
+    fn serialize_unit(self) -> FieldResult<Self::Ok> {
+        not_a_struct()
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> FieldResult<Self::Ok> {
+        Ok(Vec::new())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> FieldResult<Self::Ok> {
+        not_a_struct()
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> FieldResult<Self::Ok> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> FieldResult<Self::Ok> {
+        not_a_struct()
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> FieldResult<Self::SerializeSeq> {
+        not_a_struct()
+    }
+
+    fn serialize_tuple(self, _len: usize) -> FieldResult<Self::SerializeTuple> {
+        not_a_struct()
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> FieldResult<Self::SerializeTupleStruct> {
+        not_a_struct()
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> FieldResult<Self::SerializeTupleVariant> {
+        not_a_struct()
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> FieldResult<Self::SerializeMap> {
+        not_a_struct()
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> FieldResult<Self::SerializeStruct> {
+        Ok(Fields(Vec::with_capacity(len)))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> FieldResult<Self::SerializeStructVariant> {
+        not_a_struct()
+    }
+}
+
+impl SerializeStruct for Fields {
+    type Ok = Vec<(&'static str, Vec<u8>)>;
+    type Error = bincode::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> FieldResult<()> {
+        let bytes = serialize_to_bytes(value).map_err(ser::Error::custom)?;
+        self.0.push((key, bytes));
+        Ok(())
+    }
+
+    fn end(self) -> FieldResult<Self::Ok> {
+        Ok(self.0)
+    }
+}
+
+/// Deserializes a struct from its fields, each one serialized on its own.
+struct FieldsDeserializer<'de> {
+    fields: Vec<(&'de str, &'de [u8])>,
+}
+
+impl<'de> Deserializer<'de> for FieldsDeserializer<'de> {
+    type Error = bincode::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> FieldResult<V::Value> {
+        visitor.visit_map(FieldsAccess {
+            fields: self.fields.into_iter(),
+            known: None,
+            value: None,
+        })
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> FieldResult<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> FieldResult<V::Value> {
+        visitor.visit_map(FieldsAccess {
+            fields: self.fields.into_iter(),
+            known: Some(fields),
+            value: None,
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+struct FieldsAccess<'de> {
+    fields: std::vec::IntoIter<(&'de str, &'de [u8])>,
+    /// The fields of the struct, to skip the fields removed from it.
+    known: Option<&'static [&'static str]>,
+    value: Option<&'de [u8]>,
+}
+
+impl<'de> MapAccess<'de> for FieldsAccess<'de> {
+    type Error = bincode::Error;
+
+    fn next_key_seed<T>(&mut self, seed: T) -> FieldResult<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let known = self.known;
+
+        match self
+            .fields
+            .find(|(name, _)| known.map_or(true, |k| k.iter().any(|f| f == name)))
+        {
+            Some((name, bytes)) => {
+                self.value = Some(bytes);
+                seed.deserialize(BorrowedStrDeserializer::new(name))
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<T>(&mut self, seed: T) -> FieldResult<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let bytes = self.value.take().unwrap_or_default();
+        deserialize_seed(seed, bytes).map_err(de::Error::custom)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.fields.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Projection;
+    use crate::Db;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Row {
+        id: u32,
+        name: String,
+    }
+
+    #[test]
+    fn get_field_with_checksums() {
+        let db = Db::<u32>::open_temporary()
+            .unwrap()
+            .with_value_checksums(true);
+
+        let row = Row {
+            id: 1,
+            name: "a".to_string(),
+        };
+
+        db.put(&1, &Projection(row)).unwrap();
+
+        assert_eq!(db.get_field::<u32>(&1, "id").unwrap(), Some(1));
+        assert_eq!(
+            db.get_field::<String>(&1, "name").unwrap(),
+            Some("a".to_string())
+        );
+        assert_eq!(db.get_field::<u32>(&1, "missing").unwrap(), None);
+
+        let value = db.get(&1).unwrap().unwrap();
+        let read = value.to_inner::<Projection<Row>>().unwrap();
+        assert_eq!(read.0.name, "a");
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{Db, IteratorMode, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    marker::PhantomData,
+    sync::{Mutex, MutexGuard},
+};
+
+/// A durable FIFO queue, storing its values under increasing `u64` keys.
+///
+/// The positions of the front and the back of the queue are read on creation, then kept in
+/// memory, so the database must only be written through this table.
+pub struct QueueTable<V> {
+    _v: PhantomData<V>,
+    bounds: Mutex<Bounds>,
+    db: Db<u64>,
+}
+
+/// The key of the front value and the key following the back value.
+struct Bounds {
+    head: u64,
+    tail: u64,
+}
+
+impl<V> QueueTable<V>
+where
+    V: for<'de> Deserialize<'de> + Serialize,
+{
+    pub fn new(db: Db<u64>) -> Result<Self> {
+        let head = match db.iter(IteratorMode::Start)?.next()? {
+            Some(kv) => kv.key()?,
+            None => 0,
+        };
+
+        let tail = match db.iter(IteratorMode::End)?.next()? {
+            Some(kv) => kv.key()? + 1,
+            None => head,
+        };
+
+        Ok(Self {
+            _v: PhantomData,
+            bounds: Mutex::new(Bounds { head, tail }),
+            db,
+        })
+    }
+
+    fn bounds(&self) -> MutexGuard<Bounds> {
+        // a poisoned lock is still consistent since the bounds are updated after the db.
+        self.bounds.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Removes all the values of the queue.
+    pub fn clear(&self) -> Result<()> {
+        let mut bounds = self.bounds();
+
+        if bounds.head < bounds.tail {
+            self.db.delete_range(&bounds.head, &bounds.tail)?;
+            bounds.head = bounds.tail;
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of values in the queue.
+    pub fn len(&self) -> u64 {
+        let bounds = self.bounds();
+        bounds.tail - bounds.head
+    }
+
+    /// Returns the value at the front of the queue, without removing it.
+    pub fn peek(&self) -> Result<Option<V>> {
+        let bounds = self.bounds();
+
+        if bounds.head == bounds.tail {
+            return Ok(None);
+        }
+
+        match self.db.get(&bounds.head)? {
+            Some(v) => Ok(Some(v.to_inner()?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Removes and returns the value at the front of the queue.
+    pub fn pop_front(&self) -> Result<Option<V>> {
+        let mut bounds = self.bounds();
+
+        if bounds.head == bounds.tail {
+            return Ok(None);
+        }
+
+        let value = match self.db.get(&bounds.head)? {
+            Some(v) => Some(v.to_inner()?),
+            None => None,
+        };
+
+        self.db.delete(&bounds.head)?;
+        bounds.head += 1;
+        Ok(value)
+    }
+
+    /// Adds a value at the back of the queue, returning its key.
+    pub fn push_back(&self, value: &V) -> Result<u64> {
+        let mut bounds = self.bounds();
+        let key = bounds.tail;
+
+        self.db.put(&key, value)?;
+        bounds.tail += 1;
+        Ok(key)
+    }
+
+    /// Removes up to `count` values from the front of the queue with a single range delete,
+    /// returning the number of values removed.
+    pub fn trim_front(&self, count: u64) -> Result<u64> {
+        let mut bounds = self.bounds();
+        let count = count.min(bounds.tail - bounds.head);
+
+        if count > 0 {
+            self.db.delete_range(&bounds.head, &(bounds.head + count))?;
+            bounds.head += count;
+        }
+
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QueueTable;
+    use crate::{ChangeKind, Db};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn push_pop_order() {
+        let queue = QueueTable::new(Db::open_temporary().unwrap()).unwrap();
+
+        for value in &["a", "b", "c"] {
+            queue.push_back(&value.to_string()).unwrap();
+        }
+
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.peek().unwrap().as_deref(), Some("a"));
+        assert_eq!(queue.pop_front().unwrap().as_deref(), Some("a"));
+        assert_eq!(queue.pop_front().unwrap().as_deref(), Some("b"));
+
+        queue.push_back(&"d".to_string()).unwrap();
+        assert_eq!(queue.pop_front().unwrap().as_deref(), Some("c"));
+        assert_eq!(queue.pop_front().unwrap().as_deref(), Some("d"));
+        assert_eq!(queue.pop_front().unwrap(), None);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn trim_front_deletes_range() {
+        let db = Db::<u64>::open_temporary().unwrap();
+        let queue = QueueTable::new(db.clone()).unwrap();
+
+        for value in 0..5u32 {
+            queue.push_back(&value).unwrap();
+        }
+
+        let kinds = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&kinds);
+        db.subscribe(move |event| recorded.lock().unwrap().push(event.kind()));
+
+        assert_eq!(queue.trim_front(3).unwrap(), 3);
+        assert_eq!(*kinds.lock().unwrap(), vec![ChangeKind::DeleteRange]);
+        assert!(db.get(&2).unwrap().is_none());
+        assert_eq!(queue.pop_front().unwrap(), Some(3));
+
+        // the count is bounded by the length of the queue.
+        assert_eq!(queue.trim_front(10).unwrap(), 1);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn reopen_recovers_bounds() {
+        let db = Db::<u64>::open_temporary().unwrap();
+        let queue = QueueTable::new(db.clone()).unwrap();
+
+        for value in 0..4u32 {
+            queue.push_back(&value).unwrap();
+        }
+
+        queue.pop_front().unwrap();
+        drop(queue);
+
+        let queue = QueueTable::<u32>::new(db).unwrap();
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.push_back(&4).unwrap(), 4);
+        assert_eq!(queue.pop_front().unwrap(), Some(1));
+    }
+}
@@ -0,0 +1,36 @@
+use crate::{Db, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+
+/// A table of typed keys and values already serialized by the caller, such as protobuf messages.
+///
+/// The values are stored as is, skipping the bincode encoding of the other tables.
+pub struct RawTable<K> {
+    db: Db<K>,
+}
+
+impl<K> RawTable<K>
+where
+    K: Debug + for<'de> Deserialize<'de> + Serialize,
+{
+    pub fn new(db: Db<K>) -> Self {
+        Self { db }
+    }
+
+    pub fn contains_key(&self, key: &K) -> Result<bool> {
+        self.db.contains_key(key)
+    }
+
+    pub fn delete(&self, key: &K) -> Result<()> {
+        self.db.delete(key)
+    }
+
+    /// Returns a copy of the stored bytes of the value.
+    pub fn get(&self, key: &K) -> Result<Option<Vec<u8>>> {
+        Ok(self.db.get_raw_bytes(key)?.map(|v| v.as_bytes().to_vec()))
+    }
+
+    pub fn put(&self, key: &K, value: &[u8]) -> Result<()> {
+        self.db.put_raw(key, value)
+    }
+}
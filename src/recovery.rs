@@ -0,0 +1,54 @@
+use rocksdb::DBRecoveryMode;
+
+/// How the write-ahead log is replayed when a database is opened with
+/// [`Db::open_with_recovery`](crate::Db::open_with_recovery).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RecoveryMode {
+    /// Fails to open on any corruption of the log.
+    AbsoluteConsistency,
+    /// Recovers up to the first corrupted record, dropping the following ones. The default of RocksDB.
+    PointInTime,
+    /// Recovers all the valid records, skipping the corrupted ones. The last resort for salvaging data.
+    SkipAnyCorruptedRecords,
+    /// Ignores the incomplete records at the end of the log, left by a crash during a write.
+    TolerateCorruptedTailRecords,
+}
+
+impl From<RecoveryMode> for DBRecoveryMode {
+    fn from(mode: RecoveryMode) -> Self {
+        match mode {
+            RecoveryMode::AbsoluteConsistency => DBRecoveryMode::AbsoluteConsistency,
+            RecoveryMode::PointInTime => DBRecoveryMode::PointInTime,
+            RecoveryMode::SkipAnyCorruptedRecords => DBRecoveryMode::SkipAnyCorruptedRecord,
+            RecoveryMode::TolerateCorruptedTailRecords => {
+                DBRecoveryMode::TolerateCorruptedTailRecords
+            }
+        }
+    }
+}
+
+/// The result of [`Db::verify_checksums`](crate::Db::verify_checksums).
+#[derive(Clone, Debug, Default)]
+pub struct ChecksumReport {
+    /// The number of records read and verified.
+    pub checked: u64,
+    /// The corruption that stopped the verification, if any.
+    pub corruption: Option<CorruptedRange>,
+}
+
+impl ChecksumReport {
+    pub fn is_ok(&self) -> bool {
+        self.corruption.is_none()
+    }
+}
+
+/// A corrupted part of the database, starting after the last valid key.
+///
+/// The end of the range is unknown, since the records cannot be read past a corrupted block.
+#[derive(Clone, Debug)]
+pub struct CorruptedRange {
+    /// The serialized last valid key, `None` if the corruption is at the start of the database.
+    pub after: Option<Vec<u8>>,
+    /// The error reported by RocksDB.
+    pub message: String,
+}
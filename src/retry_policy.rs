@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+/// Configures how [`Db::rmw_with`](crate::Db::rmw_with) retries after a write conflict.
+///
+/// The delay before a retry doubles after each attempt, up to the maximum backoff.
+/// By default, 10 attempts are made, waiting from 1 ms up to 100 ms.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    initial_backoff: Duration,
+    max_attempts: u32,
+    max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the delay before the first retry.
+    pub fn initial_backoff(mut self, delay: Duration) -> Self {
+        self.initial_backoff = delay;
+        self
+    }
+
+    /// Sets the number of attempts, the first one included, before [`Error::Conflict`](crate::Error::Conflict) is returned.
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        assert!(attempts > 0);
+        self.max_attempts = attempts;
+        self
+    }
+
+    /// Sets the maximum delay between two attempts.
+    pub fn max_backoff(mut self, delay: Duration) -> Self {
+        self.max_backoff = delay;
+        self
+    }
+
+    pub(crate) fn attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Returns the delay to wait after the failed attempt `attempt`, starting at 0.
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .checked_mul(1 << attempt.min(31))
+            .map_or(self.max_backoff, |d| d.min(self.max_backoff))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(1),
+            max_attempts: 10,
+            max_backoff: Duration::from_millis(100),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use crate::{db::log_err, Db, Error, Result};
+use serde::{Deserialize, Serialize};
+use std::{any::type_name, fmt::Debug, path::Path};
+
+const SCHEMA_ID: &str = "schema_id";
+
+impl<K> Db<K>
+where
+    K: Debug + for<'de> Deserialize<'de> + Serialize,
+{
+    /// Opens the database, failing with [`Error::SchemaMismatch`] if it was written with
+    /// other key / value types than `K` and `V`.
+    ///
+    /// The types are identified by their name, which may change with the compiler version or
+    /// when a type is moved; use [`Db::open_with_schema`] for a stable identifier.
+    pub fn open_typed<V, P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_with_schema(path, &type_signature::<K, V>())
+    }
+
+    /// Opens the database, failing with [`Error::SchemaMismatch`] if it was written with
+    /// another schema id.
+    pub fn open_with_schema<P: AsRef<Path>>(path: P, schema_id: &str) -> Result<Self> {
+        let db = Self::open(path)?;
+        db.check_schema(schema_id)?;
+        Ok(db)
+    }
+
+    /// Verifies that the data was written with the schema `schema_id`, recording it if the
+    /// database has no schema id yet.
+    pub fn check_schema(&self, schema_id: &str) -> Result<()> {
+        match self.get_meta::<String>(SCHEMA_ID)? {
+            Some(found) if found != schema_id => Err(log_err(
+                Error::SchemaMismatch {
+                    expected: schema_id.to_string(),
+                    found,
+                },
+                self.name(),
+            )),
+            Some(_) => Ok(()),
+            None => self.put_meta(SCHEMA_ID, &schema_id),
+        }
+    }
+}
+
+fn type_signature<K, V>() -> String {
+    format!("{} => {}", type_name::<K>(), type_name::<V>())
+}
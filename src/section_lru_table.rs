@@ -1,16 +1,41 @@
-use super::{Aged, Db, Direction, IteratorMode, MinValue, Result};
+use super::{
+    aged::AgeIndex,
+    db::prefix_successor,
+    eviction::EvictionPolicy,
+    key_ser::{key_prefix_to_bytes, key_serialized_len, key_to_bytes},
+    lru_table::lock,
+    stats,
+    table_stats::CacheCounters,
+    transaction::PartOps,
+    Aged, Db, DecodedIter, Direction, Iter, IteratorMode, MinValue, RawBatch, Result, TableStats,
+    Transaction, UpdateFrom,
+};
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::{hash_map::RandomState, HashMap},
+    collections::{hash_map::RandomState, HashMap, HashSet},
     fmt::Debug,
     hash::{BuildHasher, Hash},
+    mem::size_of,
+    ops::{Bound, RangeBounds},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
 };
+use tracing::{field, trace_span};
 
 /// A tables that keep section of records in memory and remove the last recently used section.
+///
+/// Opening the database with [`DbBuilder::section_prefix`](crate::DbBuilder::section_prefix)
+/// makes the section loads proper prefix seeks, using the prefix bloom filters.
 pub struct SectionLruTable<S, K, V, H = RandomState> {
-    age: u64,
+    ages: AgeIndex<S>,
+    /// The maximum number of sections in memory, the pinned sections excluded.
+    capacity: usize,
+    counters: CacheCounters,
     db: Db<(S, K)>,
     map: HashMap<S, Aged<HashMap<K, V, H>>, H>,
+    /// The sections never evicted, all loaded in memory and left out of the age index.
+    pinned: HashSet<S, H>,
+    policy: Option<Box<dyn EvictionPolicy<S>>>,
 }
 
 impl<S, K, V> SectionLruTable<S, K, V, RandomState>
@@ -35,9 +60,32 @@ where
         assert!(capacity > 0);
 
         Self {
-            age: 0,
+            ages: AgeIndex::new(),
+            capacity,
+            counters: CacheCounters::default(),
             db,
             map: HashMap::with_capacity_and_hasher(capacity, hasher),
+            pinned: HashSet::default(),
+            policy: None,
+        }
+    }
+
+    /// Replaces the least recently used eviction of the sections by `policy`.
+    ///
+    /// Must be called before the table is used.
+    pub fn with_eviction_policy<P>(mut self, policy: P) -> Self
+    where
+        P: EvictionPolicy<S> + 'static,
+    {
+        self.policy = Some(Box::new(policy));
+        self
+    }
+
+    /// Creates a batch of writes applied atomically to the database and to the table on commit.
+    pub fn batch(&mut self) -> SectionLruTableBatch<S, K, V, H> {
+        SectionLruTableBatch {
+            ops: Vec::new(),
+            table: self,
         }
     }
 
@@ -68,18 +116,62 @@ where
         Ok(())
     }
 
+    /// Removes a whole section from the database and from memory, unpinning it.
+    pub fn delete_section(&mut self, section: S) -> Result<()> {
+        self.db.delete_prefix(&section)?;
+        self.remove_section(&section);
+        Ok(())
+    }
+
     fn ensure_capacity(&mut self) {
-        if self.map.capacity() == self.map.len() {
-            if let Some(key) = self.map.iter().min_by_key(|t| t.1.age).map(|t| t.0.clone()) {
-                self.map.remove(&key);
-            }
+        if self.unpinned_len() >= self.capacity {
+            self.evict_victim();
+        }
+    }
+
+    /// Removes a section from memory, unpinning it, without touching the database.
+    ///
+    /// Returns false if the section was not in memory.
+    pub fn evict_section(&mut self, section: &S) -> bool {
+        if self.remove_section(section) {
+            self.counters.eviction();
+            stats::eviction(self.db.name());
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Evicts the least recently used section, or the victim of the eviction policy.
+    fn evict_victim(&mut self) -> bool {
+        let victim = match &mut self.policy {
+            Some(policy) => policy.victim(),
+            None => self.ages.oldest().cloned(),
+        };
+
+        match victim {
+            Some(section) => self.evict_section(&section),
+            None => false,
         }
     }
 
     fn ensure_section_loaded(&mut self, section: S) -> Result<&mut HashMap<K, V, H>> {
-        self.age += 1;
+        let span = trace_span!(
+            "section_lru_get",
+            cache.hit = field::Empty,
+            db.name = self.db.name(),
+            evicted = field::Empty,
+            section.len = field::Empty,
+        );
+        let _enter = span.enter();
+        let evictions = self.counters.evictions();
+
+        let hit = self.map.contains_key(&section);
+        self.counters.lookup(hit);
+        stats::cache_lookup(self.db.name(), hit);
+        span.record("cache.hit", &hit);
 
-        if !self.map.contains_key(&section) {
+        if !hit {
             self.ensure_capacity();
 
             self.map.insert(
@@ -92,7 +184,20 @@ where
         }
 
         let aged = self.map.get_mut(&section).unwrap();
-        aged.age = self.age;
+
+        if !self.pinned.contains(&section) {
+            if let Some(policy) = &mut self.policy {
+                match hit {
+                    true => policy.access(&section),
+                    false => policy.insert(&section),
+                }
+            }
+
+            self.ages.touch(&section, &mut aged.age);
+        }
+
+        span.record("evicted", &(self.counters.evictions() - evictions));
+        span.record("section.len", &aged.value.len());
         Ok(&mut aged.value)
     }
 
@@ -100,10 +205,189 @@ where
         Ok(self.ensure_section_loaded(section)?.get(key))
     }
 
+    /// Returns the value of the key, writing the value returned by `f` if the key is missing.
+    pub fn get_or_init<F>(&mut self, section: S, key: &K, f: F) -> Result<&V>
+    where
+        F: FnOnce() -> V,
+        K: Clone,
+    {
+        if !self
+            .ensure_section_loaded(section.clone())?
+            .contains_key(key)
+        {
+            let v = f();
+            self.db.put(&(section.clone(), key.clone()), &v)?;
+            self.ensure_section_loaded(section.clone())?
+                .insert(key.clone(), v);
+        }
+
+        Ok(&self.map[&section].value[key])
+    }
+
+    pub fn get_or_default(&mut self, section: S, key: &K) -> Result<&V>
+    where
+        K: Clone,
+        V: Default,
+    {
+        self.get_or_init(section, key, Default::default)
+    }
+
     pub fn get_section(&mut self, section: S) -> Result<&HashMap<K, V, H>> {
         Ok(&*self.ensure_section_loaded(section)?)
     }
 
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    #[inline]
+    pub fn is_pinned(&self, section: &S) -> bool {
+        self.pinned.contains(section)
+    }
+
+    /// Returns the number of sections kept in memory, the pinned sections included.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns the estimated number of bytes used by the sections in memory,
+    /// excluding the heap allocations owned by the keys and values.
+    pub fn memory_usage(&self) -> usize {
+        self.map.capacity() * size_of::<(S, Aged<HashMap<K, V, H>>)>()
+            + self
+                .map
+                .values()
+                .map(|aged| aged.value.capacity() * size_of::<(K, V)>())
+                .sum::<usize>()
+    }
+
+    /// Loads the section if needed and keeps it in memory, whatever its age, until it is
+    /// unpinned or explicitly evicted.
+    ///
+    /// The pinned sections do not count in the capacity of the table.
+    pub fn pin_section(&mut self, section: S) -> Result<()> {
+        if self.pinned.contains(&section) {
+            return Ok(());
+        }
+
+        self.ensure_section_loaded(section.clone())?;
+
+        if let Some(aged) = self.map.get_mut(&section) {
+            self.ages.remove(aged.age);
+            aged.age = 0;
+
+            if let Some(policy) = &mut self.policy {
+                policy.remove(&section);
+            }
+        }
+
+        self.pinned.insert(section);
+        Ok(())
+    }
+
+    /// Removes a section from memory, from the pins and from the eviction order.
+    fn remove_section(&mut self, section: &S) -> bool {
+        let pinned = self.pinned.remove(section);
+
+        match self.map.remove(section) {
+            Some(aged) => {
+                if !pinned {
+                    self.ages.remove(aged.age);
+
+                    if let Some(policy) = &mut self.policy {
+                        policy.remove(section);
+                    }
+                }
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Makes a pinned section evictable again, as the most recently used section.
+    pub fn unpin_section(&mut self, section: &S) {
+        if !self.pinned.remove(section) {
+            return;
+        }
+
+        if let Some(aged) = self.map.get_mut(section) {
+            self.ages.touch(section, &mut aged.age);
+
+            if let Some(policy) = &mut self.policy {
+                policy.insert(section);
+            }
+        }
+
+        while self.unpinned_len() > self.capacity {
+            if !self.evict_victim() {
+                break;
+            }
+        }
+    }
+
+    /// Returns the number of sections in memory that can be evicted.
+    fn unpinned_len(&self) -> usize {
+        self.map.len() - self.pinned.len()
+    }
+
+    /// Iterates the records of a section whose key is in `range`, in the order of `dir`,
+    /// read from the database without loading the section in memory.
+    ///
+    /// A reverse scan reads the last records of a section without scanning the others.
+    pub fn iter_section_range<R>(
+        &self,
+        section: S,
+        range: R,
+        dir: Direction,
+    ) -> Result<SectionIter<S, K, V>>
+    where
+        R: RangeBounds<K>,
+    {
+        self.db.ensure_bytewise("iter_section_range")?;
+
+        let prefix = key_prefix_to_bytes(&section)?;
+
+        let start = match range.start_bound() {
+            Bound::Included(k) => Bound::Included(key_to_bytes(&(&section, k))?),
+            Bound::Excluded(k) => Bound::Excluded(key_to_bytes(&(&section, k))?),
+            Bound::Unbounded => Bound::Included(prefix.clone()),
+        };
+
+        let end = match range.end_bound() {
+            Bound::Included(k) => Bound::Included(key_to_bytes(&(&section, k))?),
+            Bound::Excluded(k) => Bound::Excluded(key_to_bytes(&(&section, k))?),
+            Bound::Unbounded => match prefix_successor(&prefix) {
+                Some(next) => Bound::Excluded(next),
+                None => Bound::Unbounded,
+            },
+        };
+
+        Ok(SectionIter {
+            iter: DecodedIter::new(self.db.iter_raw_range(start, end, dir)?),
+        })
+    }
+
+    /// Iterates the records of a section in key order, read from the database without loading
+    /// the section in memory.
+    pub fn iter_section_sorted(&self, section: S) -> Result<SectionIter<S, K, V>> {
+        Ok(SectionIter {
+            iter: DecodedIter::new(self.db.iter_prefix_same_as_start(&section)?),
+        })
+    }
+
+    /// Iterates the distinct sections stored in the database, without loading their records.
+    pub fn iter_sections(&self) -> Result<Sections<S, K>> {
+        self.db.ensure_bytewise("iter_sections")?;
+
+        Ok(Sections {
+            done: false,
+            iter: self.db.iter(IteratorMode::Start)?,
+        })
+    }
+
     pub fn put(&mut self, section: S, key: &K, value: V) -> Result<()>
     where
         K: Clone,
@@ -114,29 +398,394 @@ where
             .insert(key.clone(), value);
         Ok(())
     }
+
+    /// Writes the value of the key, or deletes the key if the value is `None`.
+    pub fn put_opt(&mut self, section: S, key: &K, value: Option<V>) -> Result<()>
+    where
+        K: Clone,
+    {
+        match value {
+            Some(value) => self.put(section, key, value),
+            None => self.delete(section, key),
+        }
+    }
+
+    /// Loads the sections missing from memory, without evicting any section, and returns
+    /// the number of sections loaded.
+    ///
+    /// The sections are expected hottest first; the loading stops once the table is full.
+    pub fn warm_up_sections<I>(&mut self, sections: I) -> Result<usize>
+    where
+        I: IntoIterator<Item = S>,
+    {
+        let mut missing = Vec::new();
+
+        for section in sections {
+            if self.unpinned_len() + missing.len() >= self.capacity {
+                break;
+            }
+
+            if !self.map.contains_key(&section) && !missing.contains(&section) {
+                missing.push(section);
+            }
+        }
+
+        let count = missing.len();
+
+        // the hottest sections are loaded last, to be the last ones evicted.
+        for section in missing.into_iter().rev() {
+            let mut aged = Aged {
+                age: 0,
+                value: load_map(section.clone(), &self.db)?,
+            };
+
+            self.ages.touch(&section, &mut aged.age);
+
+            if let Some(policy) = &mut self.policy {
+                policy.insert(&section);
+            }
+
+            self.map.insert(section, aged);
+        }
+
+        Ok(count)
+    }
+
+    /// Loads the sections on a background thread, while the table is in use, and returns the
+    /// number of sections loaded.
+    ///
+    /// The table is locked for one section at a time. The sections are expected hottest first;
+    /// the ones beyond the free capacity when the thread starts are skipped.
+    pub fn warm_up_sections_in_background<I>(
+        table: &Arc<Mutex<Self>>,
+        sections: I,
+    ) -> JoinHandle<Result<usize>>
+    where
+        I: IntoIterator<Item = S>,
+        S: Send + 'static,
+        K: Send + 'static,
+        V: Send + 'static,
+        H: Send + 'static,
+    {
+        let table = Arc::clone(table);
+        let sections = sections.into_iter().collect::<Vec<_>>();
+
+        thread::spawn(move || {
+            let missing = {
+                let table = lock(&table);
+                let room = table.capacity.saturating_sub(table.unpinned_len());
+                let mut missing = Vec::new();
+
+                for section in sections {
+                    if missing.len() >= room {
+                        break;
+                    }
+
+                    if !table.map.contains_key(&section) && !missing.contains(&section) {
+                        missing.push(section);
+                    }
+                }
+
+                missing
+            };
+
+            let mut count = 0;
+
+            // the hottest sections are loaded last, to be the last ones evicted.
+            for section in missing.into_iter().rev() {
+                count += lock(&table).warm_up_sections(Some(section))?;
+            }
+
+            Ok(count)
+        })
+    }
+
+    /// Writes many records in a single batch.
+    ///
+    /// Only the sections already loaded in memory are updated.
+    pub fn put_many<I>(&mut self, items: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (S, K, V)>,
+    {
+        let items = items
+            .into_iter()
+            .map(|(section, key, value)| ((section, key), value))
+            .collect::<Vec<_>>();
+
+        let mut batch = RawBatch::new();
+
+        for (key, value) in &items {
+            self.db.batch_put(&mut batch, key, value)?;
+        }
+
+        self.db.write_raw(batch)?;
+
+        for ((section, key), value) in items {
+            if let Some(aged) = self.map.get_mut(&section) {
+                aged.value.insert(key, value);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Updates the value of the key, the section being loaded in memory if needed.
+    ///
+    /// If the write fails, the value in memory is left untouched.
+    pub fn update<U>(&mut self, section: S, key: &K, update: U) -> Result<()>
+    where
+        K: Clone,
+        U: UpdateFrom<V>,
+    {
+        self.update_and_get(section, key, update)?;
+        Ok(())
+    }
+
+    /// Updates the value of the key, returning a reference to the new value.
+    pub fn update_and_get<U>(&mut self, section: S, key: &K, update: U) -> Result<&mut V>
+    where
+        K: Clone,
+        U: UpdateFrom<V>,
+    {
+        let old = self.ensure_section_loaded(section.clone())?.remove(key);
+        let new = update.update_from(old);
+        let db_key = (section, key.clone());
+        let r = self.db.put(&db_key, &new);
+        let map = &mut self.map.get_mut(&db_key.0).unwrap().value;
+
+        if let Err(e) = r {
+            // restores the value as it is in the database.
+            if let Some(old) = self.db.get(&db_key)? {
+                map.insert(key.clone(), old.to_inner()?);
+            }
+
+            return Err(e);
+        }
+
+        Ok(map.entry(db_key.1).or_insert(new))
+    }
 }
 
-fn load_map<K, V, S, H>(section: S, db: &Db<(S, K)>) -> Result<HashMap<K, V, H>>
+impl<S, K, V, H> TableStats for SectionLruTable<S, K, V, H>
 where
-    S: for<'de> Deserialize<'de> + Clone + Debug + PartialEq + Serialize,
+    S: for<'de> Deserialize<'de> + Clone + Debug + Eq + Hash + Serialize,
     K: for<'de> Deserialize<'de> + Debug + Eq + Hash + MinValue + Serialize,
     V: for<'de> Deserialize<'de> + Serialize,
     H: BuildHasher + Default,
 {
-    let key = (section.clone(), K::min_value());
-    let mode = IteratorMode::From(key, Direction::Forward);
-    let mut iter = db.iter(mode)?;
-    let mut map = HashMap::with_hasher(Default::default());
+    fn bytes_in_memory(&self) -> usize {
+        self.memory_usage()
+    }
 
-    while let Some(item) = iter.next()? {
-        let (s, key) = item.key()?;
+    /// Returns the number of records of all the sections in memory.
+    fn entries_in_memory(&self) -> usize {
+        self.map.values().map(|aged| aged.value.len()).sum()
+    }
+
+    /// Returns the number of sections removed from memory.
+    fn evictions(&self) -> u64 {
+        self.counters.evictions()
+    }
+
+    /// Returns the number of section lookups served from memory.
+    fn hits(&self) -> u64 {
+        self.counters.hits()
+    }
+
+    /// Returns the number of section lookups that loaded the section from the database.
+    fn misses(&self) -> u64 {
+        self.counters.misses()
+    }
+}
+
+/// An iterator over the records of a section of a [`SectionLruTable`], in key order or
+/// in the order of the direction given to [`SectionLruTable::iter_section_range`].
+pub struct SectionIter<'a, S, K, V> {
+    iter: DecodedIter<'a, (S, K), V>,
+}
+
+impl<'a, S, K, V> Iterator for SectionIter<'a, S, K, V>
+where
+    S: for<'de> Deserialize<'de>,
+    K: for<'de> Deserialize<'de>,
+    V: for<'de> Deserialize<'de>,
+{
+    type Item = Result<(K, V)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.iter.next()?.map(|((_, key), value)| (key, value)))
+    }
+}
+
+/// An iterator over the distinct sections of a [`SectionLruTable`].
+pub struct Sections<'a, S, K> {
+    done: bool,
+    iter: Iter<'a, (S, K)>,
+}
+
+impl<'a, S, K> Sections<'a, S, K>
+where
+    S: for<'de> Deserialize<'de> + Serialize,
+    K: for<'de> Deserialize<'de>,
+{
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<S>> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let section = match self.iter.next()? {
+            Some(kv) => kv.key()?.0,
+            None => return Ok(None),
+        };
+
+        // skip all the remaining records of the section.
+        match prefix_successor(&key_prefix_to_bytes(&section)?) {
+            Some(next) => self.iter.seek_raw(&next),
+            None => self.done = true,
+        }
+
+        Ok(Some(section))
+    }
+}
+
+/// A batch of writes on a [`SectionLruTable`].
+///
+/// The writes are kept in memory until [`SectionLruTableBatch::commit`] is called.
+/// Only the sections already loaded in memory are updated on commit.
+pub struct SectionLruTableBatch<'a, S, K, V, H> {
+    ops: Vec<((S, K), Option<V>)>,
+    table: &'a mut SectionLruTable<S, K, V, H>,
+}
+
+impl<'a, S, K, V, H> SectionLruTableBatch<'a, S, K, V, H>
+where
+    S: for<'de> Deserialize<'de> + Clone + Debug + Eq + Hash + Serialize,
+    K: for<'de> Deserialize<'de> + Debug + Eq + Hash + MinValue + Serialize,
+    V: for<'de> Deserialize<'de> + Serialize,
+    H: BuildHasher + Default,
+{
+    /// Writes the batch to the database, then applies it to the table.
+    pub fn commit(self) -> Result<()> {
+        let mut tx = Transaction::new();
+        tx.enlist(self)?;
+        tx.commit()
+    }
 
-        if s != section {
-            break;
+    pub fn delete(&mut self, section: S, key: K) {
+        self.ops.push(((section, key), None));
+    }
+
+    pub fn put(&mut self, section: S, key: K, value: V) {
+        self.ops.push(((section, key), Some(value)));
+    }
+}
+
+impl<'a, S, K, V, H> PartOps for SectionLruTableBatch<'a, S, K, V, H>
+where
+    S: for<'de> Deserialize<'de> + Clone + Debug + Eq + Hash + Serialize,
+    K: for<'de> Deserialize<'de> + Debug + Eq + Hash + MinValue + Serialize,
+    V: for<'de> Deserialize<'de> + Serialize,
+    H: BuildHasher + Default,
+{
+    fn db(&self) -> &Arc<rocksdb::DB> {
+        self.table.db.raw_db()
+    }
+
+    fn db_name(&self) -> &str {
+        self.table.db.name()
+    }
+
+    fn write_ops(&self, batch: &mut RawBatch) -> Result<()> {
+        for (key, value) in &self.ops {
+            match value {
+                Some(value) => self.table.db.batch_put(batch, key, value)?,
+                None => self.table.db.batch_delete(batch, key)?,
+            }
         }
 
-        map.insert(key, item.value()?);
+        Ok(())
+    }
+
+    fn apply_ops(self: Box<Self>) {
+        let batch = *self;
+
+        for ((section, key), value) in batch.ops {
+            if let Some(aged) = batch.table.map.get_mut(&section) {
+                match value {
+                    Some(value) => {
+                        aged.value.insert(key, value);
+                    }
+                    None => {
+                        aged.value.remove(&key);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Loads the records of a section in a map, such as a `HashMap` or a `BTreeMap`.
+pub(crate) fn load_map<K, V, S, M>(section: S, db: &Db<(S, K)>) -> Result<M>
+where
+    S: for<'de> Deserialize<'de> + Debug + Serialize,
+    K: for<'de> Deserialize<'de> + Debug + Serialize,
+    V: for<'de> Deserialize<'de> + Serialize,
+    M: Default + Extend<(K, V)>,
+{
+    let mut iter = db.iter_prefix_same_as_start(&section)?;
+    let mut map = M::default();
+
+    while let Some(item) = iter.next()? {
+        let (_, key): (S, K) = item.key()?;
+        map.extend(Some((key, item.value()?)));
     }
 
     Ok(map)
 }
+
+/// Extracts the serialized section at the start of a `(S, K)` key.
+///
+/// Since the key encodings are self-delimiting, the section is found by decoding it.
+pub(crate) fn section_prefix<S>(key: &[u8]) -> &[u8]
+where
+    S: for<'de> Deserialize<'de>,
+{
+    match key_serialized_len::<S>(key) {
+        Some(len) => &key[..len],
+        None => key,
+    }
+}
+
+pub(crate) fn section_in_domain<S>(key: &[u8]) -> bool
+where
+    S: for<'de> Deserialize<'de>,
+{
+    !key.is_empty() && key_serialized_len::<S>(key).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SectionLruTable;
+    use crate::Db;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn warm_up_sections_in_background() {
+        let db = Db::<(u32, u32)>::open_temporary().unwrap();
+
+        for section in 0..4 {
+            db.put(&(section, 0), &section.to_string()).unwrap();
+        }
+
+        let table = SectionLruTable::<u32, u32, String>::with_capacity(db, 2);
+        let table = Arc::new(Mutex::new(table));
+
+        let handle = SectionLruTable::warm_up_sections_in_background(&table, vec![3, 1, 3, 0]);
+        assert_eq!(handle.join().unwrap().unwrap(), 2);
+
+        let mut table = table.lock().unwrap();
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.get(3, &0).unwrap(), Some(&"3".to_string()));
+    }
+}
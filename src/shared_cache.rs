@@ -0,0 +1,44 @@
+use crate::{Error, Result};
+use rocksdb::Cache;
+use std::{fmt, sync::Arc};
+
+/// An LRU block cache shared by several databases, so their cached blocks fit in a single
+/// memory budget instead of one cache per database.
+///
+/// The cache is passed to [`DbBuilder::shared_block_cache`](crate::DbBuilder::shared_block_cache)
+/// for each database opened.
+#[derive(Clone)]
+pub struct SharedCache {
+    inner: Arc<Cache>,
+}
+
+impl SharedCache {
+    /// Creates a cache of `capacity` bytes.
+    pub fn new(capacity: usize) -> Result<Self> {
+        Ok(Self {
+            inner: Arc::new(Cache::new_lru_cache(capacity).map_err(Error::RocksDb)?),
+        })
+    }
+
+    pub(crate) fn cache(&self) -> &Cache {
+        &self.inner
+    }
+
+    /// Returns the number of bytes of the blocks pinned by the iterators and the readers.
+    pub fn pinned_usage(&self) -> usize {
+        self.inner.get_pinned_usage()
+    }
+
+    /// Returns the number of bytes used by the cached blocks.
+    pub fn usage(&self) -> usize {
+        self.inner.get_usage()
+    }
+}
+
+impl fmt::Debug for SharedCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SharedCache")
+            .field("usage", &self.usage())
+            .finish()
+    }
+}
@@ -0,0 +1,126 @@
+use std::ops::Range;
+
+/// A distribution of sizes in bytes, bucketed by powers of two.
+///
+/// Returned by [`Db::key_size_histogram`](crate::Db::key_size_histogram) and
+/// [`Db::value_size_histogram`](crate::Db::value_size_histogram).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SizeHistogram {
+    buckets: Vec<u64>,
+    max: usize,
+    min: usize,
+    sample_every: u64,
+    sampled: u64,
+    scanned: u64,
+    sum: u64,
+}
+
+impl SizeHistogram {
+    pub(crate) fn new(sample_every: u64) -> Self {
+        Self {
+            sample_every: sample_every.max(1),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the range of sizes and the number of sampled entries of each non-empty bucket.
+    ///
+    /// The first bucket holds the empty entries, then each bucket spans `2^(i-1)..2^i` bytes.
+    pub fn buckets(&self) -> impl Iterator<Item = (Range<usize>, u64)> + '_ {
+        self.buckets
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(i, count)| (bucket_range(i), *count))
+    }
+
+    /// Returns the estimated total size of all the entries, scaled by the sampling rate.
+    pub fn estimated_total_bytes(&self) -> u64 {
+        self.sum.saturating_mul(self.sample_every)
+    }
+
+    /// Returns the size of the largest sampled entry.
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Returns the mean size of the sampled entries, or 0 if none were sampled.
+    pub fn mean(&self) -> f64 {
+        if self.sampled == 0 {
+            0.0
+        } else {
+            self.sum as f64 / self.sampled as f64
+        }
+    }
+
+    /// Returns the size of the smallest sampled entry.
+    pub fn min(&self) -> usize {
+        self.min
+    }
+
+    /// Returns the largest size of the bucket holding the `p` percentile (0.0 to 1.0) of the sizes.
+    pub fn percentile(&self, p: f64) -> usize {
+        let target = (self.sampled as f64 * p.max(0.0).min(1.0)).ceil() as u64;
+        let mut seen = 0;
+
+        for (range, count) in self.buckets() {
+            seen += count;
+
+            if seen >= target {
+                return (range.end - 1).min(self.max);
+            }
+        }
+
+        self.max
+    }
+
+    /// Returns the number of entries recorded in the histogram.
+    pub fn sampled(&self) -> u64 {
+        self.sampled
+    }
+
+    /// Returns the number of entries read from the database, sampled or not.
+    pub fn scanned(&self) -> u64 {
+        self.scanned
+    }
+
+    /// Returns the total size of the sampled entries.
+    pub fn total_bytes(&self) -> u64 {
+        self.sum
+    }
+
+    pub(crate) fn record(&mut self, size: usize) {
+        self.scanned += 1;
+
+        if (self.scanned - 1) % self.sample_every != 0 {
+            return;
+        }
+
+        let index = bucket_index(size);
+
+        if self.buckets.len() <= index {
+            self.buckets.resize(index + 1, 0);
+        }
+
+        self.buckets[index] += 1;
+        self.min = if self.sampled == 0 {
+            size
+        } else {
+            self.min.min(size)
+        };
+        self.max = self.max.max(size);
+        self.sampled += 1;
+        self.sum += size as u64;
+    }
+}
+
+fn bucket_index(size: usize) -> usize {
+    (usize::MAX.count_ones() - size.leading_zeros()) as usize
+}
+
+fn bucket_range(index: usize) -> Range<usize> {
+    match index {
+        0 => 0..1,
+        _ => 1 << (index - 1)..(1usize << (index - 1)).saturating_mul(2),
+    }
+}
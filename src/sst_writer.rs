@@ -0,0 +1,150 @@
+use crate::{
+    db::{db_name_from_path, default_options, log_err, map_log_err, with_value_bytes},
+    key_ser::key_to_bytes,
+    Error, Result,
+};
+use rocksdb::{Options, SstFileWriter};
+use serde::Serialize;
+use std::{fmt::Debug, marker::PhantomData, path::Path};
+use tracing::trace_span;
+
+/// The options used to write external SST files, created by [`DbBuilder::sst_options`](crate::DbBuilder::sst_options).
+///
+/// The options should match those of the database in which the files are ingested.
+pub struct SstOptions(Options);
+
+impl SstOptions {
+    pub(crate) fn new(opts: Options) -> Self {
+        Self(opts)
+    }
+
+    /// Creates an SST file at `path`, to be filled with keys in ascending order.
+    pub fn writer<K, V, P>(&self, path: P) -> Result<SstWriter<K, V>>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let db_name = db_name_from_path(path);
+
+        let span = trace_span!(
+            "sst_writer",
+            db.name = db_name.as_str(),
+            db.system = "rocksdb"
+        );
+        let _enter = span.enter();
+
+        let writer = SstFileWriter::create(&self.0);
+        writer.open(path).map_err(|e| map_log_err(e, &db_name))?;
+
+        Ok(SstWriter {
+            _kv: PhantomData,
+            checksums: false,
+            count: 0,
+            db_name,
+            last_key: None,
+            writer,
+        })
+    }
+}
+
+impl Default for SstOptions {
+    fn default() -> Self {
+        Self::new(default_options())
+    }
+}
+
+/// Writes sorted typed keys / values to an external SST file, ingested with [`Db::ingest`](crate::Db::ingest).
+///
+/// The keys must be added in ascending order of their serialized bytes.
+pub struct SstWriter<'a, K, V> {
+    _kv: PhantomData<(K, V)>,
+    /// True when the values are written with a trailing CRC32, see [`Db::with_value_checksums`](crate::Db::with_value_checksums).
+    checksums: bool,
+    count: u64,
+    db_name: String,
+    last_key: Option<Vec<u8>>,
+    writer: SstFileWriter<'a>,
+}
+
+impl<'a, K, V> SstWriter<'a, K, V>
+where
+    K: Debug + Serialize,
+    V: Serialize,
+{
+    /// Writes the values with a trailing CRC32, as expected by a database using
+    /// [`Db::with_value_checksums`](crate::Db::with_value_checksums).
+    pub fn with_value_checksums(mut self, enabled: bool) -> Self {
+        self.checksums = enabled;
+        self
+    }
+
+    /// Finishes the file, returning the number of keys written.
+    pub fn finish(mut self) -> Result<u64> {
+        let span = trace_span!(
+            "finish",
+            db.name = self.db_name.as_str(),
+            db.statement = format!("len = {}", self.count).as_str(),
+            db.system = "rocksdb",
+        );
+        let _enter = span.enter();
+
+        self.writer
+            .finish()
+            .map_err(|e| map_log_err(e, &self.db_name))?;
+
+        Ok(self.count)
+    }
+
+    /// Adds a key / value, returning [`Error::KeyOutOfOrder`] if the key is not greater than
+    /// the previous one.
+    pub fn put(&mut self, key: &K, value: &V) -> Result<()> {
+        let k = key_to_bytes(key).map_err(|e| log_err(e, &self.db_name))?;
+
+        if self.last_key.as_ref().map_or(false, |last| &k <= last) {
+            return Err(log_err(
+                Error::KeyOutOfOrder(format!("{:?}", key)),
+                &self.db_name,
+            ));
+        }
+
+        let writer = &mut self.writer;
+        let db_name = &self.db_name;
+
+        with_value_bytes(value, self.checksums, db_name, |v| {
+            writer.put(&k, v).map_err(|e| map_log_err(e, db_name))
+        })?;
+
+        self.count += 1;
+        self.last_key = Some(k);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SstOptions;
+    use crate::{db::default_options, Db};
+
+    #[test]
+    fn ingest_with_checksums() {
+        let db = Db::<u32>::open_temporary()
+            .unwrap()
+            .with_value_checksums(true);
+
+        let path = std::env::temp_dir().join(format!("rocks-tables-{}.sst", std::process::id()));
+
+        let mut writer = SstOptions::new(default_options())
+            .writer::<u32, String, _>(&path)
+            .unwrap()
+            .with_value_checksums(true);
+
+        writer.put(&1, &"a".to_string()).unwrap();
+        writer.finish().unwrap();
+
+        db.ingest(&[&path]).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        let value = db.get(&1).unwrap().unwrap();
+        assert_eq!(value.to_inner::<String>().unwrap(), "a");
+    }
+}
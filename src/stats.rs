@@ -0,0 +1,92 @@
+//! Records the metrics of the databases and tables through the `metrics` facade.
+//!
+//! Without the `metrics` feature, all the functions are no-ops.
+
+#[cfg(feature = "metrics")]
+use metrics::{counter, histogram};
+#[cfg(feature = "metrics")]
+use std::time::Instant;
+
+/// Measures the latency of an operation, recorded when dropped.
+pub(crate) struct Timer<'a> {
+    #[cfg(feature = "metrics")]
+    db_name: &'a str,
+    #[cfg(feature = "metrics")]
+    op: &'static str,
+    #[cfg(feature = "metrics")]
+    start: Instant,
+    #[cfg(not(feature = "metrics"))]
+    _db_name: std::marker::PhantomData<&'a str>,
+}
+
+#[cfg(feature = "metrics")]
+impl<'a> Drop for Timer<'a> {
+    fn drop(&mut self) {
+        histogram!(
+            "rocks_tables_op_duration_ns",
+            self.start.elapsed().as_nanos() as u64,
+            "db" => self.db_name.to_string(),
+            "op" => self.op,
+        );
+    }
+}
+
+#[cfg(feature = "metrics")]
+pub(crate) fn timer<'a>(op: &'static str, db_name: &'a str) -> Timer<'a> {
+    Timer {
+        db_name,
+        op,
+        start: Instant::now(),
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+#[inline]
+pub(crate) fn timer<'a>(_op: &'static str, _db_name: &'a str) -> Timer<'a> {
+    Timer {
+        _db_name: std::marker::PhantomData,
+    }
+}
+
+#[cfg(feature = "metrics")]
+pub(crate) fn bytes_read(db_name: &str, len: usize) {
+    counter!("rocks_tables_bytes_read", len as u64, "db" => db_name.to_string());
+}
+
+#[cfg(not(feature = "metrics"))]
+#[inline]
+pub(crate) fn bytes_read(_db_name: &str, _len: usize) {}
+
+#[cfg(feature = "metrics")]
+pub(crate) fn bytes_written(db_name: &str, len: usize) {
+    counter!("rocks_tables_bytes_written", len as u64, "db" => db_name.to_string());
+}
+
+#[cfg(not(feature = "metrics"))]
+#[inline]
+pub(crate) fn bytes_written(_db_name: &str, _len: usize) {}
+
+/// Counts a lookup served from the memory of a table, or loaded from the database on a miss.
+#[cfg(feature = "metrics")]
+pub(crate) fn cache_lookup(db_name: &str, hit: bool) {
+    let name = if hit {
+        "rocks_tables_cache_hits"
+    } else {
+        "rocks_tables_cache_misses"
+    };
+
+    counter!(name, 1, "db" => db_name.to_string());
+}
+
+#[cfg(not(feature = "metrics"))]
+#[inline]
+pub(crate) fn cache_lookup(_db_name: &str, _hit: bool) {}
+
+#[cfg(feature = "metrics")]
+pub(crate) fn eviction(db_name: &str) {
+    counter!("rocks_tables_evictions", 1, "db" => db_name.to_string());
+}
+
+#[cfg(not(feature = "metrics"))]
+#[inline]
+pub(crate) fn eviction(_db_name: &str) {}
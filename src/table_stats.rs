@@ -0,0 +1,55 @@
+use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
+
+/// The cache statistics of a table, counted since its creation.
+///
+/// Unlike the `metrics` feature, the counters are always maintained and are read from the table itself.
+pub trait TableStats {
+    /// Returns the estimated number of bytes used by the entries in memory.
+    fn bytes_in_memory(&self) -> usize;
+
+    /// Returns the number of records kept in memory.
+    fn entries_in_memory(&self) -> usize;
+
+    /// Returns the number of entries removed from memory to make room for others.
+    fn evictions(&self) -> u64;
+
+    /// Returns the number of lookups served from memory.
+    fn hits(&self) -> u64;
+
+    /// Returns the number of lookups not found in memory.
+    fn misses(&self) -> u64;
+}
+
+/// The counters behind a [`TableStats`] implementation, updatable through a shared reference.
+#[derive(Debug, Default)]
+pub(crate) struct CacheCounters {
+    evictions: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CacheCounters {
+    pub fn eviction(&self) {
+        self.evictions.fetch_add(1, Relaxed);
+    }
+
+    pub fn evictions(&self) -> u64 {
+        self.evictions.load(Relaxed)
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Relaxed)
+    }
+
+    pub fn lookup(&self, hit: bool) {
+        if hit {
+            self.hits.fetch_add(1, Relaxed);
+        } else {
+            self.misses.fetch_add(1, Relaxed);
+        }
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Relaxed)
+    }
+}
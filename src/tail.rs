@@ -0,0 +1,114 @@
+use crate::{
+    db::{log_err, value_from_bytes, META_KEY},
+    key_ser::key_from_bytes,
+    Result,
+};
+use rocksdb::{DBWALIterator, WriteBatchIterator, DB};
+use serde::Deserialize;
+use std::{collections::VecDeque, marker::PhantomData, sync::Arc};
+
+/// The kind of a write read from the write-ahead log by a [`Tail`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Operation {
+    Delete,
+    Put,
+}
+
+/// Follows the writes of a database from its write-ahead log, created by
+/// [`Db::tail_from_sequence`](crate::Db::tail_from_sequence).
+///
+/// Each item is a key, the kind of write and the value written. Once the items available are
+/// read, the iteration ends; a new `Tail` starting at [`Tail::next_sequence`] reads the writes
+/// made since.
+///
+/// Only the puts and the deletes of the default column family are returned; the merges, the
+/// range deletes and the writes on the other column families are skipped.
+pub struct Tail<K, V> {
+    _kv: PhantomData<(K, V)>,
+    checksum: bool,
+    db_name: String,
+    iter: DBWALIterator,
+    next_sequence: u64,
+    pending: VecDeque<(Box<[u8]>, Option<Box<[u8]>>)>,
+    /// Keeps the database open while the log is read; dropped after the iterator.
+    _db: Arc<DB>,
+}
+
+impl<K, V> Tail<K, V> {
+    pub(crate) fn new(
+        db: Arc<DB>,
+        db_name: String,
+        checksum: bool,
+        iter: DBWALIterator,
+        sequence: u64,
+    ) -> Self {
+        Self {
+            _kv: PhantomData,
+            checksum,
+            db_name,
+            iter,
+            next_sequence: sequence,
+            pending: VecDeque::new(),
+            _db: db,
+        }
+    }
+
+    /// Returns the sequence number following the last batch read, to resume the tailing from.
+    pub fn next_sequence(&self) -> u64 {
+        self.next_sequence
+    }
+}
+
+impl<K, V> Iterator for Tail<K, V>
+where
+    K: for<'de> Deserialize<'de>,
+    V: for<'de> Deserialize<'de>,
+{
+    type Item = Result<(K, Operation, Option<V>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((key, value)) = self.pending.pop_front() {
+                if &*key == META_KEY {
+                    continue;
+                }
+
+                return Some(self.decode(&key, value.as_deref()));
+            }
+
+            let (sequence, batch) = self.iter.next()?;
+            self.next_sequence = sequence + batch.len() as u64;
+            batch.iterate(&mut Collector(&mut self.pending));
+        }
+    }
+}
+
+impl<K, V> Tail<K, V>
+where
+    K: for<'de> Deserialize<'de>,
+    V: for<'de> Deserialize<'de>,
+{
+    fn decode(&self, key: &[u8], value: Option<&[u8]>) -> Result<(K, Operation, Option<V>)> {
+        let key = key_from_bytes(key).map_err(|e| log_err(e, &self.db_name))?;
+
+        Ok(match value {
+            Some(value) => {
+                let value = value_from_bytes(value, self.checksum, &self.db_name)?;
+                (key, Operation::Put, Some(value))
+            }
+            None => (key, Operation::Delete, None),
+        })
+    }
+}
+
+struct Collector<'a>(&'a mut VecDeque<(Box<[u8]>, Option<Box<[u8]>>)>);
+
+impl<'a> WriteBatchIterator for Collector<'a> {
+    fn put(&mut self, key: Box<[u8]>, value: Box<[u8]>) {
+        self.0.push_back((key, Some(value)));
+    }
+
+    fn delete(&mut self, key: Box<[u8]>) {
+        self.0.push_back((key, None));
+    }
+}
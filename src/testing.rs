@@ -0,0 +1,143 @@
+//! Checks that a key type is stored in its natural order, so that the iterations and the
+//! range scans of a [`Db`](crate::Db) return its keys sorted.
+//!
+//! The keys to check are generated by the caller, for instance by a property-testing crate.
+
+use crate::{
+    key_ser::{key_from_bytes, key_to_bytes},
+    Result,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt::Debug;
+
+/// Asserts that the key is decoded back to itself.
+pub fn assert_key_roundtrip<K>(key: &K)
+where
+    K: Debug + DeserializeOwned + PartialEq + Serialize,
+{
+    let bytes = key_to_bytes(key).expect("key encoding failed");
+    let decoded: K = key_from_bytes(&bytes).expect("key decoding failed");
+
+    assert_eq!(&decoded, key, "the key is not decoded back to itself");
+}
+
+/// Asserts that the encoded keys compare like the keys, as reported by [`key_order_violation`].
+pub fn assert_key_order<K>(keys: &[K])
+where
+    K: Debug + Ord + Serialize,
+{
+    if let Some((a, b)) = key_order_violation(keys).expect("key encoding failed") {
+        panic!(
+            "the encoded keys do not compare like the keys: {:?} and {:?}",
+            a, b
+        );
+    }
+}
+
+/// Returns two keys whose encodings do not compare like the keys themselves, if any.
+///
+/// Equal keys must also have the same encoding, since they are the same key in the database.
+pub fn key_order_violation<K>(keys: &[K]) -> Result<Option<(&K, &K)>>
+where
+    K: Ord + Serialize,
+{
+    let mut encoded = keys
+        .iter()
+        .map(|k| Ok((k, key_to_bytes(k)?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    encoded.sort_by(|a, b| a.0.cmp(b.0));
+
+    Ok(encoded
+        .windows(2)
+        .find(|w| w[0].0.cmp(w[1].0) != w[0].1.cmp(&w[1].1))
+        .map(|w| (w[0].0, w[1].0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assert_key_order, assert_key_roundtrip, key_order_violation};
+    use crate::Encoded;
+    use serde::{de::DeserializeOwned, Serialize};
+    use std::{cmp::Ordering, fmt::Debug};
+
+    fn check<K>(keys: &[K])
+    where
+        K: Debug + DeserializeOwned + Ord + Serialize,
+    {
+        keys.iter().for_each(assert_key_roundtrip);
+        assert_key_order(keys);
+    }
+
+    #[test]
+    fn integers() {
+        check(&[0u8, 1, 127, 128, 255]);
+        check(&[0u16, 1, 256, u16::MAX]);
+        check(&[0u32, 1, 1 << 16, u32::MAX]);
+        check(&[0u64, 1, 1 << 32, u64::MAX]);
+        check(&[0u128, 1, 1 << 64, u128::MAX]);
+        check(&[i8::MIN, -1, 0, 1, i8::MAX]);
+        check(&[i16::MIN, -1, 0, 1, i16::MAX]);
+        check(&[i32::MIN, -1, 0, 1, i32::MAX]);
+        check(&[i64::MIN, -1, 0, 1, i64::MAX]);
+        check(&[i128::MIN, -1, 0, 1, i128::MAX]);
+    }
+
+    #[test]
+    fn scalars() {
+        check(&[false, true]);
+        check(&['\0', 'a', 'z', 'é', '\u{10ffff}']);
+        check(&[(), ()]);
+    }
+
+    #[test]
+    fn strings_and_bytes() {
+        let strings = ["", "\0", "\0\0", "a", "a\0", "a\u{1}", "ab", "é"];
+        check(&strings.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+
+        check(&[vec![], vec![0u8], vec![0, 0], vec![0, 255], vec![1]]);
+        check(&[Encoded(vec![0u8]), Encoded(vec![0, 0]), Encoded(vec![1])]);
+    }
+
+    #[test]
+    fn compounds() {
+        check(&[None, Some(0u32), Some(1)]);
+        check(&[(0u32, -1i64), (0, 0), (1, i64::MIN)]);
+        check(&[
+            (1u8, "a".to_string(), None),
+            (1, "a".to_string(), Some(0u16)),
+            (1, "a\0".to_string(), None),
+            (2, String::new(), None),
+        ]);
+        check(&[vec![(0u8, "b".to_string())], vec![(1, "a".to_string())]]);
+        check(&[(Encoded(vec![0u8]), 1u8), (Encoded(vec![0, 0]), 0)]);
+    }
+
+    #[derive(Debug, Eq, PartialEq, Serialize)]
+    struct Reversed(u32);
+
+    impl Ord for Reversed {
+        fn cmp(&self, other: &Self) -> Ordering {
+            other.0.cmp(&self.0)
+        }
+    }
+
+    impl PartialOrd for Reversed {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    #[test]
+    fn violation_reported() {
+        let keys = [Reversed(1), Reversed(2)];
+        let violation = key_order_violation(&keys).unwrap();
+        assert_eq!(violation, Some((&keys[1], &keys[0])));
+    }
+
+    #[test]
+    #[should_panic(expected = "do not compare like the keys")]
+    fn violation_asserted() {
+        assert_key_order(&[Reversed(1), Reversed(2)]);
+    }
+}
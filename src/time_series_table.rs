@@ -0,0 +1,114 @@
+use crate::{db::log_err, Db, Direction, Error, IteratorMode, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Debug,
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+};
+
+/// A table of values timestamped per id, such as measures or events.
+///
+/// The values are stored under `(Id, i64)` keys, the timestamps being in nanoseconds since
+/// the epoch, so the values of an id are ordered by time. The timestamps are thus limited to
+/// the years 1677 to 2262.
+pub struct TimeSeriesTable<Id, V> {
+    _v: PhantomData<V>,
+    db: Db<(Id, i64)>,
+}
+
+impl<Id, V> TimeSeriesTable<Id, V>
+where
+    Id: Clone + Debug + for<'de> Deserialize<'de> + PartialEq + Serialize,
+    V: for<'de> Deserialize<'de> + Serialize,
+{
+    pub fn new(db: Db<(Id, i64)>) -> Self {
+        Self {
+            _v: PhantomData,
+            db,
+        }
+    }
+
+    /// Writes a value at a timestamp, replacing the value already at this timestamp.
+    ///
+    /// Fails with [`Error::KeyEncoding`] if the timestamp is out of the range of the table.
+    pub fn append(&self, id: &Id, at: DateTime<Utc>, value: &V) -> Result<()> {
+        let nanos = at.timestamp_nanos_opt().ok_or_else(|| {
+            let e = Error::KeyEncoding(format!("timestamp out of range: {}", at));
+            log_err(e, self.db.name())
+        })?;
+
+        self.db.put(&(id.clone(), nanos), value)
+    }
+
+    /// Returns the most recent value of the id.
+    pub fn latest(&self, id: &Id) -> Result<Option<(DateTime<Utc>, V)>> {
+        let mut iter = self.db.iter(IteratorMode::From(
+            (id.clone(), i64::MAX),
+            Direction::Reverse,
+        ))?;
+
+        match iter.next()? {
+            Some(kv) => {
+                let (k, nanos) = kv.key()?;
+
+                if &k == id {
+                    Ok(Some((Utc.timestamp_nanos(nanos), kv.value()?)))
+                } else {
+                    Ok(None)
+                }
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Removes the values of the id older than `before`, with a single range delete.
+    ///
+    /// Called periodically with `Utc::now() - retention`, it keeps only the recent values.
+    pub fn prune_before(&self, id: &Id, before: DateTime<Utc>) -> Result<()> {
+        self.db.delete_range(
+            &(id.clone(), i64::MIN),
+            &(id.clone(), clamped_nanos(&before)),
+        )
+    }
+
+    /// Returns the values of the id within a range of timestamps, in ascending order of time.
+    pub fn range<R>(&self, id: &Id, range: R) -> Result<Vec<(DateTime<Utc>, V)>>
+    where
+        R: RangeBounds<DateTime<Utc>>,
+    {
+        let key = |at: &DateTime<Utc>| (id.clone(), clamped_nanos(at));
+
+        let start = match range.start_bound() {
+            Bound::Included(at) => Bound::Included(key(at)),
+            Bound::Excluded(at) => Bound::Excluded(key(at)),
+            Bound::Unbounded => Bound::Included((id.clone(), i64::MIN)),
+        };
+
+        let end = match range.end_bound() {
+            Bound::Included(at) => Bound::Included(key(at)),
+            Bound::Excluded(at) => Bound::Excluded(key(at)),
+            Bound::Unbounded => Bound::Included((id.clone(), i64::MAX)),
+        };
+
+        let mut iter = self.db.iter_range((start, end))?;
+        let mut values = Vec::new();
+
+        while let Some(kv) = iter.next()? {
+            let (_, nanos) = kv.key()?;
+            values.push((Utc.timestamp_nanos(nanos), kv.value()?));
+        }
+
+        Ok(values)
+    }
+}
+
+/// Returns the nanoseconds since the epoch, the timestamps out of range being clamped to the
+/// bounds of the keys.
+fn clamped_nanos(at: &DateTime<Utc>) -> i64 {
+    match at.timestamp_nanos_opt() {
+        Some(nanos) => nanos,
+        None if at.timestamp() < 0 => i64::MIN,
+        None => i64::MAX,
+    }
+}
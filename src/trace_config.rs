@@ -0,0 +1,68 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fmt::Debug,
+    hash::{Hash, Hasher},
+};
+use tracing::Level;
+
+/// How the keys and the other arguments of an operation are recorded in the `db.statement`
+/// field of the spans of a [`Db`](crate::Db).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StatementCapture {
+    /// The arguments are recorded with their `Debug` formatting.
+    Full,
+    /// Only a hash of the arguments is recorded, so the spans of a key can be correlated
+    /// without exposing the key.
+    Hashed,
+    /// The `db.statement` field is left empty.
+    Off,
+}
+
+/// Configures the spans created by the operations of a [`Db`](crate::Db).
+///
+/// By default, the spans are at the `TRACE` level and record the full statements.
+/// The statements are only formatted when the span is enabled.
+#[derive(Clone, Copy, Debug)]
+pub struct TraceConfig {
+    pub(crate) level: Level,
+    statements: StatementCapture,
+}
+
+impl Default for TraceConfig {
+    fn default() -> Self {
+        Self {
+            level: Level::TRACE,
+            statements: StatementCapture::Full,
+        }
+    }
+}
+
+impl TraceConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the level of the spans.
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Sets how the statements are recorded.
+    pub fn statements(mut self, capture: StatementCapture) -> Self {
+        self.statements = capture;
+        self
+    }
+
+    pub(crate) fn statement(&self, statement: &dyn Debug) -> Option<String> {
+        match self.statements {
+            StatementCapture::Full => Some(format!("{:?}", statement)),
+            StatementCapture::Hashed => {
+                let mut hasher = DefaultHasher::new();
+                format!("{:?}", statement).hash(&mut hasher);
+                Some(format!("{:016x}", hasher.finish()))
+            }
+            StatementCapture::Off => None,
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use crate::{commit_group::JournalOp, db::log_err, Db, Error, RawBatch, Result, WriteMode};
+use serde::{Deserialize, Serialize};
+use std::{fmt::Debug, sync::Arc};
+use tracing::trace_span;
+
+/// A part of a [`Transaction`], usually the batch of a table.
+///
+/// The writes are added to the RocksDB batch when the part is enlisted and the in-memory
+/// changes are only applied once the transaction has been committed.
+///
+/// The trait is sealed, implemented by the table batches of this crate.
+pub trait TransactionPart: PartOps {}
+
+impl<T: PartOps> TransactionPart for T {}
+
+/// The operations of a [`TransactionPart`], kept out of the public API since the module is private.
+pub trait PartOps {
+    fn db(&self) -> &Arc<rocksdb::DB>;
+    fn db_name(&self) -> &str;
+    fn write_ops(&self, batch: &mut RawBatch) -> Result<()>;
+    fn apply_ops(self: Box<Self>);
+}
+
+/// A set of writes spanning multiple tables of the same database, applied atomically.
+///
+/// All the tables must be column families of the same [`DbHandle`](crate::DbHandle).
+/// If the commit fails, the in-memory state of the enlisted tables is left untouched and no
+/// change is notified to the subscribers of the databases.
+pub struct Transaction<'a> {
+    batch: RawBatch,
+    db: Option<(Arc<rocksdb::DB>, String)>,
+    parts: Vec<Box<dyn TransactionPart + 'a>>,
+}
+
+impl<'a> Transaction<'a> {
+    pub fn new() -> Self {
+        Self {
+            batch: RawBatch::new(),
+            db: None,
+            parts: Vec::new(),
+        }
+    }
+
+    fn check_db(&mut self, db: &Arc<rocksdb::DB>, db_name: &str) -> Result<()> {
+        match &self.db {
+            Some((d, _)) if Arc::ptr_eq(d, db) => Ok(()),
+            Some(_) => Err(log_err(Error::MixedDatabases, db_name)),
+            None => {
+                self.db = Some((Arc::clone(db), db_name.to_string()));
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes all the parts atomically, then applies the in-memory changes of each part.
+    pub fn commit(self) -> Result<()> {
+        self.commit_with(WriteMode::Default)
+    }
+
+    /// Commits the transaction with the durability of `mode`.
+    pub fn commit_with(self, mode: WriteMode) -> Result<()> {
+        let (db, db_name) = match self.db {
+            Some(db) => db,
+            None => return Ok(()),
+        };
+
+        let span = trace_span!(
+            "commit",
+            db.name = db_name.as_str(),
+            db.statement = format!("len = {}", self.batch.len()).as_str(),
+            db.system = "rocksdb",
+        );
+        let _enter = span.enter();
+
+        self.batch.write_opt(&db, &db_name, mode)?;
+
+        for part in self.parts {
+            part.apply_ops();
+        }
+
+        Ok(())
+    }
+
+    /// Creates a transaction recording its writes, to be replayed by a commit group.
+    pub(crate) fn journaled() -> Self {
+        Self {
+            batch: RawBatch::journaled(),
+            ..Self::new()
+        }
+    }
+
+    pub(crate) fn journal_ops(&self) -> &[JournalOp] {
+        self.batch.journal_ops()
+    }
+
+    /// Adds the removal of a key in `db` to the transaction.
+    pub fn delete<K>(&mut self, db: &Db<K>, key: &K) -> Result<()>
+    where
+        K: Debug + for<'de> Deserialize<'de> + Serialize,
+    {
+        self.check_db(db.raw_db(), db.name())?;
+        db.batch_delete(&mut self.batch, key)
+    }
+
+    /// Adds a table batch to the transaction.
+    pub fn enlist<P>(&mut self, part: P) -> Result<()>
+    where
+        P: TransactionPart + 'a,
+    {
+        self.check_db(part.db(), part.db_name())?;
+        part.write_ops(&mut self.batch)?;
+        self.parts.push(Box::new(part));
+        Ok(())
+    }
+
+    /// Adds the insertion of a key / value in `db` to the transaction.
+    pub fn put<K, V>(&mut self, db: &Db<K>, key: &K, value: &V) -> Result<()>
+    where
+        K: Debug + for<'de> Deserialize<'de> + Serialize,
+        V: Serialize,
+    {
+        self.check_db(db.raw_db(), db.name())?;
+        db.batch_put(&mut self.batch, key, value)
+    }
+}
+
+impl<'a> Default for Transaction<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,3 +1,13 @@
 pub trait UpdateFrom<T> {
     fn update_from(self, old: Option<T>) -> T;
 }
+
+impl<T, F> UpdateFrom<T> for F
+where
+    F: FnOnce(Option<T>) -> T,
+{
+    #[inline]
+    fn update_from(self, old: Option<T>) -> T {
+        self(old)
+    }
+}